@@ -0,0 +1,353 @@
+// problems found while reading the project, they point at the ruby they're about and are printed
+// the way rustc prints its errors so the line can be jumped to
+
+use lib_ruby_parser::{ErrorLevel, Node};
+
+/// a file without any classes or modules
+pub const EMPTY_FILE: &str = "E0001";
+/// ruby that doesn't parse, what could still be parsed is read
+pub const SYNTAX_ERROR: &str = "E0002";
+/// a route to a controller that none of the files define, from `--lint`
+pub const MISSING_CONTROLLER: &str = "E0003";
+/// a route to an action its controller doesn't define, from `--lint`
+pub const MISSING_ACTION: &str = "E0004";
+/// a route to a private method, rails raises `ActionNotFound` for these, from `--lint`
+pub const ROUTED_PRIVATE_METHOD: &str = "E0005";
+/// a statement in a file, class or module body that is skipped
+pub const SKIPPED_STATEMENT: &str = "W0001";
+/// a route pointing at an action no controller defines
+pub const UNMATCHED_ROUTE: &str = "W0002";
+/// a class or module named by something other than a constant e.g. `class self::Dogs`, or a
+/// superclass that isn't a constant
+pub const UNSUPPORTED_CLASS: &str = "W0003";
+/// something ruby itself would warn about e.g. an ambiguous first argument
+pub const SYNTAX_WARNING: &str = "W0004";
+/// a public method of a routed controller that no route points at, from `--lint`
+pub const UNROUTED_METHOD: &str = "W0005";
+/// a route with the same verb and path as an earlier one which rails always matches first, from `--lint`
+pub const DUPLICATE_ROUTE: &str = "W0006";
+/// a call in `config/routes.rb` that couldn't be evaluated e.g. `member` outside of `resources`,
+/// the routes it would draw are left out
+pub const UNSUPPORTED_ROUTE: &str = "W0007";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// the file, class or module couldn't be read
+    Error,
+    /// something was skipped but everything else was read
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// byte offsets of the ruby a diagnostic is about, the line and column are counted from 1
+/// and are 0 until the diagnostic is located in its file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Span {
+    pub begin: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    /// the line the span starts on, shown under the message
+    pub source_line: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// e.g. `W0001`, one of the codes above
+    pub code: &'static str,
+    pub message: String,
+    /// the file it was found in, empty when it isn't about a file e.g. a route
+    pub path: String,
+    // boxed to keep the diagnostic small enough to return as an error
+    pub span: Option<Box<Span>>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            path: String::new(),
+            span: None,
+        }
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(code, message)
+        }
+    }
+
+    /// points the diagnostic at a node, from where the node starts to where it ends
+    pub fn at(self, node: &Node) -> Diagnostic {
+        let range = node.expression();
+        Diagnostic {
+            span: Some(Box::new(Span {
+                begin: range.begin_pos,
+                end: range.end_pos,
+                ..Span::default()
+            })),
+            ..self
+        }
+    }
+
+    /// a syntax error or warning from lib-ruby-parser
+    pub fn parser(diagnostic: &lib_ruby_parser::Diagnostic) -> Diagnostic {
+        let message = diagnostic.render_message();
+        let range = &diagnostic.range;
+        let span = Some(Box::new(Span {
+            begin: range.begin_pos,
+            end: range.end_pos,
+            ..Span::default()
+        }));
+        match diagnostic.level {
+            ErrorLevel::Error => Diagnostic {
+                span,
+                ..Diagnostic::error(SYNTAX_ERROR, message)
+            },
+            ErrorLevel::Warning => Diagnostic {
+                span,
+                ..Diagnostic::warning(SYNTAX_WARNING, message)
+            },
+        }
+    }
+
+    /// lib-ruby-parser's errors and warnings for a file, located in it
+    pub fn syntax_errors(
+        path: &str,
+        source: &[u8],
+        diagnostics: &[lib_ruby_parser::Diagnostic],
+    ) -> Vec<Diagnostic> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let mut diagnostic = Diagnostic::parser(diagnostic);
+                diagnostic.locate(path, source);
+                diagnostic
+            })
+            .collect()
+    }
+
+    /// Fills in the file along with the line and column the span starts at
+    pub fn locate(&mut self, path: &str, source: &[u8]) {
+        self.path = path.to_string();
+        if let Some(span) = &mut self.span {
+            let begin = span.begin.min(source.len());
+            let line_start = source[..begin]
+                .iter()
+                .rposition(|&c| c == b'\n')
+                .map_or(0, |i| i + 1);
+            let line_end = source[begin..]
+                .iter()
+                .position(|&c| c == b'\n')
+                .map_or(source.len(), |i| begin + i);
+
+            span.line = source[..begin].iter().filter(|&&c| c == b'\n').count() + 1;
+            span.column = String::from_utf8_lossy(&source[line_start..begin])
+                .chars()
+                .count()
+                + 1;
+            span.source_line = String::from_utf8_lossy(&source[line_start..line_end])
+                .trim_end_matches('\r')
+                .to_string();
+        }
+    }
+}
+
+/// ```text
+/// warning[W0001]: skipped a statement in the body of `DogsController`
+///  --> app/controllers/dogs_controller.rb:2:3
+///   |
+/// 2 |   @count = 1
+///   |   ^^^^^^^^^^
+/// ```
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+
+        let span = match &self.span {
+            Some(span) if span.line > 0 => span,
+            _ => {
+                if !self.path.is_empty() {
+                    write!(f, "\n --> {}", self.path)?;
+                }
+                return Ok(());
+            }
+        };
+
+        let gutter = " ".repeat(span.line.to_string().len());
+        // spans over several lines are underlined to the end of the first one
+        let length = span.source_line.chars().count() + 1 - span.column;
+        let underline = (span.end - span.begin).clamp(1, length.max(1));
+
+        write!(
+            f,
+            "\n{gutter}--> {path}:{line}:{column}\n{gutter} |\n{line} | {source}\n{gutter} | {indent}{carets}",
+            gutter = gutter,
+            path = self.path,
+            line = span.line,
+            column = span.column,
+            source = span.source_line,
+            indent = " ".repeat(span.column - 1),
+            carets = "^".repeat(underline)
+        )
+    }
+}
+
+fn files(count: usize) -> &'static str {
+    if count == 1 {
+        "file"
+    } else {
+        "files"
+    }
+}
+
+/// ```text
+/// skipped 1 file:
+///   app/controllers/broken_controller.rb
+/// partially read 1 file with syntax errors:
+///   app/controllers/dogs_controller.rb
+/// 3 errors, 1 warning
+/// ```
+/// `read` are the paths of the files that were parsed, files with errors that aren't among them were skipped
+pub fn summary(diagnostics: &[Diagnostic], read: &[&str]) -> String {
+    let mut skipped: Vec<&str> = Vec::new();
+    let mut partial: Vec<&str> = Vec::new();
+    for diagnostic in diagnostics {
+        let path = diagnostic.path.as_str();
+        if diagnostic.severity != Severity::Error || path.is_empty() {
+            continue;
+        }
+        let list = if !read.contains(&path) {
+            &mut skipped
+        } else if diagnostic.code == SYNTAX_ERROR {
+            &mut partial
+        } else {
+            continue;
+        };
+        if !list.contains(&path) {
+            list.push(path);
+        }
+    }
+
+    let mut summary = String::new();
+    if !skipped.is_empty() {
+        summary += &format!("skipped {} {}:\n", skipped.len(), files(skipped.len()));
+        for path in &skipped {
+            summary += &format!("  {}\n", path);
+        }
+    }
+    if !partial.is_empty() {
+        summary += &format!(
+            "partially read {} {} with syntax errors:\n",
+            partial.len(),
+            files(partial.len())
+        );
+        for path in &partial {
+            summary += &format!("  {}\n", path);
+        }
+    }
+    summary + &counts(diagnostics)
+}
+
+/// e.g. `3 errors, 1 warning`
+pub fn counts(diagnostics: &[Diagnostic]) -> String {
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    format!(
+        "{} error{}, {} warning{}",
+        errors,
+        if errors == 1 { "" } else { "s" },
+        warnings,
+        if warnings == 1 { "" } else { "s" }
+    )
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use lib_ruby_parser::{Node, Parser};
+    use pretty_assertions::assert_eq;
+
+    use super::{summary, Diagnostic, EMPTY_FILE, SKIPPED_STATEMENT, SYNTAX_ERROR};
+
+    #[test]
+    fn render() {
+        let input = "class DogsController < ApplicationController\n  attr_accessor :dog\n  @count = 1\nend\n";
+        let ast = Parser::new(input.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap();
+        let statement = match &ast {
+            Node::Class(class) => match class.body.as_deref() {
+                Some(Node::Begin(begin)) => &begin.statements[1],
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+
+        let mut diagnostic =
+            Diagnostic::warning(SKIPPED_STATEMENT, "unknown statement in class body").at(statement);
+        diagnostic.locate("app/controllers/dogs_controller.rb", input.as_bytes());
+        assert_eq!(
+            diagnostic.to_string(),
+            "warning[W0001]: unknown statement in class body
+ --> app/controllers/dogs_controller.rb:3:3
+  |
+3 |   @count = 1
+  |   ^^^^^^^^^^"
+        );
+
+        let mut diagnostic = Diagnostic::error(EMPTY_FILE, "no classes or modules found");
+        diagnostic.locate("app/controllers/empty.rb", b"");
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[E0001]: no classes or modules found
+ --> app/controllers/empty.rb"
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let input = "class DogsController < ApplicationController\n  def show(\nend\n";
+        let parsed = Parser::new(input.as_bytes(), Default::default()).do_parse();
+        let mut diagnostics = parsed
+            .diagnostics
+            .iter()
+            .map(Diagnostic::parser)
+            .collect::<Vec<Diagnostic>>();
+        assert!(!diagnostics.is_empty());
+        assert_eq!(diagnostics[0].code, SYNTAX_ERROR);
+        diagnostics[0].locate("app/controllers/dogs_controller.rb", input.as_bytes());
+        assert_eq!(diagnostics[0].span.as_ref().unwrap().line, 3);
+
+        let mut empty = Diagnostic::error(EMPTY_FILE, "no classes or modules found");
+        empty.locate("app/controllers/empty.rb", b"");
+        diagnostics.push(empty);
+        diagnostics.push(Diagnostic::warning(SKIPPED_STATEMENT, "skipped"));
+        assert_eq!(
+            summary(&diagnostics, &["app/controllers/dogs_controller.rb"]),
+            format!(
+                "skipped 1 file:
+  app/controllers/empty.rb
+partially read 1 file with syntax errors:
+  app/controllers/dogs_controller.rb
+{} errors, 1 warning",
+                diagnostics.len() - 1
+            )
+        );
+    }
+}
@@ -0,0 +1,361 @@
+// a JSON dump of everything worked out about the project for other tools to read, the layout is
+// versioned by `DUMP_VERSION` which changes whenever a field is renamed, removed or changes meaning
+
+use std::fs;
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    diagnostics::Diagnostic,
+    ivars::{Ivar, Value as IvarValue},
+    openapi::schema,
+    params::Param,
+    responses::Response,
+    routes::Request,
+    ruby_parser::{Action, Callback, Controller, Method, Module, RubyFile},
+    strong_params::Permitted,
+    types::Type,
+    Endpoint,
+};
+
+/// bumped when the layout of the dump changes in a way readers have to handle, adding fields doesn't
+pub const DUMP_VERSION: u32 = 1;
+
+fn types(types: &[(String, Type)]) -> Value {
+    Value::Object(
+        types
+            .iter()
+            .map(|(name, value)| (name.clone(), schema(value)))
+            .collect::<Map<String, Value>>(),
+    )
+}
+
+fn ivar(ivar: &Ivar) -> Value {
+    let value = match &ivar.value {
+        IvarValue::Record(model) => json!({ "kind": "record", "model": model }),
+        IvarValue::Collection(model) => json!({ "kind": "collection", "model": model }),
+        IvarValue::Type(value) => json!({ "kind": "value", "schema": schema(value) }),
+        IvarValue::Unknown => json!({ "kind": "unknown" }),
+    };
+    json!({ "name": ivar.name, "expression": ivar.expression, "value": value })
+}
+
+fn permitted(keys: &[(String, Permitted)]) -> Value {
+    Value::Object(
+        keys.iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    Permitted::Scalar => json!("scalar"),
+                    Permitted::Array => json!("array"),
+                    Permitted::Hash(nested) => permitted(nested),
+                    Permitted::AnyHash => json!("any_hash"),
+                };
+                (name.clone(), value)
+            })
+            .collect::<Map<String, Value>>(),
+    )
+}
+
+fn response(response: &Response) -> Value {
+    let mut value = match response {
+        Response::Json {
+            body,
+            serializer,
+            include,
+            view,
+            ..
+        } => json!({
+            "kind": "json",
+            "body": body,
+            "serializer": serializer,
+            "include": include,
+            "view": view
+        }),
+        Response::Head { .. } => json!({ "kind": "head" }),
+        Response::Redirect { location, .. } => {
+            json!({ "kind": "redirect", "location": location })
+        }
+        Response::Template { template, .. } => {
+            json!({ "kind": "template", "template": template })
+        }
+        Response::File { content_type, .. } => {
+            json!({ "kind": "file", "content_type": content_type })
+        }
+        Response::Other { method, .. } => json!({ "kind": "other", "method": method }),
+        Response::Call(method) => json!({ "kind": "call", "method": method }),
+    };
+    value["status"] = json!(response.status());
+    value["description"] = json!(response.to_string());
+    value
+}
+
+fn method(method: &Method) -> Value {
+    json!({
+        "name": method.name,
+        "private": method.private,
+        "params": method.params,
+        "param_types": types(&method.param_types),
+        "inputs": method.inputs.iter().map(|input| input.to_string()).collect::<Vec<String>>(),
+        "ivars": method.ivars.iter().map(ivar).collect::<Vec<Value>>(),
+        "responses": method.returns.iter().map(response).collect::<Vec<Value>>(),
+        "callbacks": method.callbacks,
+        "strong_params": method.strong_params.as_ref().map(|strong_params| json!({
+            "require": strong_params.require,
+            "permitted": permitted(&strong_params.permitted)
+        })),
+        "calls": method.calls
+    })
+}
+
+fn callback(kind: &str, callback: &Callback) -> Value {
+    json!({
+        "kind": kind,
+        "methods": callback.methods,
+        "only": callback.only,
+        "except": callback.except,
+        "if": callback.if_conditions,
+        "unless": callback.unless_conditions,
+        "prepend": callback.prepend
+    })
+}
+
+fn action(action: &Action) -> Value {
+    match action {
+        Action::BeforeAction(c) => callback("before_action", c),
+        Action::AroundAction(c) => callback("around_action", c),
+        Action::AfterAction(c) => callback("after_action", c),
+        Action::SkipAuthMethods(c) => callback("skip_auth_methods", c),
+        Action::SkipBeforeAction(c) => callback("skip_before_action", c),
+        Action::SkipAroundAction(c) => callback("skip_around_action", c),
+        Action::SkipAfterAction(c) => callback("skip_after_action", c),
+        Action::RescueFrom(rescue) => json!({
+            "kind": "rescue_from",
+            "exceptions": rescue.exceptions,
+            "with": rescue.with
+        }),
+    }
+}
+
+fn controller(controller: &Controller) -> Value {
+    json!({
+        "name": controller.name,
+        "parent": controller.parent,
+        "includes": controller.includes,
+        "actions": controller.actions.iter().map(action).collect::<Vec<Value>>(),
+        "methods": controller.methods.iter().map(method).collect::<Vec<Value>>()
+    })
+}
+
+fn module(module: &Module) -> Value {
+    json!({
+        "name": module.name,
+        "requires": module.requires,
+        "includes": module.includes,
+        "actions": module.actions.iter().map(action).collect::<Vec<Value>>(),
+        "methods": module.methods.iter().map(method).collect::<Vec<Value>>(),
+        "controllers": module.classes.iter().map(controller).collect::<Vec<Value>>()
+    })
+}
+
+fn file(file: &RubyFile) -> Value {
+    json!({
+        "path": file.path,
+        "requires": file.requires,
+        "modules": file.modules.iter().map(module).collect::<Vec<Value>>(),
+        "controllers": file.controllers.iter().map(controller).collect::<Vec<Value>>()
+    })
+}
+
+fn route(route: &Request) -> Value {
+    json!({
+        "method": route.method.lowercase(),
+        "uri": route.uri,
+        "name": route.prefix,
+        "controller": route.controller,
+        "action": route.action
+    })
+}
+
+fn param(param: &Param) -> Value {
+    json!({
+        "name": param.name,
+        "in": param.location.to_string(),
+        "schema": schema(&param.param_type)
+    })
+}
+
+/// routes are referred to by their index in `routes`
+fn endpoint(endpoint: &Endpoint, routes: &[Request]) -> Value {
+    json!({
+        "route": routes.iter().position(|route| route == &endpoint.request),
+        "controller": endpoint.controller,
+        "action": endpoint.method.name,
+        "params": endpoint.params.iter().map(param).collect::<Vec<Value>>(),
+        "view": endpoint.view.as_ref().map(schema),
+        "bodies": endpoint
+            .bodies
+            .iter()
+            .map(|(rendered, body)| json!({
+                "response": response(rendered),
+                "schema": schema(body)
+            }))
+            .collect::<Vec<Value>>()
+    })
+}
+
+fn diagnostic(diagnostic: &Diagnostic) -> Value {
+    json!({
+        "severity": diagnostic.severity.to_string(),
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+        "path": diagnostic.path,
+        "span": diagnostic.span.as_ref().map(|span| json!({
+            "begin": span.begin,
+            "end": span.end,
+            "line": span.line,
+            "column": span.column
+        }))
+    })
+}
+
+/// Builds the dump of the parsed files, the routes and the endpoints they were matched to
+pub fn build(
+    files: &[RubyFile],
+    routes: &[Request],
+    endpoints: &[Endpoint],
+    diagnostics: &[Diagnostic],
+) -> Value {
+    json!({
+        "version": DUMP_VERSION,
+        "files": files.iter().map(file).collect::<Vec<Value>>(),
+        "routes": routes.iter().map(route).collect::<Vec<Value>>(),
+        "endpoints": endpoints
+            .iter()
+            .map(|e| endpoint(e, routes))
+            .collect::<Vec<Value>>(),
+        "diagnostics": diagnostics.iter().map(diagnostic).collect::<Vec<Value>>()
+    })
+}
+
+/// Writes the dump as pretty printed JSON
+pub fn write(
+    files: &[RubyFile],
+    routes: &[Request],
+    endpoints: &[Endpoint],
+    diagnostics: &[Diagnostic],
+    output: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dump = build(files, routes, endpoints, diagnostics);
+    fs::write(output, serde_json::to_string_pretty(&dump)? + "\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod dump_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{build, DUMP_VERSION};
+    use crate::{
+        inflection::Inflections, match_routes, routes_rb::parse_routes_rb, ruby_parser::parse_file,
+        unmatched_routes,
+    };
+
+    #[test]
+    fn dump() {
+        let controller = "
+        class DogsController < ApplicationController
+            before_action :set_dog, only: [:show]
+
+            def show
+                render json: @dog, status: :ok
+            end
+
+            private
+
+            def set_dog
+                @dog = Dog.find(params[:id])
+            end
+        end
+        ";
+        let file = parse_file(
+            Parser::new(controller.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+        )
+        .unwrap();
+        let routes = parse_routes_rb(
+            "
+            Rails.application.routes.draw do
+                resources :dogs, only: [:index, :show]
+            end
+            ",
+            false,
+        )
+        .unwrap()
+        .0;
+        let files = vec![file];
+        let endpoints = match_routes(&files, &routes, &Inflections::default());
+        let dump = build(
+            &files,
+            &routes,
+            &endpoints,
+            &unmatched_routes(&routes, &endpoints),
+        );
+
+        assert_eq!(dump["version"], json!(DUMP_VERSION));
+        let controller = &dump["files"][0]["controllers"][0];
+        assert_eq!(controller["name"], json!("DogsController"));
+        assert_eq!(
+            controller["actions"][0],
+            json!({
+                "kind": "before_action",
+                "methods": ["set_dog"],
+                "only": ["show"],
+                "except": [],
+                "if": [],
+                "unless": [],
+                "prepend": false
+            })
+        );
+        assert_eq!(
+            controller["methods"][0]["responses"][0],
+            json!({
+                "kind": "json",
+                "body": "@dog",
+                "serializer": null,
+                "include": [],
+                "view": null,
+                "status": 200,
+                "description": "render json: @dog 200"
+            })
+        );
+        assert_eq!(
+            dump["routes"][1],
+            json!({
+                "method": "get",
+                "uri": "/dogs/:id",
+                "name": "dog",
+                "controller": "dogs",
+                "action": "show"
+            })
+        );
+        assert_eq!(dump["endpoints"][0]["route"], json!(1));
+        assert_eq!(
+            dump["endpoints"][0]["params"],
+            json!([{ "name": "id", "in": "path", "schema": { "type": "string" } }])
+        );
+        assert_eq!(
+            dump["diagnostics"],
+            json!([{
+                "severity": "warning",
+                "code": "W0002",
+                "message": "no action found for GET /dogs dogs#index",
+                "path": "",
+                "span": null
+            }])
+        );
+    }
+}
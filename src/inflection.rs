@@ -0,0 +1,354 @@
+// a small subset of ActiveSupport::Inflector, enough for the conventions rails uses to name things
+
+use lib_ruby_parser::{nodes, traverse::Visitor, Node, Parser};
+
+const IRREGULAR: [(&str, &str); 3] = [
+    ("person", "people"),
+    ("child", "children"),
+    ("mouse", "mice"),
+];
+
+const UNCOUNTABLE: [&str; 8] = [
+    "equipment",
+    "information",
+    "rice",
+    "money",
+    "species",
+    "series",
+    "fish",
+    "sheep",
+];
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+/// `dog` -> `dogs`, `category` -> `categories`, `box` -> `boxes`
+pub fn pluralize(word: &str) -> String {
+    if word.is_empty() || UNCOUNTABLE.contains(&word) {
+        return word.to_string();
+    }
+    for (singular, plural) in IRREGULAR.iter() {
+        if word == *plural {
+            return word.to_string();
+        }
+        if let Some(start) = word.strip_suffix(singular) {
+            return format!("{}{}", start, plural);
+        }
+    }
+
+    if let Some(start) = word.strip_suffix('y') {
+        if !start.ends_with(is_vowel) {
+            return format!("{}ies", start);
+        }
+    }
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|suffix| word.ends_with(suffix))
+    {
+        return format!("{}es", word);
+    }
+
+    format!("{}s", word)
+}
+
+/// `dogs` -> `dog`, `categories` -> `category`, `boxes` -> `box`
+pub fn singularize(word: &str) -> String {
+    if word.is_empty() || UNCOUNTABLE.contains(&word) {
+        return word.to_string();
+    }
+    for (singular, plural) in IRREGULAR.iter() {
+        if word == *singular {
+            return word.to_string();
+        }
+        if let Some(start) = word.strip_suffix(plural) {
+            return format!("{}{}", start, singular);
+        }
+    }
+
+    // ActiveSupport's singular rules, checked in the order it applies them
+    if let Some(start) = word.strip_suffix("quizzes") {
+        return format!("{}quiz", start);
+    }
+    for stem in ["alias", "status", "bus"].iter() {
+        if word.ends_with(stem) {
+            return word.to_string();
+        }
+        if let Some(start) = word.strip_suffix(&format!("{}es", stem)) {
+            return format!("{}{}", start, stem);
+        }
+    }
+    if word == "lice" {
+        return "louse".to_string();
+    }
+    for suffix in ["xes", "ches", "sses", "shes"].iter() {
+        if word.ends_with(suffix) {
+            return word[..word.len() - 2].to_string();
+        }
+    }
+    if let Some(start) = word.strip_suffix("movies") {
+        return format!("{}movie", start);
+    }
+    if let Some(start) = word.strip_suffix("ies") {
+        if !start.ends_with(|c| is_vowel(c) || c == 'y') || start.ends_with("qu") {
+            return format!("{}y", start);
+        }
+    }
+    if word.ends_with("ss") {
+        return word.to_string();
+    }
+    if let Some(start) = word.strip_suffix('s') {
+        return start.to_string();
+    }
+
+    word.to_string()
+}
+
+/// `dogs` and `people` are plural, `dog` and `sheep` aren't
+pub fn is_plural(word: &str) -> bool {
+    let singular = singularize(word);
+    singular != word && pluralize(&singular) == word
+}
+
+/// the acronyms an app declares in `config/initializers/inflections.rb`, they change how words are
+/// camelized and underscored e.g. `html_pages` is `HTMLPages` once `HTML` is an acronym
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Inflections {
+    acronyms: Vec<String>,
+}
+
+#[derive(Default)]
+struct AcronymSearch {
+    acronyms: Vec<String>,
+}
+
+// `inflect.acronym "HTML"` inside `ActiveSupport::Inflector.inflections(:en) do |inflect|`
+impl Visitor for AcronymSearch {
+    fn on_send(&mut self, send: &nodes::Send) {
+        if send.method_name == "acronym" {
+            if let [Node::Str(acronym)] = &send.args[..] {
+                self.acronyms.push(acronym.value.to_string_lossy());
+            }
+        }
+        if let Some(recv) = &send.recv {
+            self.visit(recv);
+        }
+        self.visit_all(&send.args);
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().collect::<String>() + &chars.as_str().to_lowercase(),
+        None => String::new(),
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl Inflections {
+    pub fn new(acronyms: Vec<String>) -> Inflections {
+        Inflections { acronyms }
+    }
+
+    /// Reads the acronyms out of `config/initializers/inflections.rb`
+    pub fn parse(input: &str) -> Inflections {
+        let mut search = AcronymSearch::default();
+        if let Some(ast) = Parser::new(input.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+        {
+            search.visit(&ast);
+        }
+        Inflections::new(search.acronyms)
+    }
+
+    // an acronym is looked up by its lowercase form, anything else is capitalized
+    fn camelize_part(&self, part: &str) -> String {
+        self.acronyms
+            .iter()
+            .find(|acronym| acronym.to_lowercase() == part)
+            .cloned()
+            .unwrap_or_else(|| capitalize(part))
+    }
+
+    /// `dog_toy` -> `DogToy`, `admin/dog` -> `Admin::Dog`, `html_pages` -> `HTMLPages` with `HTML` as an acronym
+    pub fn camelize(&self, word: &str) -> String {
+        let chars = word.chars().collect::<Vec<char>>();
+        let run = |start: usize, matches: fn(&char) -> bool| {
+            chars[start..].iter().take_while(|c| matches(c)).count()
+        };
+
+        // the lowercase start of the word, then every part after a `_` or `/`
+        let first = run(0, |c| c.is_ascii_lowercase() || c.is_ascii_digit());
+        let mut camelized = self.camelize_part(&chars[..first].iter().collect::<String>());
+        let mut i = first;
+        while i < chars.len() {
+            if chars[i] == '_' || chars[i] == '/' {
+                let length = run(i + 1, char::is_ascii_alphanumeric);
+                if chars[i] == '/' {
+                    camelized += "::";
+                }
+                camelized +=
+                    &self.camelize_part(&chars[i + 1..i + 1 + length].iter().collect::<String>());
+                i += 1 + length;
+            } else {
+                camelized.push(chars[i]);
+                i += 1;
+            }
+        }
+        camelized
+    }
+
+    /// `DogToy` -> `dog_toy`, `Admin::Dog` -> `admin/dog`, `HTMLPages` -> `html_pages`
+    pub fn underscore(&self, word: &str) -> String {
+        if !word.contains(|c: char| c.is_ascii_uppercase() || c == '-') && !word.contains("::") {
+            return word.to_string();
+        }
+        let chars = word.replace("::", "/").chars().collect::<Vec<char>>();
+
+        // acronyms are lowercased whole, with a `_` when they follow a letter or digit
+        let mut word = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let previous = if i > 0 { Some(chars[i - 1]) } else { None };
+            let acronym = self.acronyms.iter().find(|acronym| {
+                let acronym = acronym.chars().collect::<Vec<char>>();
+                let end = i + acronym.len();
+                end <= chars.len()
+                    && chars[i..end] == acronym[..]
+                    && previous.is_none_or(|p| p.is_ascii_alphanumeric() || !is_word(p))
+                    && chars.get(end).is_none_or(|c| !c.is_ascii_lowercase())
+            });
+            match acronym {
+                Some(acronym) => {
+                    if previous.is_some_and(|p| p.is_ascii_alphanumeric()) {
+                        word.push('_');
+                    }
+                    word.extend(acronym.to_lowercase().chars());
+                    i += acronym.chars().count();
+                }
+                None => {
+                    word.push(chars[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        // `DogToy` -> `Dog_Toy`, `HTMLPages` -> `HTML_Pages`
+        let mut underscored = String::new();
+        for (i, &c) in word.iter().enumerate() {
+            underscored.push(c);
+            let next = word.get(i + 1).copied().unwrap_or_default();
+            let after = word.get(i + 2).copied().unwrap_or_default();
+            if (c.is_ascii_uppercase() && next.is_ascii_uppercase() && after.is_ascii_lowercase())
+                || ((c.is_ascii_lowercase() || c.is_ascii_digit()) && next.is_ascii_uppercase())
+            {
+                underscored.push('_');
+            }
+        }
+        underscored.replace('-', "_").to_lowercase()
+    }
+
+    /// `api/v1/dogs` -> `Api::V1::DogsController`, the class rails loads for a route's controller
+    pub fn controller_class(&self, controller: &str) -> String {
+        format!("{}Controller", self.camelize(controller))
+    }
+}
+
+/// `dog_toy` -> `DogToy`, `admin/dog` -> `Admin::Dog`
+pub fn camelize(word: &str) -> String {
+    Inflections::default().camelize(word)
+}
+
+/// `DogToy` -> `dog_toy`, `Admin::Dog` -> `admin/dog`
+pub fn underscore(word: &str) -> String {
+    Inflections::default().underscore(word)
+}
+
+#[cfg(test)]
+mod inflection_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{camelize, is_plural, pluralize, singularize, underscore, Inflections};
+
+    #[test]
+    fn plurals() {
+        assert_eq!(pluralize("dog"), "dogs");
+        assert_eq!(pluralize("category"), "categories");
+        assert_eq!(pluralize("day"), "days");
+        assert_eq!(pluralize("box"), "boxes");
+        assert_eq!(pluralize("address"), "addresses");
+        assert_eq!(pluralize("person"), "people");
+        assert_eq!(pluralize("sales_person"), "sales_people");
+        assert_eq!(pluralize("sheep"), "sheep");
+    }
+
+    #[test]
+    fn singulars() {
+        assert_eq!(singularize("dogs"), "dog");
+        assert_eq!(singularize("categories"), "category");
+        assert_eq!(singularize("boxes"), "box");
+        assert_eq!(singularize("addresses"), "address");
+        assert_eq!(singularize("people"), "person");
+        assert_eq!(singularize("status"), "status");
+        assert_eq!(singularize("statuses"), "status");
+        assert_eq!(singularize("houses"), "house");
+        assert_eq!(singularize("courses"), "course");
+        assert_eq!(singularize("sizes"), "size");
+        assert_eq!(singularize("buses"), "bus");
+        assert_eq!(singularize("movies"), "movie");
+        assert_eq!(singularize("quizzes"), "quiz");
+        assert_eq!(singularize("aliases"), "alias");
+        assert_eq!(singularize("churches"), "church");
+        assert_eq!(singularize("soliloquies"), "soliloquy");
+        assert!(is_plural("houses"));
+        assert_eq!(singularize("dog"), "dog");
+    }
+
+    #[test]
+    fn cases() {
+        assert_eq!(camelize("dog_toy"), "DogToy");
+        assert_eq!(camelize("admin/dog"), "Admin::Dog");
+        assert_eq!(underscore("DogToy"), "dog_toy");
+        assert_eq!(underscore("Admin::Dog"), "admin/dog");
+    }
+
+    #[test]
+    fn acronyms() {
+        assert_eq!(underscore("HTMLPagesController"), "html_pages_controller");
+        assert_eq!(camelize("html_pages"), "HtmlPages");
+        assert_eq!(
+            underscore("Api::V1::Admin::DogFormsController"),
+            "api/v1/admin/dog_forms_controller"
+        );
+
+        let inflections = Inflections::parse(
+            "
+            ActiveSupport::Inflector.inflections(:en) do |inflect|
+                inflect.acronym 'HTML'
+                inflect.acronym 'RESTful'
+                inflect.irregular 'octopus', 'octopi'
+            end
+            ",
+        );
+        assert_eq!(
+            inflections,
+            Inflections::new(vec!["HTML".to_string(), "RESTful".to_string()])
+        );
+        assert_eq!(inflections.camelize("html_pages"), "HTMLPages");
+        assert_eq!(inflections.camelize("admin/html"), "Admin::HTML");
+        assert_eq!(inflections.camelize("restful_dogs"), "RESTfulDogs");
+        assert_eq!(inflections.underscore("HTMLPages"), "html_pages");
+        assert_eq!(inflections.underscore("MyHTMLPages"), "my_html_pages");
+        assert_eq!(inflections.underscore("RESTfulDogs"), "restful_dogs");
+        assert_eq!(underscore("RESTfulDogs"), "res_tful_dogs");
+        assert_eq!(
+            inflections.controller_class("api/v1/html_pages"),
+            "Api::V1::HTMLPagesController"
+        );
+    }
+}
@@ -0,0 +1,433 @@
+// instance variables an action assigns, views read them so knowing which model they hold lets the
+// attributes rendered from them be typed e.g. `@dog = Dog.find(params[:id])` then `json.name @dog.name`
+
+use lib_ruby_parser::{nodes, traverse::Visitor, Node};
+
+use crate::{
+    inflection::{camelize, is_plural, pluralize, singularize, underscore},
+    types::Type,
+    views::{value_type, Env},
+};
+
+/// what an instance variable holds
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// a single record e.g. `Dog.find(params[:id])`
+    Record(String),
+    /// a relation e.g. `Dog.where(good: true)` or `@owner.dogs`
+    Collection(String),
+    /// anything else whose type is known e.g. `@page = 1` or `Dog.count`
+    Type(Type),
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ivar {
+    pub name: String,
+    /// the ruby that was assigned e.g. `Dog.where(good: true)`
+    pub expression: String,
+    pub value: Value,
+}
+
+// query methods that load a single record
+const RECORD_METHODS: [&str; 19] = [
+    "find",
+    "find_by",
+    "find_by!",
+    "find_sole_by",
+    "sole",
+    "first",
+    "first!",
+    "last",
+    "last!",
+    "take",
+    "take!",
+    "second",
+    "third",
+    "new",
+    "build",
+    "create",
+    "create!",
+    "find_or_create_by",
+    "find_or_initialize_by",
+];
+
+// query methods that return another relation
+const COLLECTION_METHODS: [&str; 25] = [
+    "all",
+    "where",
+    "not",
+    "or",
+    "order",
+    "reorder",
+    "includes",
+    "preload",
+    "eager_load",
+    "joins",
+    "left_joins",
+    "left_outer_joins",
+    "references",
+    "limit",
+    "offset",
+    "page",
+    "per",
+    "distinct",
+    "select",
+    "group",
+    "having",
+    "unscoped",
+    "rewhere",
+    "none",
+    "reverse_order",
+];
+
+fn args(args: &[Node]) -> String {
+    args.iter().map(source).collect::<Vec<String>>().join(", ")
+}
+
+/// renders the ruby a node was parsed from, close enough to read and to follow method chains through
+pub fn source(node: &Node) -> String {
+    match node {
+        Node::Const(constant) => match &constant.scope {
+            Some(scope) => format!("{}::{}", source(scope), constant.name),
+            None => constant.name.clone(),
+        },
+        Node::Cbase(_) => String::new(),
+        Node::Ivar(ivar) => ivar.name.clone(),
+        Node::Lvar(lvar) => lvar.name.clone(),
+        Node::Self_(_) => "self".to_string(),
+        Node::Nil(_) => "nil".to_string(),
+        Node::True(_) => "true".to_string(),
+        Node::False(_) => "false".to_string(),
+        Node::Int(int) => int.value.clone(),
+        Node::Float(float) => float.value.clone(),
+        Node::Sym(sym) => format!(":{}", sym.name.to_string_lossy()),
+        Node::Str(str) => format!("{:?}", str.value.to_string_lossy()),
+        Node::Array(array) => format!("[{}]", args(&array.elements)),
+        Node::Hash(hash) => format!("{{ {} }}", args(&hash.pairs)),
+        Node::Kwargs(kwargs) => args(&kwargs.pairs),
+        Node::Pair(pair) => match &*pair.key {
+            Node::Sym(sym) => format!("{}: {}", sym.name.to_string_lossy(), source(&pair.value)),
+            key => format!("{} => {}", source(key), source(&pair.value)),
+        },
+        Node::Index(index) => format!("{}[{}]", source(&index.recv), args(&index.indexes)),
+        Node::Send(send) => {
+            let call = match &send.recv {
+                Some(recv) => format!("{}.{}", source(recv), send.method_name),
+                None => send.method_name.clone(),
+            };
+            if send.args.is_empty() {
+                call
+            } else {
+                format!("{}({})", call, args(&send.args))
+            }
+        }
+        Node::CSend(send) => {
+            let call = format!("{}&.{}", source(&send.recv), send.method_name);
+            if send.args.is_empty() {
+                call
+            } else {
+                format!("{}({})", call, args(&send.args))
+            }
+        }
+        Node::Block(block) => format!("{} {{ ... }}", source(&block.call)),
+        _ => "...".to_string(),
+    }
+}
+
+/// splits `@dog.toys.where(good: true)` into `@dog` and the methods `toys`, `where`
+fn chain(expression: &str) -> (String, Vec<String>) {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in expression.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '.' if depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current);
+
+    let root = parts.remove(0);
+    let methods = parts
+        .into_iter()
+        .map(|part| {
+            part.split(['(', '[', ' '])
+                .next()
+                .unwrap_or_default()
+                .trim_start_matches('&')
+                .to_string()
+        })
+        .collect();
+    (root, methods)
+}
+
+fn is_model(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == ':')
+}
+
+fn call(value: Value, method: &str, scopes: &[(String, String)]) -> Value {
+    match value {
+        Value::Collection(model) => match method {
+            "count" | "size" | "length" => Value::Type(Type::Integer),
+            "exists?" | "any?" | "empty?" | "none?" => Value::Type(Type::Boolean),
+            _ if RECORD_METHODS.contains(&method) => Value::Record(model),
+            _ if COLLECTION_METHODS.contains(&method) => Value::Collection(model),
+            _ if scopes
+                .iter()
+                .any(|(scoped, scope)| scoped == &model && scope == method) =>
+            {
+                Value::Collection(model)
+            }
+            _ => Value::Unknown,
+        },
+        // has_many associations are plural, singular methods could be an attribute or a belongs_to
+        Value::Record(model) => match method {
+            "reload" | "presence" | "itself" => Value::Record(model),
+            method if is_plural(method) => Value::Collection(camelize(&singularize(method))),
+            _ => Value::Unknown,
+        },
+        _ => Value::Unknown,
+    }
+}
+
+/// what an expression holds given the instance variables assigned before it and the
+/// `scope`s each model declares
+pub fn classify(expression: &str, known: &[Ivar], scopes: &[(String, String)]) -> Value {
+    let (root, methods) = chain(expression);
+    let root = root.trim_start_matches("::");
+    let mut value = if root.starts_with('@') {
+        known
+            .iter()
+            .rev()
+            .find(|ivar| ivar.name == root)
+            .map_or(Value::Unknown, |ivar| ivar.value.clone())
+    } else if is_model(root) && !methods.is_empty() {
+        Value::Collection(root.to_string())
+    } else {
+        Value::Unknown
+    };
+
+    for method in &methods {
+        value = call(value, method, scopes);
+    }
+    value
+}
+
+/// rewrites an instance variable holding a model to the model's name so the schema can find its table
+/// e.g. `@pets[].name` -> `dogs[].name` when `@pets = Dog.all`
+pub fn model_expression(ivars: &[Ivar], expression: &str) -> String {
+    let end = expression.find(['.', '[']).unwrap_or(expression.len());
+    let (root, rest) = expression.split_at(end);
+
+    match ivars.iter().rev().find(|ivar| ivar.name == root) {
+        Some(Ivar {
+            value: Value::Record(model),
+            ..
+        }) => format!("{}{}", underscore(model).replace('/', "_"), rest),
+        Some(Ivar {
+            value: Value::Collection(model),
+            ..
+        }) => format!(
+            "{}{}",
+            pluralize(&underscore(model).replace('/', "_")),
+            rest
+        ),
+        _ => expression.to_string(),
+    }
+}
+
+/// the type of an instance variable holding a plain value e.g. `@count` when `@count = Dog.count`
+pub fn ivar_type(ivars: &[Ivar], expression: &str) -> Option<Type> {
+    match ivars.iter().rev().find(|ivar| ivar.name == expression) {
+        Some(Ivar {
+            value: Value::Type(value),
+            ..
+        }) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Search {
+    ivars: Vec<Ivar>,
+}
+
+impl Search {
+    fn assign(&mut self, name: &str, value: &Node) {
+        let expression = source(value);
+        let value = match value {
+            Node::Str(_)
+            | Node::Dstr(_)
+            | Node::Sym(_)
+            | Node::Int(_)
+            | Node::Float(_)
+            | Node::True(_)
+            | Node::False(_)
+            | Node::Nil(_)
+            | Node::Array(_)
+            | Node::Hash(_) => Value::Type(value_type(value, &Env::new())),
+            _ => classify(&expression, &self.ivars, &[]),
+        };
+        self.ivars.push(Ivar {
+            name: name.to_string(),
+            expression,
+            value,
+        });
+    }
+}
+
+impl Visitor for Search {
+    fn on_ivasgn(&mut self, ivasgn: &nodes::Ivasgn) {
+        if let Some(value) = &ivasgn.value {
+            self.visit(value);
+            self.assign(&ivasgn.name, value);
+        }
+    }
+
+    // `@dog ||= Dog.find(params[:id])`
+    fn on_or_asgn(&mut self, or_asgn: &nodes::OrAsgn) {
+        self.visit(&or_asgn.value);
+        if let Node::Ivasgn(ivasgn) = &*or_asgn.recv {
+            self.assign(&ivasgn.name, &or_asgn.value);
+        }
+    }
+}
+
+/// Every instance variable a method body assigns in the order they're assigned
+pub fn search_for_ivars(body: &Node) -> Vec<Ivar> {
+    let mut search = Search::default();
+    search.visit(body);
+    search.ivars
+}
+
+/// Works out the instance variables that were chained off ones assigned earlier e.g. by a callback,
+/// or off a model's scopes e.g. `Dog.good.order(:name)` once `scopes` has `good` for `Dog`
+pub fn resolve_ivars(ivars: Vec<Ivar>, scopes: &[(String, String)]) -> Vec<Ivar> {
+    let mut resolved: Vec<Ivar> = Vec::new();
+    for mut ivar in ivars {
+        if ivar.value == Value::Unknown {
+            ivar.value = classify(&ivar.expression, &resolved, scopes);
+        }
+        resolved.push(ivar);
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod ivars_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::{ivar_type, model_expression, resolve_ivars, search_for_ivars, Ivar, Value};
+    use crate::types::Type;
+
+    fn helper(input: &str) -> Vec<Ivar> {
+        search_for_ivars(
+            &Parser::new(input.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+        )
+    }
+
+    fn ivar(name: &str, expression: &str, value: Value) -> Ivar {
+        Ivar {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn assignments() {
+        let input = "
+        @dog = Dog.find(params[:id])
+        @dogs = Dog.includes(:owner).where(good: true).order(:name)
+        @count = @dogs.count
+        @toys = @dog.toys
+        @owner ||= Admin::Owner.find_by(email: params[:email])
+        @page = 1
+        @title = helpers.title
+        ";
+
+        assert_eq!(
+            helper(input),
+            vec![
+                ivar(
+                    "@dog",
+                    "Dog.find(params[:id])",
+                    Value::Record("Dog".to_string())
+                ),
+                ivar(
+                    "@dogs",
+                    "Dog.includes(:owner).where(good: true).order(:name)",
+                    Value::Collection("Dog".to_string())
+                ),
+                ivar("@count", "@dogs.count", Value::Type(Type::Integer)),
+                ivar("@toys", "@dog.toys", Value::Collection("Toy".to_string())),
+                ivar(
+                    "@owner",
+                    "Admin::Owner.find_by(email: params[:email])",
+                    Value::Record("Admin::Owner".to_string())
+                ),
+                ivar("@page", "1", Value::Type(Type::Integer)),
+                ivar("@title", "helpers.title", Value::Unknown),
+            ]
+        );
+    }
+
+    #[test]
+    fn callbacks() {
+        let mut ivars = helper("@dog = Dog.find(params[:id])");
+        ivars.extend(helper("@friends = @dog.friends.limit(5)"));
+
+        assert_eq!(
+            resolve_ivars(ivars, &[])[1].value,
+            Value::Collection("Friend".to_string())
+        );
+    }
+
+    #[test]
+    fn scopes() {
+        let ivars = helper(
+            "@dogs = Dog.good.order(:name)
+            @count = @dogs.count",
+        );
+        let scopes = vec![("Dog".to_string(), "good".to_string())];
+        assert_eq!(ivars[0].value, Value::Unknown);
+        assert_eq!(
+            resolve_ivars(ivars, &scopes)
+                .into_iter()
+                .map(|ivar| ivar.value)
+                .collect::<Vec<Value>>(),
+            vec![
+                Value::Collection("Dog".to_string()),
+                Value::Type(Type::Integer)
+            ]
+        );
+    }
+
+    #[test]
+    fn model_expressions() {
+        let ivars = helper(
+            "@pet = Dog.find(params[:id])
+            @pets = Dog.all",
+        );
+
+        assert_eq!(model_expression(&ivars, "@pet.name"), "dog.name");
+        assert_eq!(model_expression(&ivars, "@pets[].name"), "dogs[].name");
+        assert_eq!(model_expression(&ivars, "@owner.name"), "@owner.name");
+        assert_eq!(
+            ivar_type(&helper("@count = Dog.where(good: true).count"), "@count"),
+            Some(Type::Integer)
+        );
+    }
+}
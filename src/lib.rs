@@ -1,14 +1,125 @@
-use std::fs;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
+use diagnostics::{Diagnostic, Severity};
+use inflection::Inflections;
+use ivars::{ivar_type, model_expression, resolve_ivars};
 use lib_ruby_parser::Parser;
-use routes::{parse_routes, Request};
-use ruby_parser::{parse_file, Method, RubyFile};
+use models::Models;
+use params::{classify_params, Param, ParamLocation};
+use responses::Response;
+use routes::{parse_routes, Request, RequestMethod};
+use routes_rb::parse_routes_rb;
+use ruby_parser::{parse_file, recover, Method, RubyFile};
+use schema::parse_schema;
+use serializers::Serializers;
+use types::Type;
+use views::Views;
 use walkdir::{DirEntry, WalkDir};
 
+pub mod diagnostics;
+pub mod dump;
+pub mod inflection;
+pub mod ivars;
+pub mod lint;
+pub mod models;
+pub mod openapi;
 pub mod params;
 mod parser_parser;
+pub mod responses;
 pub mod routes;
+pub mod routes_rb;
 mod ruby_parser;
+pub mod schema;
+pub mod serializers;
+pub mod strong_params;
+pub mod types;
+pub mod typescript;
+pub mod views;
+
+/// A route from the routes file along with the controller method that handles it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub request: Request,
+    pub controller: String,
+    pub method: Method,
+    /// the method's params along with where the route expects them
+    pub params: Vec<Param>,
+    /// the JSON rendered by the action's view, when it has one
+    pub view: Option<Type>,
+    /// the JSON each `render json:` sends when it can be worked out e.g. through a serializer
+    pub bodies: Vec<(Response, Type)>,
+}
+
+impl Endpoint {
+    /// the JSON a response sends, when it's known
+    pub fn body(&self, response: &Response) -> Option<&Type> {
+        self.bodies
+            .iter()
+            .find(|(rendered, _)| rendered == response)
+            .map(|(_, body)| body)
+    }
+
+    /// the JSON sent back when the action succeeds, from its view or a successful `render json:`
+    pub fn success_body(&self) -> Option<&Type> {
+        self.view.as_ref().or_else(|| {
+            self.bodies
+                .iter()
+                .find(|(response, _)| response.status().is_none_or(|status| status < 300))
+                .map(|(_, body)| body)
+        })
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} ", self.request, self.method.name)?;
+
+        for location in &[
+            ParamLocation::Path,
+            ParamLocation::Query,
+            ParamLocation::Body,
+        ] {
+            let names = self
+                .params
+                .iter()
+                .filter(|param| &param.location == location)
+                .map(|param| param.name.clone())
+                .collect::<Vec<String>>();
+            if !names.is_empty() {
+                write!(f, "{}: {} ", location, names.join(","))?;
+            }
+        }
+
+        if !self.method.inputs.is_empty() {
+            let inputs = self
+                .method
+                .inputs
+                .iter()
+                .map(|input| input.to_string())
+                .collect::<Vec<String>>();
+            write!(f, "inputs: {} ", inputs.join(","))?;
+        }
+
+        if !self.method.returns.is_empty() {
+            let returns = self
+                .method
+                .returns
+                .iter()
+                .map(|response| response.to_string())
+                .collect::<Vec<String>>();
+            write!(f, "responses: {} ", returns.join(","))?;
+        }
+
+        if !self.method.callbacks.is_empty() {
+            write!(f, "callbacks: {} ", self.method.callbacks.join(","))?;
+        }
+
+        Ok(())
+    }
+}
 
 fn is_hidden(entry: &DirEntry) -> bool {
     entry
@@ -18,9 +129,12 @@ fn is_hidden(entry: &DirEntry) -> bool {
         .unwrap_or(false)
 }
 
-pub fn parse_files(path: &str) -> Result<Vec<RubyFile>, Box<dyn std::error::Error>> {
+/// Parses every ruby file under `path`, along with the diagnostics for each file that couldn't be read.
+/// The syntax errors and warnings for files that were read are kept on the file
+pub fn parse_files(
+    path: &str,
+) -> Result<(Vec<RubyFile>, Vec<Diagnostic>), Box<dyn std::error::Error>> {
     let mut errors = Vec::new();
-    let mut file_count = 0;
     let mut results = Vec::new();
     for entry in WalkDir::new(path)
         .into_iter()
@@ -29,140 +143,355 @@ pub fn parse_files(path: &str) -> Result<Vec<RubyFile>, Box<dyn std::error::Erro
     {
         let f = entry.path();
         if f.is_file() && f.display().to_string().ends_with(".rb") {
-            let parser = Parser::new(&fs::read(entry.path())?, Default::default());
-            let result = parse_file(parser.do_parse().ast.unwrap());
-            if result.is_ok() {
-                results.push(result.unwrap());
-            } else {
-                errors.push((f.display().to_string(), result.err()));
+            let path = f.display().to_string();
+            let source = fs::read(entry.path())?;
+            let parsed = Parser::new(&source, Default::default()).do_parse();
+            let mut found = parsed
+                .diagnostics
+                .iter()
+                .map(Diagnostic::parser)
+                .collect::<Vec<Diagnostic>>();
+            let broken = found.iter().any(|d| d.severity == Severity::Error);
+
+            // files with syntax errors are read without the lines the errors are on
+            let syntax_errors = &parsed.diagnostics;
+            let ast = parsed.ast.or_else(|| recover(&source, syntax_errors));
+            let file = match ast.map(parse_file) {
+                Some(Ok(mut file)) => {
+                    found.append(&mut file.diagnostics);
+                    Some(file)
+                }
+                Some(Err(diagnostic)) => {
+                    // a broken file being empty is down to the syntax error
+                    if !broken || diagnostic.code != diagnostics::EMPTY_FILE {
+                        found.push(diagnostic);
+                    }
+                    None
+                }
+                // nothing in the file or nothing that could be parsed
+                None => {
+                    if !broken {
+                        found.push(Diagnostic::error(
+                            diagnostics::EMPTY_FILE,
+                            "no classes or modules found",
+                        ));
+                    }
+                    None
+                }
+            };
+            for diagnostic in &mut found {
+                diagnostic.locate(&path, &source);
+            }
+            match file {
+                Some(file) => results.push(RubyFile {
+                    path,
+                    diagnostics: found,
+                    ..file
+                }),
+                None => errors.append(&mut found),
             }
         }
-        file_count += 1;
     }
 
-    if !errors.is_empty() {
-        println!(
-            "Got {} errors out of a total of {} files",
-            errors.len(),
-            file_count
-        );
-
-        println!("{:?}", errors);
-    }
+    Ok((results, errors))
+}
 
-    Ok(results)
+/// routes pointing at actions none of the parsed controllers define
+pub fn unmatched_routes(routes: &[Request], endpoints: &[Endpoint]) -> Vec<Diagnostic> {
+    routes
+        .iter()
+        .filter(|route| !endpoints.iter().any(|endpoint| &endpoint.request == *route))
+        .map(|route| {
+            Diagnostic::warning(
+                diagnostics::UNMATCHED_ROUTE,
+                format!(
+                    "no action found for {} {}#{}",
+                    route, route.controller, route.action
+                ),
+            )
+        })
+        .collect()
 }
 
-fn get_name(module: &str, controller: &str) -> String {
-    let mut name = module.to_lowercase();
-
-    if !name.is_empty() {
-        name += "/";
-    }
-
-    let mut first = true;
-    let controller_name: Vec<&str> = controller.split("::").collect();
-    if controller_name.len() == 2 {
-        name += &(controller_name[0].to_lowercase() + "/");
-        for c in controller_name[1].replace("Controller", "").chars() {
-            if first {
-                name += &c.to_lowercase().to_string();
-                first = !first;
-            } else if c.is_uppercase() {
-                name += "_";
-                name += &c.to_lowercase().to_string();
-            } else {
-                name += &c.to_string();
-            }
-        }
-    } else {
-        for c in controller_name[0].replace("Controller", "").chars() {
-            if first {
-                name += &c.to_lowercase().to_string();
-                first = !first;
-            } else if c.is_uppercase() {
-                name += "_";
-                name += &c.to_lowercase().to_string();
-            } else {
-                name += &c.to_string();
-            }
-        }
+/// the full name of a controller e.g. `Api::V1::DogsController` for a `DogsController` nested in
+/// `module Api` and `module V1`, which is what a route's controller camelizes to
+pub(crate) fn class_name(module: &str, controller: &str) -> String {
+    let controller = controller.trim_start_matches("::");
+    match module {
+        "" => controller.to_string(),
+        module => format!("{}::{}", module.replace('.', "::"), controller),
     }
-
-    name
 }
 
 fn search_in_routes(
     name: &str,
-    routes: &Vec<Request>,
-    methods: &Vec<Method>,
-) -> Result<(), String> {
-    let mut found = false;
+    controller: &str,
+    routes: &[Request],
+    methods: &[Method],
+    inflections: &Inflections,
+    endpoints: &mut Vec<Endpoint>,
+) {
     for route in routes {
-        if route.controller == name {
+        if inflections.controller_class(&route.controller) == name {
             for method in methods {
                 if method.name == route.action {
-                    println!("{} {}", route, method);
-                    found = true;
+                    endpoints.push(Endpoint {
+                        request: route.clone(),
+                        controller: controller.to_string(),
+                        method: method.clone(),
+                        params: classify_params(route, method),
+                        view: None,
+                        bodies: Vec::new(),
+                    });
                 }
             }
         }
     }
-    if !found {
-        Err("unable to find method on controller".to_string())
-    } else {
-        Ok(())
-    }
 }
 
-pub fn compute(controller_path: &str, routes_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // TODO: abstract these out so unit tests can written... ah more work but will help
-    let routes_file = fs::read_to_string(routes_file);
-    if let Err(e) = routes_file{
-        Err(format!("Error in reading routes file: {}", e.to_string()))?
-    }else{
-        let files = parse_files(controller_path)?;
-        let routes = parse_routes(&routes_file.unwrap())?;
-    
-        for file in &files {
-            for module in &file.modules {
-                for controller in &module.classes {
-                    if search_in_routes(
-                        &get_name(&module.name, &controller.name),
-                        &routes,
-                        &controller.methods,
-                    )
-                    .is_err()
-                    {
-                        println!(
-                            "unable to find controller for {} {} -- {}",
-                            module.name,
-                            controller.name,
-                            &get_name(&module.name, &controller.name)
-                        );
-                    }
-                }
-            }
-    
-            for controller in &file.controllers {
-                if search_in_routes(
-                    &get_name("", &controller.name),
-                    &routes,
+/// Pairs every route with the controller method it points at, `--lint` reports the routes that
+/// can't be paired
+pub fn match_routes(
+    files: &[RubyFile],
+    routes: &[Request],
+    inflections: &Inflections,
+) -> Vec<Endpoint> {
+    let mut endpoints = Vec::new();
+    for file in files {
+        for module in &file.modules {
+            for controller in &module.classes {
+                let name = class_name(&module.name, &controller.name);
+                search_in_routes(
+                    &name,
+                    &controller.name,
+                    routes,
                     &controller.methods,
-                )
-                .is_err()
-                {
-                    println!(
-                        "unable to find controller for {} {} -- {}",
-                        "",
-                        controller.name,
-                        &get_name("", &controller.name)
-                    );
-                }
+                    inflections,
+                    &mut endpoints,
+                );
             }
         }
+
+        for controller in &file.controllers {
+            let name = class_name("", &controller.name);
+            search_in_routes(
+                &name,
+                &controller.name,
+                routes,
+                &controller.methods,
+                inflections,
+                &mut endpoints,
+            );
+        }
+    }
+
+    endpoints
+}
+
+/// exit code for `--lint` finding errors, failing to read the project exits with 1
+pub const LINT_FAILED: i32 = 2;
+
+/// Where to read the rails project from and which outputs to generate
+#[derive(Debug, Default)]
+pub struct Options {
+    pub controllers: String,
+    pub routes: String,
+    pub openapi: Option<String>,
+    pub typescript: Option<String>,
+    /// file to write a JSON dump of the parsed files, routes and endpoints to
+    pub dump: Option<String>,
+    /// check the routes and controllers agree instead of listing the endpoints
+    pub lint: bool,
+    pub views: Option<String>,
+    pub schema: Option<String>,
+    /// e.g. `app/models`
+    pub models: Option<String>,
+    /// e.g. `config/initializers/inflections.rb`, found next to `config/routes.rb` when not given
+    pub inflections: Option<String>,
+    /// e.g. `app/serializers` and `app/blueprints`
+    pub serializers: Vec<String>,
+}
+
+/// the given inflections file or the one next to `config/routes.rb` when it has one
+fn inflections_file(options: &Options) -> Option<PathBuf> {
+    match &options.inflections {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Path::new(&options.routes)
+            .parent()
+            .map(|config| config.join("initializers").join("inflections.rb"))
+            .filter(|path| path.is_file()),
+    }
+}
+
+/// whether the `config/application.rb` next to `config/routes.rb` makes the app api only
+fn api_only(options: &Options) -> bool {
+    Path::new(&options.routes)
+        .parent()
+        .and_then(|config| fs::read_to_string(config.join("application.rb")).ok())
+        .is_some_and(|application| routes_rb::api_only(&application))
+}
+
+/// the given views or the `app/views` next to `app/controllers` when there is one
+fn views_dir(options: &Options) -> Option<PathBuf> {
+    match &options.views {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Path::new(&options.controllers)
+            .parent()
+            .map(|app| app.join("views"))
+            .filter(|path| path.is_dir()),
+    }
+}
+
+/// Parses the controllers and routes file then writes each of the requested outputs,
+/// when no output is given the matched routes are printed instead. Returns the exit code, with
+/// `--lint` it's `LINT_FAILED` when any of the checks found an error
+pub fn compute(options: &Options) -> Result<i32, Box<dyn std::error::Error>> {
+    // TODO: abstract these out so unit tests can written... ah more work but will help
+    let routes_file = fs::read_to_string(&options.routes)
+        .map_err(|e| format!("Error in reading routes file: {}", e))?;
+    let (files, mut diagnostics) = parse_files(&options.controllers)?;
+    let defined = parser_parser::merge_reopened(files);
+    let files = defined
+        .iter()
+        .cloned()
+        .map(|file| parser_parser::parse(file, &defined))
+        .collect::<Result<Vec<RubyFile>, String>>()?;
+    let routes = if options.routes.ends_with(".rb") {
+        let (routes, mut skipped) = parse_routes_rb(&routes_file, api_only(options))?;
+        for diagnostic in &mut skipped {
+            diagnostic.locate(&options.routes, routes_file.as_bytes());
+        }
+        diagnostics.append(&mut skipped);
+        routes
+    } else {
+        parse_routes(&routes_file)?
+    };
+    let inflections = match inflections_file(options) {
+        Some(path) => Inflections::parse(
+            &fs::read_to_string(&path)
+                .map_err(|e| format!("Error in reading inflections file: {}", e))?,
+        ),
+        None => Inflections::default(),
+    };
+    let mut endpoints = match_routes(&files, &routes, &inflections);
+
+    let mut models = match &options.models {
+        Some(models) => Models::parse_dir(models)?,
+        None => Models::default(),
+    };
+    diagnostics.append(&mut models.diagnostics);
+    let scopes = models.scopes();
+    for endpoint in &mut endpoints {
+        let ivars = std::mem::take(&mut endpoint.method.ivars);
+        endpoint.method.ivars = resolve_ivars(ivars, &scopes);
+        // presence is only enforced on create, updates can leave attributes out
+        models.refine_params(
+            &mut endpoint.params,
+            endpoint.request.method == RequestMethod::POST,
+        );
+    }
+
+    if let Some(views) = &options.views {
+        let mut views = Views::parse_dir(views)?;
+        diagnostics.append(&mut views.diagnostics);
+        for endpoint in &mut endpoints {
+            endpoint.view =
+                views.for_action(&endpoint.request.controller, &endpoint.request.action);
+        }
+    }
+
+    if !options.serializers.is_empty() {
+        let mut serializers = Serializers::default();
+        for path in &options.serializers {
+            serializers.merge(Serializers::parse_dir(path)?);
+        }
+        diagnostics.append(&mut serializers.diagnostics);
+        for endpoint in &mut endpoints {
+            endpoint.bodies = endpoint
+                .method
+                .returns
+                .iter()
+                .filter_map(|response| {
+                    let body = serializers.for_response(
+                        response,
+                        &endpoint.method.ivars,
+                        &inflections.camelize(&endpoint.request.controller),
+                    )?;
+                    Some((response.clone(), body))
+                })
+                .collect();
+        }
+    }
+
+    let schema = match &options.schema {
+        Some(schema) => {
+            let schema = fs::read_to_string(schema)
+                .map_err(|e| format!("Error in reading schema file: {}", e))?;
+            Some(parse_schema(&schema)?)
+        }
+        None => None,
+    };
+    for endpoint in &mut endpoints {
+        // the instance variables the action assigns say which model a view is reading from
+        let ivars = &endpoint.method.ivars;
+        let lookup = |expression: &str| {
+            ivar_type(ivars, expression)
+                .or_else(|| models.type_of(schema.as_ref(), &model_expression(ivars, expression)))
+        };
+        endpoint.view = endpoint
+            .view
+            .take()
+            .map(|view| view.resolve_expressions(&lookup));
+        for (_, body) in &mut endpoint.bodies {
+            *body = std::mem::replace(body, Type::Unknown).resolve_expressions(&lookup);
+        }
+    }
+
+    if let Some(output) = &options.openapi {
+        openapi::write(&endpoints, output)?;
+    }
+
+    if let Some(output) = &options.typescript {
+        fs::write(output, typescript::generate(&endpoints))?;
+    }
+
+    for file in &files {
+        diagnostics.extend(file.diagnostics.iter().cloned());
+    }
+    // the lint reports unmatched routes in more detail
+    let lint = if options.lint {
+        let templates = views_dir(options)
+            .map(|path| lint::templates(&path.to_string_lossy()))
+            .unwrap_or_default();
+        lint::lint(&defined, &files, &routes, &templates, &inflections)
+    } else {
+        diagnostics.extend(unmatched_routes(&routes, &endpoints));
+        Vec::new()
+    };
+    diagnostics.extend(lint.iter().cloned());
+    for diagnostic in &diagnostics {
+        eprintln!("{}\n", diagnostic);
+    }
+    if !diagnostics.is_empty() {
+        let read = files.iter().map(|f| f.path.as_str()).collect::<Vec<&str>>();
+        eprintln!("{}", diagnostics::summary(&diagnostics, &read));
+    }
+
+    if let Some(output) = &options.dump {
+        dump::write(&files, &routes, &endpoints, &diagnostics, output)?;
+    }
+
+    if options.lint {
+        // the total counts everything reported, not just the checks
+        println!("{}", lint::table(&diagnostics));
+        if lint.iter().any(|d| d.severity == Severity::Error) {
+            return Ok(LINT_FAILED);
+        }
+    } else if options.openapi.is_none() && options.typescript.is_none() && options.dump.is_none() {
+        for endpoint in &endpoints {
+            println!("{}", endpoint);
+        }
     }
-    
 
-    Ok(())
+    Ok(0)
 }
@@ -0,0 +1,422 @@
+// `--lint` checks the routes and controllers agree with each other, every route should reach a
+// public action and every public action of a routed controller should have a route
+
+use std::path::Path;
+
+use walkdir::WalkDir;
+
+use crate::{
+    class_name,
+    diagnostics::{
+        counts, Diagnostic, DUPLICATE_ROUTE, MISSING_ACTION, MISSING_CONTROLLER,
+        ROUTED_PRIVATE_METHOD, UNROUTED_METHOD,
+    },
+    inflection::Inflections,
+    routes::Request,
+    ruby_parser::{Controller, RubyFile},
+};
+
+/// the checks in the order they're listed in the summary
+const CHECKS: [(&str, &str); 5] = [
+    (MISSING_CONTROLLER, "routes to missing controllers"),
+    (MISSING_ACTION, "routes to missing actions"),
+    (ROUTED_PRIVATE_METHOD, "routed private methods"),
+    (UNROUTED_METHOD, "public methods without a route"),
+    (DUPLICATE_ROUTE, "duplicate routes"),
+];
+
+/// controllers rails and gems draw routes to, they aren't in `app/controllers` unless overridden
+const FRAMEWORK_CONTROLLERS: [&str; 5] = [
+    "rails/",
+    "active_storage/",
+    "action_mailbox/",
+    "action_text/",
+    "devise/",
+];
+
+/// every controller with the file it's in and its full name e.g. `Api::V1::DogsController`
+fn controllers(files: &[RubyFile]) -> Vec<(String, &str, &Controller)> {
+    let mut controllers = Vec::new();
+    for file in files {
+        for module in &file.modules {
+            for controller in &module.classes {
+                let name = class_name(&module.name, &controller.name);
+                controllers.push((name, file.path.as_str(), controller));
+            }
+        }
+        for controller in &file.controllers {
+            controllers.push((
+                class_name("", &controller.name),
+                file.path.as_str(),
+                controller,
+            ));
+        }
+    }
+    controllers
+}
+
+/// The `controller/action` name of every template under `app/views`, rails renders them for
+/// actions without a method. Partials are left out
+pub fn templates(path: &str) -> Vec<String> {
+    let root = Path::new(path);
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry
+                .path()
+                .strip_prefix(root)
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let (dir, file) = relative.rsplit_once('/')?;
+            if file.starts_with('_') {
+                return None;
+            }
+            // `index.html.erb` and `index.html+phone.erb` are both `index`
+            let action = file.split('.').next()?;
+            Some(format!("{}/{}", dir, action))
+        })
+        .collect()
+}
+
+/// Checks the routes against the controllers. `resolved` are the controllers with the methods they
+/// inherit and include, `defined` are as they were written and are used to only report the
+/// methods a controller defines itself. Controllers no route points at are taken to be base
+/// classes and their methods aren't reported, `templates` are the actions rendered without a method
+pub fn lint(
+    defined: &[RubyFile],
+    resolved: &[RubyFile],
+    routes: &[Request],
+    templates: &[String],
+    inflections: &Inflections,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let resolved = controllers(resolved);
+
+    for (i, route) in routes.iter().enumerate() {
+        if let Some(first) = routes[..i]
+            .iter()
+            .find(|r| r.method == route.method && r.uri == route.uri)
+        {
+            diagnostics.push(Diagnostic::warning(
+                DUPLICATE_ROUTE,
+                format!(
+                    "{} {}#{} is never reached, {}#{} is matched first",
+                    route, route.controller, route.action, first.controller, first.action
+                ),
+            ));
+        }
+
+        let (path, controller) = match resolved
+            .iter()
+            .find(|(name, ..)| name == &inflections.controller_class(&route.controller))
+        {
+            Some((_, path, controller)) => (path, controller),
+            None if FRAMEWORK_CONTROLLERS
+                .iter()
+                .any(|prefix| route.controller.starts_with(prefix)) =>
+            {
+                continue
+            }
+            None => {
+                diagnostics.push(Diagnostic::error(
+                    MISSING_CONTROLLER,
+                    format!(
+                        "no controller found for {} {}#{}",
+                        route, route.controller, route.action
+                    ),
+                ));
+                continue;
+            }
+        };
+        let diagnostic = match controller.methods.iter().find(|m| m.name == route.action) {
+            Some(method) if method.private => Diagnostic::error(
+                ROUTED_PRIVATE_METHOD,
+                format!(
+                    "{} {}#{} points at a private method",
+                    route, controller.name, route.action
+                ),
+            ),
+            Some(_) => continue,
+            None if templates.contains(&format!("{}/{}", route.controller, route.action)) => {
+                continue
+            }
+            None => Diagnostic::error(
+                MISSING_ACTION,
+                format!(
+                    "{} has no action `{}` for {}",
+                    controller.name, route.action, route
+                ),
+            ),
+        };
+        diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            ..diagnostic
+        });
+    }
+
+    for (name, path, controller) in controllers(defined) {
+        let routed = routes
+            .iter()
+            .filter(|route| inflections.controller_class(&route.controller) == name)
+            .collect::<Vec<&Request>>();
+        if routed.is_empty() {
+            continue;
+        }
+        for method in &controller.methods {
+            if !method.private && !routed.iter().any(|route| route.action == method.name) {
+                diagnostics.push(Diagnostic {
+                    path: path.to_string(),
+                    ..Diagnostic::warning(
+                        UNROUTED_METHOD,
+                        format!(
+                            "public method {}#{} has no route, make it private if it isn't an action",
+                            controller.name, method.name
+                        ),
+                    )
+                });
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// ```text
+/// check                           code   count
+/// routes to missing controllers   E0003      1
+/// routes to missing actions       E0004      0
+/// ...
+/// 1 error, 0 warnings
+/// ```
+pub fn table(diagnostics: &[Diagnostic]) -> String {
+    let width = CHECKS.iter().map(|(_, name)| name.len()).max().unwrap_or(0);
+    let mut table = format!(
+        "{:width$}  {:5}  {:>5}\n",
+        "check",
+        "code",
+        "count",
+        width = width
+    );
+    for (code, name) in CHECKS.iter() {
+        let count = diagnostics.iter().filter(|d| d.code == *code).count();
+        table += &format!("{:width$}  {}  {:>5}\n", name, code, count, width = width);
+    }
+    table + &counts(diagnostics)
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::{lint, table};
+    use crate::{
+        diagnostics::{
+            DUPLICATE_ROUTE, MISSING_ACTION, MISSING_CONTROLLER, ROUTED_PRIVATE_METHOD,
+            UNROUTED_METHOD,
+        },
+        inflection::Inflections,
+        parser_parser::parse,
+        routes_rb::parse_routes_rb,
+        ruby_parser::{parse_file, RubyFile},
+    };
+
+    fn helper(input: &str) -> RubyFile {
+        parse_file(
+            Parser::new(input.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn checks() {
+        let defined = vec![
+            helper(
+                "
+                class ApplicationController < ActionController::API
+                    def current_user
+                    end
+                end
+                ",
+            ),
+            helper(
+                "
+                class DogsController < ApplicationController
+                    def index
+                    end
+
+                    def bark
+                    end
+
+                    private
+
+                    def destroy
+                    end
+                end
+                ",
+            ),
+        ];
+        let resolved = defined
+            .iter()
+            .cloned()
+            .map(|file| parse(file, &defined).unwrap())
+            .collect::<Vec<RubyFile>>();
+        let routes = parse_routes_rb(
+            "
+            Rails.application.routes.draw do
+                resources :dogs, only: [:index, :show, :destroy]
+                get 'dogs', to: 'dogs#index'
+                resources :cats, only: [:index]
+            end
+            ",
+            false,
+        )
+        .unwrap()
+        .0;
+
+        let diagnostics = lint(&defined, &resolved, &routes, &[], &Inflections::default());
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.code, d.message.as_str()))
+                .collect::<Vec<(&str, &str)>>(),
+            vec![
+                (MISSING_ACTION, "DogsController has no action `show` for GET /dogs/:id"),
+                (
+                    ROUTED_PRIVATE_METHOD,
+                    "DELETE /dogs/:id DogsController#destroy points at a private method"
+                ),
+                (
+                    DUPLICATE_ROUTE,
+                    "GET /dogs dogs#index is never reached, dogs#index is matched first"
+                ),
+                (MISSING_CONTROLLER, "no controller found for GET /cats cats#index"),
+                (
+                    UNROUTED_METHOD,
+                    "public method DogsController#bark has no route, make it private if it isn't an action"
+                ),
+            ]
+        );
+        assert_eq!(
+            table(&diagnostics),
+            "check                           code   count
+routes to missing controllers   E0003      1
+routes to missing actions       E0004      1
+routed private methods          E0005      1
+public methods without a route  W0005      1
+duplicate routes                W0006      1
+3 errors, 2 warnings"
+        );
+    }
+
+    #[test]
+    fn nested_and_acronyms() {
+        let defined = vec![helper(
+            "
+            class HTMLPagesController < ApplicationController
+                def index
+                end
+            end
+
+            module Api
+                module V1
+                    class Admin::DogFormsController < ApplicationController
+                        def index
+                        end
+                    end
+                end
+            end
+            ",
+        )];
+        let resolved = defined
+            .iter()
+            .cloned()
+            .map(|file| parse(file, &defined).unwrap())
+            .collect::<Vec<RubyFile>>();
+        let routes = parse_routes_rb(
+            "
+            Rails.application.routes.draw do
+                resources :html_pages, only: [:index]
+                namespace :api do
+                    namespace :v1 do
+                        namespace :admin do
+                            resources :dog_forms, only: [:index]
+                        end
+                    end
+                end
+            end
+            ",
+            false,
+        )
+        .unwrap()
+        .0;
+
+        // rails looks for `HtmlPagesController` until `HTML` is an acronym
+        let diagnostics = lint(&defined, &resolved, &routes, &[], &Inflections::default());
+        assert_eq!(
+            diagnostics.iter().map(|d| d.code).collect::<Vec<&str>>(),
+            vec![MISSING_CONTROLLER]
+        );
+        let inflections = Inflections::new(vec!["HTML".to_string()]);
+        assert_eq!(
+            lint(&defined, &resolved, &routes, &[], &inflections),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn framework_and_templates() {
+        let defined = vec![helper(
+            "
+            class PagesController < ApplicationController
+                def home
+                end
+            end
+            ",
+        )];
+        let resolved = defined
+            .iter()
+            .cloned()
+            .map(|file| parse(file, &defined).unwrap())
+            .collect::<Vec<RubyFile>>();
+        let routes = parse_routes_rb(
+            "
+            Rails.application.routes.draw do
+                get 'up' => 'rails/health#show', as: :rails_health_check
+                get 'home', to: 'pages#home'
+                get 'about', to: 'pages#about'
+                get 'contact', to: 'pages#contact'
+            end
+            ",
+            false,
+        )
+        .unwrap()
+        .0;
+
+        // `about` renders `pages/about.html.erb` without a method
+        let templates = vec!["pages/about".to_string()];
+        let diagnostics = lint(
+            &defined,
+            &resolved,
+            &routes,
+            &templates,
+            &Inflections::default(),
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.code, d.message.as_str()))
+                .collect::<Vec<(&str, &str)>>(),
+            vec![(
+                MISSING_ACTION,
+                "PagesController has no action `contact` for GET /contact"
+            )]
+        );
+    }
+}
@@ -1,5 +1,5 @@
-use rts::compute;
 use argh::FromArgs;
+use rts::{compute, Options};
 
 #[derive(FromArgs)]
 /// Parse Ruby controller folder and routes file to produce Swagger file
@@ -8,16 +8,64 @@ struct RtsCmd {
     #[argh(positional)]
     controller: String,
 
-    /// routes file generated by running bundle exec rails routes
+    /// config/routes.rb or a routes file generated by running bundle exec rails routes
     #[argh(positional)]
     routes: String,
+
+    /// file to write the OpenAPI document to, written as YAML when it ends in .yaml or .yml otherwise JSON
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// file to write the TypeScript definitions to
+    #[argh(option, short = 't')]
+    typescript: Option<String>,
+
+    /// file to write a versioned JSON dump of the parsed controllers, routes and matched endpoints to
+    #[argh(option)]
+    dump: Option<String>,
+
+    /// check every route reaches a public action and every public action has a route, prints a summary
+    /// table and exits with 2 when a route is broken so it can gate CI
+    #[argh(switch)]
+    lint: bool,
+
+    /// app/views directory, used to work out the response bodies from jbuilder and jb templates
+    #[argh(option, short = 'v')]
+    views: Option<String>,
+
+    /// db/schema.rb, used to type the model attributes rendered by the views
+    #[argh(option, short = 's')]
+    schema: Option<String>,
+
+    /// app/models directory, used for the enums, validations, attributes and associations models declare
+    #[argh(option, short = 'm')]
+    models: Option<String>,
+
+    /// config/initializers/inflections.rb, the acronyms it declares are used to match routes to controllers,
+    /// defaults to the one next to the routes file
+    #[argh(option)]
+    inflections: Option<String>,
+
+    /// app/serializers or app/blueprints directory, used to work out the JSON rendered through
+    /// active_model_serializers, jsonapi-serializer or blueprinter, can be given more than once
+    #[argh(option)]
+    serializers: Vec<String>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cmd: RtsCmd = argh::from_env();
-    compute(
-        &cmd.controller,
-        &cmd.routes,
-    )?;
-    Ok(())
+    let code = compute(&Options {
+        controllers: cmd.controller,
+        routes: cmd.routes,
+        openapi: cmd.output,
+        typescript: cmd.typescript,
+        dump: cmd.dump,
+        lint: cmd.lint,
+        views: cmd.views,
+        schema: cmd.schema,
+        models: cmd.models,
+        inflections: cmd.inflections,
+        serializers: cmd.serializers,
+    })?;
+    std::process::exit(code)
 }
@@ -0,0 +1,826 @@
+// the models under app/models, their enums and validations narrow the types db/schema.rb gives
+// attributes and their associations say which table an expression like `@dog.owner.name` reads from
+
+use std::fs;
+
+use lib_ruby_parser::{nodes, Node, Parser};
+use walkdir::WalkDir;
+
+use crate::{
+    diagnostics::Diagnostic,
+    inflection::{camelize, pluralize, singularize, underscore},
+    ivars::source,
+    params::Param,
+    ruby_parser::classes,
+    schema::{ColumnType, Schema},
+    types::{Constraints, Type},
+    views::{key, option},
+};
+
+/// `belongs_to :owner`, `has_many :toys` or `has_one :collar, class_name: "DogCollar"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Association {
+    pub name: String,
+    /// `has_many` and `has_and_belongs_to_many`
+    pub many: bool,
+    /// the model it loads, from `class_name:` or the association's name
+    pub class_name: String,
+}
+
+/// An ActiveRecord class and what it declares on top of its table's columns
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Model {
+    pub name: String,
+    pub parent: Option<String>,
+    /// `self.table_name = "canines"`
+    pub table_name: Option<String>,
+    /// `self.abstract_class = true` e.g. `ApplicationRecord`
+    pub abstract_class: bool,
+    pub associations: Vec<Association>,
+    /// `enum status: { active: 0, archived: 1 }` and `inclusion: { in: %w[small large] }` validations
+    pub enums: Vec<(String, Vec<String>)>,
+    /// `validates :name, presence: true, length: { maximum: 50 }`
+    pub validations: Vec<(String, Constraints)>,
+    /// `attribute :price, :decimal, default: 0`
+    pub attributes: Vec<(String, Type)>,
+    /// `scope :good, -> { where(good: true) }`
+    pub scopes: Vec<String>,
+}
+
+// options `enum` takes alongside the values
+const ENUM_OPTIONS: [&str; 6] = [
+    "prefix",
+    "suffix",
+    "scopes",
+    "default",
+    "validate",
+    "instance_methods",
+];
+
+fn statements(body: Option<&Node>) -> Vec<&Node> {
+    match body {
+        Some(Node::Begin(begin)) => begin.statements.iter().collect(),
+        Some(node) => vec![node],
+        None => Vec::new(),
+    }
+}
+
+/// the pairs of a hash or keyword arguments by their key
+fn pairs(node: &Node) -> Vec<(String, &Node)> {
+    let pairs = match node {
+        Node::Hash(nodes::Hash { pairs, .. }) | Node::Kwargs(nodes::Kwargs { pairs, .. }) => pairs,
+        _ => return Vec::new(),
+    };
+    pairs
+        .iter()
+        .filter_map(|pair| match pair {
+            Node::Pair(pair) => Some((key(&pair.key)?, &*pair.value)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// the values of `[:active, :archived]`, `%w[active archived]` or `{ active: 0, archived: 1 }`
+fn enum_values(node: &Node) -> Option<Vec<String>> {
+    match node {
+        Node::Array(array) => Some(array.elements.iter().filter_map(key).collect()),
+        Node::Hash(_) | Node::Kwargs(_) => Some(
+            pairs(node)
+                .into_iter()
+                .map(|(name, _)| name)
+                .filter(|name| !name.starts_with('_') && !ENUM_OPTIONS.contains(&name.as_str()))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn length(node: &Node) -> Option<usize> {
+    match node {
+        Node::Int(int) => int.value.parse().ok(),
+        _ => None,
+    }
+}
+
+/// `/\A[a-z]+\z/` as an ECMA regex, interpolated regexes are skipped and so are ones with the
+/// `i`, `x` or `m` flags which a JSON schema pattern can't carry
+fn pattern(node: &Node) -> Option<String> {
+    let regexp = match node {
+        Node::Regexp(regexp) => regexp,
+        _ => return None,
+    };
+    if let Some(Node::RegOpt(flags)) = regexp.options.as_deref() {
+        if flags.options.iter().any(|c| matches!(c, 'i' | 'x' | 'm')) {
+            return None;
+        }
+    }
+    let mut pattern = String::new();
+    for part in &regexp.parts {
+        match part {
+            Node::Str(str) => pattern += &str.value.to_string_lossy(),
+            _ => return None,
+        }
+    }
+    Some(
+        pattern
+            .replace("\\A", "^")
+            .replace("\\z", "$")
+            .replace("\\Z", "$"),
+    )
+}
+
+impl Model {
+    fn constraints(&mut self, attribute: &str) -> &mut Constraints {
+        let index = match self
+            .validations
+            .iter()
+            .position(|(name, _)| name == attribute)
+        {
+            Some(index) => index,
+            None => {
+                self.validations
+                    .push((attribute.to_string(), Constraints::default()));
+                self.validations.len() - 1
+            }
+        };
+        &mut self.validations[index].1
+    }
+
+    /// one validator e.g. `length: { maximum: 50 }`, `value` is none for `validates_presence_of :name`
+    fn validate(&mut self, attribute: &str, validator: &str, value: Option<&Node>, always: bool) {
+        if matches!(value, Some(Node::False(_)) | Some(Node::Nil(_))) {
+            return;
+        }
+        let options = value.map(pairs).unwrap_or_default();
+        let option = |name: &str| {
+            options
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| *value)
+        };
+
+        match validator {
+            "presence" => self.constraints(attribute).required |= always,
+            "length" => {
+                let range = option("in").or_else(|| option("within"));
+                let (min, max) = match range {
+                    Some(Node::Irange(range)) => (range.left.as_deref(), range.right.as_deref()),
+                    _ => (option("minimum"), option("maximum")),
+                };
+                let (min, max) = match option("is") {
+                    Some(is) => (Some(is), Some(is)),
+                    None => (min, max),
+                };
+                let constraints = self.constraints(attribute);
+                constraints.min_length = min.and_then(length).or(constraints.min_length);
+                constraints.max_length = max.and_then(length).or(constraints.max_length);
+            }
+            "format" => {
+                if let Some(pattern) = option("with").and_then(pattern) {
+                    self.constraints(attribute).pattern = Some(pattern);
+                }
+            }
+            "inclusion" => {
+                let values = option("in")
+                    .or_else(|| option("within"))
+                    .and_then(enum_values);
+                if let Some(values) = values {
+                    if !self.enums.iter().any(|(name, _)| name == attribute) {
+                        self.enums.push((attribute.to_string(), values));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `validates :name, :nickname, presence: true` or `validates_presence_of :name`
+    fn validates(&mut self, send: &nodes::Send) {
+        let attributes = send.args.iter().filter_map(key).collect::<Vec<String>>();
+        let options = send.args.last().map(pairs).unwrap_or_default();
+        let always = !options
+            .iter()
+            .any(|(name, _)| matches!(name.as_str(), "if" | "unless" | "on"));
+
+        let method = send.method_name.as_str();
+        for attribute in &attributes {
+            match method
+                .strip_prefix("validates_")
+                .and_then(|method| method.strip_suffix("_of"))
+            {
+                Some(validator) => {
+                    self.validate(attribute, validator, send.args.last(), always);
+                }
+                None => {
+                    for (validator, value) in &options {
+                        self.validate(attribute, validator, Some(value), always);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `enum status: { active: 0 }`, `enum status: [:active]` or `enum :status, { active: 0 }`
+    fn enums(&mut self, args: &[Node]) {
+        let enums = match args.first() {
+            Some(Node::Sym(sym)) => args
+                .get(1)
+                .and_then(enum_values)
+                .map(|values| (sym.name.to_string_lossy(), values))
+                .into_iter()
+                .collect(),
+            Some(first) => pairs(first)
+                .into_iter()
+                .filter(|(name, _)| !name.starts_with('_'))
+                .filter_map(|(name, values)| Some((name, enum_values(values)?)))
+                .collect(),
+            None => Vec::new(),
+        };
+        for (name, values) in enums {
+            self.enums.retain(|(existing, _)| existing != &name);
+            self.enums.push((name, values));
+        }
+    }
+
+    fn statement(&mut self, send: &nodes::Send) {
+        let name = send.args.first().and_then(key);
+        match (send.recv.as_deref(), send.method_name.as_str()) {
+            (Some(Node::Self_(_)), "table_name=") => {
+                self.table_name = send.args.first().and_then(key)
+            }
+            (Some(Node::Self_(_)), "abstract_class=") => {
+                self.abstract_class = matches!(send.args.first(), Some(Node::True(_)))
+            }
+            (Some(_), _) => {}
+            (None, "belongs_to" | "has_one" | "has_many" | "has_and_belongs_to_many") => {
+                if let Some(name) = name {
+                    let class_name = option(&send.args, "class_name")
+                        .map(|class_name| match class_name {
+                            Node::Const(_) => source(class_name),
+                            class_name => key(class_name).unwrap_or_default(),
+                        })
+                        .unwrap_or_else(|| camelize(&singularize(&name)));
+                    self.associations.retain(|existing| existing.name != name);
+                    self.associations.push(Association {
+                        many: matches!(
+                            send.method_name.as_str(),
+                            "has_many" | "has_and_belongs_to_many"
+                        ),
+                        name,
+                        class_name: class_name.trim_start_matches("::").to_string(),
+                    });
+                }
+            }
+            (None, "enum") => self.enums(&send.args),
+            (None, method) if method == "validates" || method.starts_with("validates_") => {
+                self.validates(send)
+            }
+            // `attribute :price, :decimal, default: 0`, without a default it's nil until set
+            (None, "attribute") => {
+                if let Some(name) = name {
+                    let value = send
+                        .args
+                        .get(1)
+                        .and_then(key)
+                        .map_or(Type::Unknown, |t| ColumnType::new(&t).json_type());
+                    let value = match option(&send.args, "default") {
+                        Some(_) => value,
+                        None => Type::Nullable(Box::new(value)),
+                    };
+                    self.attributes.retain(|(existing, _)| existing != &name);
+                    self.attributes.push((name, value));
+                }
+            }
+            (None, "scope") => self.scopes.extend(name),
+            _ => {}
+        }
+    }
+}
+
+/// The associations, enums, validations, attributes and scopes an ActiveRecord class body declares
+pub fn parse_model(name: &str, class: &nodes::Class) -> Model {
+    let mut model = Model {
+        name: name.to_string(),
+        parent: class.superclass.as_deref().map(source),
+        ..Model::default()
+    };
+    for statement in statements(class.body.as_deref()) {
+        if let Node::Send(send) = statement {
+            model.statement(send);
+        }
+    }
+    model
+}
+
+/// Every model under `app/models` by its full name e.g. `Admin::User`
+#[derive(Debug, Default)]
+pub struct Models {
+    models: Vec<Model>,
+    /// syntax errors in files that couldn't be parsed
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Models {
+    pub fn insert(&mut self, model: Model) {
+        self.models.retain(|existing| existing.name != model.name);
+        self.models.push(model);
+    }
+
+    /// Parses every model class under `path`
+    pub fn parse_dir(path: &str) -> Result<Models, Box<dyn std::error::Error>> {
+        let mut models = Models::default();
+        for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+            let file = entry.path();
+            if !file.is_file() || file.extension().is_none_or(|extension| extension != "rb") {
+                continue;
+            }
+
+            let input = fs::read(file)?;
+            let parsed = Parser::new(&input, Default::default()).do_parse();
+            let ast = match parsed.ast {
+                Some(ast) => ast,
+                None => {
+                    models.diagnostics.extend(Diagnostic::syntax_errors(
+                        &file.display().to_string(),
+                        &input,
+                        &parsed.diagnostics,
+                    ));
+                    continue;
+                }
+            };
+
+            let mut found = Vec::new();
+            classes(&ast, "", &mut found);
+            for (name, class) in found {
+                models.insert(parse_model(&name, class));
+            }
+        }
+
+        Ok(models)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Model> {
+        let name = name.trim_start_matches("::");
+        self.models.iter().find(|model| model.name == name)
+    }
+
+    /// the model followed by the models it inherits from e.g. with single table inheritance
+    fn ancestors<'a>(&'a self, model: &'a Model) -> Vec<&'a Model> {
+        let mut ancestors = vec![model];
+        while let Some(parent) = ancestors
+            .last()
+            .and_then(|last| last.parent.as_deref())
+            .and_then(|parent| self.get(parent))
+        {
+            if parent.abstract_class || ancestors.iter().any(|a| a.name == parent.name) {
+                break;
+            }
+            ancestors.push(parent);
+        }
+        ancestors
+    }
+
+    /// the table a model is stored in, subclasses share their parent's table
+    pub fn table(&self, model: &Model) -> String {
+        let ancestors = self.ancestors(model);
+        if let Some(table_name) = ancestors.iter().find_map(|a| a.table_name.clone()) {
+            return table_name;
+        }
+        let base = ancestors.last().map_or(&model.name, |base| &base.name);
+        pluralize(&underscore(base).replace('/', "_"))
+    }
+
+    /// the model a table holds, subclasses share their parent's table so the base class is picked
+    fn for_table(&self, table: &str) -> Option<&Model> {
+        self.models
+            .iter()
+            .filter(|model| !model.abstract_class && self.table(model) == table)
+            .min_by_key(|model| self.ancestors(model).len())
+    }
+
+    /// the scopes every model can chain by the model's name, including the ones it inherits
+    pub fn scopes(&self) -> Vec<(String, String)> {
+        let mut scopes = Vec::new();
+        for model in &self.models {
+            for ancestor in self.ancestors(model) {
+                for scope in &ancestor.scopes {
+                    scopes.push((model.name.clone(), scope.clone()));
+                }
+            }
+        }
+        scopes
+    }
+
+    fn find<'a, T>(&'a self, model: &'a Model, f: impl Fn(&'a Model) -> Option<T>) -> Option<T> {
+        self.ancestors(model).into_iter().find_map(f)
+    }
+
+    fn enum_values(&self, model: &Model, attribute: &str) -> Option<Vec<String>> {
+        self.find(model, |model| {
+            model
+                .enums
+                .iter()
+                .find(|(name, _)| name == attribute)
+                .map(|(_, values)| values.clone())
+        })
+    }
+
+    fn association<'a>(&'a self, model: &'a Model, name: &str) -> Option<&'a Association> {
+        self.find(model, |model| {
+            model
+                .associations
+                .iter()
+                .find(|association| association.name == name)
+        })
+    }
+
+    /// The type of an attribute read in a view e.g. `@dog.status` or `dogs[].owner.created_at`, the models'
+    /// enums and attribute declarations come first and then the columns in `schema.rb`.
+    /// Associations are followed to the model they name otherwise the table is guessed from their name
+    pub fn type_of(&self, schema: Option<&Schema>, expression: &str) -> Option<Type> {
+        let mut segments = expression
+            .split('.')
+            .map(|segment| segment.trim_start_matches('@').trim_end_matches("[]"))
+            .collect::<Vec<&str>>();
+        let attribute = segments.pop()?;
+        if segments.is_empty() {
+            return None;
+        }
+
+        let mut table = pluralize(&singularize(segments[0]));
+        let mut model = self.for_table(&table);
+        for segment in &segments[1..] {
+            model = match model.and_then(|model| self.association(model, segment)) {
+                Some(association) => self.get(&association.class_name),
+                None => self.for_table(&pluralize(&singularize(segment))),
+            };
+            table = match model {
+                Some(model) => self.table(model),
+                None => pluralize(&singularize(segment)),
+            };
+        }
+
+        let column = schema.and_then(|schema| schema.type_of(&format!("{}.{}", table, attribute)));
+        let model = match model {
+            Some(model) => model,
+            None => return column,
+        };
+
+        let declared = self.find(model, |model| {
+            model
+                .attributes
+                .iter()
+                .find(|(name, _)| name == attribute)
+                .map(|(_, value)| value.clone())
+        });
+        match (declared, self.enum_values(model, attribute)) {
+            (Some(declared), _) => Some(declared),
+            (None, Some(values)) => Some(match column {
+                Some(Type::Nullable(_)) => Type::Nullable(Box::new(Type::Enum(values))),
+                _ => Type::Enum(values),
+            }),
+            (None, None) => column,
+        }
+    }
+
+    /// narrows the fields of params sent for a model e.g. `dog[status]`, nested `toys_attributes`
+    /// are narrowed through the association's model. Presence is only required when `creating`
+    fn refine(&self, model: &Model, value: Type, creating: bool) -> Type {
+        let fields = match value {
+            Type::Object(fields) => fields,
+            Type::Array(item) => {
+                return Type::Array(Box::new(self.refine(model, *item, creating)));
+            }
+            value => return value,
+        };
+
+        Type::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let nested = name
+                        .strip_suffix("_attributes")
+                        .and_then(|association| self.association(model, association))
+                        .and_then(|association| self.get(&association.class_name));
+                    if let Some(nested) = nested {
+                        let value = self.refine(nested, value, creating);
+                        return (name, value);
+                    }
+
+                    let value = match (self.enum_values(model, &name), value) {
+                        (Some(values), Type::Array(_)) => Type::Array(Box::new(Type::Enum(values))),
+                        (Some(values), _) => Type::Enum(values),
+                        (None, value) => value,
+                    };
+                    let constraints = self.find(model, |model| {
+                        model
+                            .validations
+                            .iter()
+                            .find(|(attribute, _)| attribute == &name)
+                            .map(|(_, constraints)| constraints.clone())
+                    });
+                    let value = match constraints {
+                        Some(mut constraints) => {
+                            constraints.required &= creating;
+                            if constraints == Constraints::default() {
+                                value
+                            } else {
+                                Type::Constrained(Box::new(value), constraints)
+                            }
+                        }
+                        None => value,
+                    };
+                    (name, value)
+                })
+                .collect(),
+        )
+    }
+
+    /// Narrows the params nested under a model's name e.g. `params.require(:dog).permit(:name, :status)`
+    /// with the enums and validations the model declares
+    pub fn refine_params(&self, params: &mut [Param], creating: bool) {
+        for param in params {
+            let model = match self.for_table(&pluralize(&singularize(&param.name))) {
+                Some(model) => model,
+                None => continue,
+            };
+            if let Type::Object(_) = param.param_type {
+                let value = std::mem::replace(&mut param.param_type, Type::Unknown);
+                param.param_type = self.refine(model, value, creating);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod models_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::{parse_model, Association, Models};
+    use crate::{
+        params::{Param, ParamLocation},
+        ruby_parser::classes,
+        schema::parse_schema,
+        types::{Constraints, Type},
+    };
+
+    fn helper(input: &str) -> Models {
+        let ast = Parser::new(input.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap();
+        let mut found = Vec::new();
+        classes(&ast, "", &mut found);
+
+        let mut models = Models::default();
+        for (name, class) in found {
+            models.insert(parse_model(&name, class));
+        }
+        models
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn field(name: &str, value: Type) -> (String, Type) {
+        (name.to_string(), value)
+    }
+
+    const INPUT: &str = "
+    class ApplicationRecord < ActiveRecord::Base
+        self.abstract_class = true
+    end
+
+    class Dog < ApplicationRecord
+        belongs_to :owner, class_name: \"Person\"
+        has_many :toys, dependent: :destroy
+        accepts_nested_attributes_for :toys
+
+        enum status: { active: 0, archived: 1 }, _prefix: true
+        enum :size, [:small, :large]
+
+        validates :name, presence: true, length: { maximum: 50 }
+        validates :nickname, length: { in: 2..20 }, presence: true, if: :named?
+        validates_format_of :chip, with: /\\A[0-9]{15}\\z/
+        validates :coat, inclusion: { in: %w[short long] }
+
+        attribute :weight_in_grams, :big_integer
+        attribute :good, :boolean, default: true
+
+        scope :good, -> { where(good: true) }
+    end
+
+    class Puppy < Dog
+    end
+
+    class Person < ApplicationRecord
+        self.table_name = \"people\"
+    end
+
+    class Toy < ApplicationRecord
+        validates :name, presence: true
+    end
+    ";
+
+    #[test]
+    fn parse() {
+        let models = helper(INPUT);
+        let dog = models.get("Dog").unwrap();
+
+        assert_eq!(
+            dog.associations,
+            vec![
+                Association {
+                    name: "owner".to_string(),
+                    many: false,
+                    class_name: "Person".to_string(),
+                },
+                Association {
+                    name: "toys".to_string(),
+                    many: true,
+                    class_name: "Toy".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            dog.enums,
+            vec![
+                ("status".to_string(), strings(&["active", "archived"])),
+                ("size".to_string(), strings(&["small", "large"])),
+                ("coat".to_string(), strings(&["short", "long"])),
+            ]
+        );
+        assert_eq!(
+            dog.validations,
+            vec![
+                (
+                    "name".to_string(),
+                    Constraints {
+                        required: true,
+                        max_length: Some(50),
+                        ..Constraints::default()
+                    }
+                ),
+                (
+                    "nickname".to_string(),
+                    Constraints {
+                        min_length: Some(2),
+                        max_length: Some(20),
+                        ..Constraints::default()
+                    }
+                ),
+                (
+                    "chip".to_string(),
+                    Constraints {
+                        pattern: Some("^[0-9]{15}$".to_string()),
+                        ..Constraints::default()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            dog.attributes,
+            vec![
+                field("weight_in_grams", Type::Nullable(Box::new(Type::Integer))),
+                field("good", Type::Boolean),
+            ]
+        );
+        assert_eq!(
+            models.scopes(),
+            vec![
+                ("Dog".to_string(), "good".to_string()),
+                ("Puppy".to_string(), "good".to_string()),
+            ]
+        );
+        assert_eq!(models.table(models.get("Puppy").unwrap()), "dogs");
+    }
+
+    #[test]
+    fn regexp_flags() {
+        let models = helper(
+            r"
+            class Dog < ApplicationRecord
+                validates :name, format: { with: /\A[a-z]+\z/i }
+                validates :chip, format: { with: /\A [0-9]{15} \z/x }
+                validates :bio, format: { with: /\A.+\z/m }
+                validates :tag, format: { with: /\A[a-z]+\z/o }
+            end
+            ",
+        );
+
+        // the flags change what matches so only `tag` gets a pattern
+        assert_eq!(
+            models.get("Dog").unwrap().validations,
+            vec![(
+                "tag".to_string(),
+                Constraints {
+                    pattern: Some("^[a-z]+$".to_string()),
+                    ..Constraints::default()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn types() {
+        let models = helper(INPUT);
+        let schema = parse_schema(
+            "
+            ActiveRecord::Schema.define(version: 1) do
+                create_table \"dogs\" do |t|
+                    t.string \"name\", null: false
+                    t.integer \"status\", null: false
+                    t.integer \"size\"
+                    t.references \"owner\"
+                end
+
+                create_table \"people\" do |t|
+                    t.string \"email\"
+                end
+            end
+            ",
+        )
+        .unwrap();
+        let type_of = |expression| models.type_of(Some(&schema), expression);
+
+        let enumeration = |values| Type::Enum(strings(values));
+        assert_eq!(
+            type_of("dog.status"),
+            Some(enumeration(&["active", "archived"]))
+        );
+        assert_eq!(
+            type_of("dogs[].size"),
+            Some(Type::Nullable(Box::new(enumeration(&["small", "large"]))))
+        );
+        assert_eq!(type_of("@dog.good"), Some(Type::Boolean));
+        assert_eq!(type_of("dog.name"), Some(Type::String));
+        assert_eq!(
+            type_of("dog.owner.email"),
+            Some(Type::Nullable(Box::new(Type::String)))
+        );
+        assert_eq!(type_of("dog.unknown"), None);
+        assert_eq!(
+            Models::default().type_of(Some(&schema), "dog.status"),
+            Some(Type::Integer)
+        );
+    }
+
+    #[test]
+    fn params() {
+        let models = helper(INPUT);
+        let mut params = vec![Param {
+            name: "dog".to_string(),
+            location: ParamLocation::Body,
+            param_type: Type::Object(vec![
+                field("name", Type::String),
+                field("status", Type::String),
+                field("size", Type::Array(Box::new(Type::String))),
+                field(
+                    "toys_attributes",
+                    Type::Array(Box::new(Type::Object(vec![field("name", Type::String)]))),
+                ),
+            ]),
+        }];
+        models.refine_params(&mut params, true);
+
+        let required = |value| {
+            Type::Constrained(
+                Box::new(value),
+                Constraints {
+                    required: true,
+                    ..Constraints::default()
+                },
+            )
+        };
+        assert_eq!(
+            params[0].param_type,
+            Type::Object(vec![
+                field(
+                    "name",
+                    Type::Constrained(
+                        Box::new(Type::String),
+                        Constraints {
+                            required: true,
+                            max_length: Some(50),
+                            ..Constraints::default()
+                        }
+                    )
+                ),
+                field("status", Type::Enum(strings(&["active", "archived"]))),
+                field(
+                    "size",
+                    Type::Array(Box::new(Type::Enum(strings(&["small", "large"]))))
+                ),
+                field(
+                    "toys_attributes",
+                    Type::Array(Box::new(Type::Object(vec![field(
+                        "name",
+                        required(Type::String)
+                    )])))
+                ),
+            ])
+        );
+    }
+}
@@ -0,0 +1,685 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    params::{Input, ParamLocation},
+    responses::Response,
+    routes::Request,
+    types::Type,
+    Endpoint,
+};
+
+const OPENAPI_VERSION: &str = "3.1.0";
+
+/// converts a rails uri such as `/dogs/:dog_id/styles` into an OpenAPI path template `/dogs/{dog_id}/styles`
+pub fn path_template(request: &Request) -> String {
+    request.map_path_params(|name| format!("{{{}}}", name))
+}
+
+/// the JSON schema for a value, expressions that can't be typed yet accept anything
+pub fn schema(value: &Type) -> Value {
+    match value {
+        Type::Null => json!({ "type": "null" }),
+        Type::Boolean => json!({ "type": "boolean" }),
+        Type::Integer => json!({ "type": "integer" }),
+        Type::Number => json!({ "type": "number" }),
+        Type::String => json!({ "type": "string" }),
+        Type::Date => json!({ "type": "string", "format": "date" }),
+        Type::DateTime => json!({ "type": "string", "format": "date-time" }),
+        Type::Literal(value) => json!({ "type": "string", "const": value }),
+        Type::Enum(values) => json!({ "type": "string", "enum": values }),
+        Type::Id => json!({ "type": ["integer", "string"] }),
+        Type::Nullable(value) => {
+            let mut value = schema(value);
+            match value.get("type").cloned() {
+                Some(Value::String(t)) => {
+                    value["type"] = json!([t, "null"]);
+                    // an enum has to list null as well for null to be valid
+                    if let Some(values) = value.get_mut("enum").and_then(Value::as_array_mut) {
+                        values.push(Value::Null);
+                    }
+                    value
+                }
+                _ => json!({ "anyOf": [value, { "type": "null" }] }),
+            }
+        }
+        Type::Array(item) => json!({ "type": "array", "items": schema(item) }),
+        Type::Object(fields) => {
+            let properties = fields
+                .iter()
+                .map(|(name, field)| (name.clone(), schema(field)))
+                .collect::<Map<String, Value>>();
+            let required = fields
+                .iter()
+                .filter(|(_, field)| {
+                    matches!(field, Type::Constrained(_, constraints) if constraints.required)
+                })
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<String>>();
+
+            let mut object = json!({ "type": "object", "properties": properties });
+            if !required.is_empty() {
+                object["required"] = json!(required);
+            }
+            object
+        }
+        Type::AllOf(types) => json!({ "allOf": types.iter().map(schema).collect::<Vec<Value>>() }),
+        Type::OneOf(types) => json!({ "oneOf": types.iter().map(schema).collect::<Vec<Value>>() }),
+        Type::Constrained(value, constraints) => {
+            let mut value = schema(value);
+            if let Some(min_length) = constraints.min_length {
+                value["minLength"] = json!(min_length);
+            }
+            if let Some(max_length) = constraints.max_length {
+                value["maxLength"] = json!(max_length);
+            }
+            if let Some(pattern) = &constraints.pattern {
+                value["pattern"] = json!(pattern);
+            }
+            value
+        }
+        Type::Unknown | Type::Expression(_) | Type::Partial { .. } => json!({}),
+    }
+}
+
+/// responses sharing a status code each add their body, an unknown body `{}` doesn't replace a known
+/// one and different bodies are combined with `oneOf`
+fn add_schema(content: &mut Value, body: Value) {
+    let existing = match content.get_mut("schema") {
+        Some(existing) if *existing != json!({}) => existing,
+        _ => {
+            content["schema"] = body;
+            return;
+        }
+    };
+    if body == json!({}) || *existing == body {
+        return;
+    }
+    match existing.get_mut("oneOf").and_then(Value::as_array_mut) {
+        Some(schemas) => {
+            if !schemas.contains(&body) {
+                schemas.push(body);
+            }
+        }
+        None => *existing = json!({ "oneOf": [existing.take(), body] }),
+    }
+}
+
+fn operation(endpoint: &Endpoint, operation_id: String) -> Value {
+    let mut parameters = Vec::new();
+    let mut body = Map::new();
+    for param in &endpoint.params {
+        match param.location {
+            ParamLocation::Path => parameters.push(json!({
+                "name": param.name,
+                "in": "path",
+                "required": true,
+                "schema": schema(&param.param_type)
+            })),
+            ParamLocation::Query => parameters.push(json!({
+                "name": param.name,
+                "in": "query",
+                "required": false,
+                "schema": schema(&param.param_type)
+            })),
+            ParamLocation::Body => {
+                body.insert(param.name.clone(), schema(&param.param_type));
+            }
+        }
+    }
+
+    let mut responses = Map::new();
+    for response in &endpoint.method.returns {
+        let code = response
+            .status()
+            .map_or("default".to_string(), |status| status.to_string());
+        let entry = responses
+            .entry(code)
+            .or_insert_with(|| json!({ "description": "" }));
+
+        let description = match entry["description"].as_str() {
+            Some("") | None => response.to_string(),
+            Some(description) => format!("{}, {}", description, response),
+        };
+        entry["description"] = Value::String(description);
+
+        match response {
+            Response::Json { .. } => add_schema(
+                &mut entry["content"]["application/json"],
+                endpoint.body(response).map_or(json!({}), schema),
+            ),
+            Response::File { content_type, .. } => {
+                let content_type = content_type
+                    .clone()
+                    .filter(|content_type| content_type.contains('/'))
+                    .unwrap_or_else(|| "application/octet-stream".to_string());
+                entry["content"][content_type]["schema"] =
+                    json!({ "type": "string", "format": "binary" });
+            }
+            Response::Redirect { .. } => {
+                entry["headers"]["Location"]["schema"] = json!({ "type": "string" });
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(view) = &endpoint.view {
+        let entry = responses.entry("200").or_insert_with(|| {
+            json!({
+                "description": format!("{}/{}", endpoint.request.controller, endpoint.request.action)
+            })
+        });
+        add_schema(&mut entry["content"]["application/json"], schema(view));
+    }
+
+    if responses.is_empty() {
+        responses.insert(
+            "default".to_string(),
+            json!({ "description": "no response found" }),
+        );
+    }
+
+    let mut operation = json!({
+        "operationId": operation_id,
+        "tags": [endpoint.request.controller],
+        "responses": responses
+    });
+
+    let mut security = Vec::new();
+    let mut session = Vec::new();
+    for input in &endpoint.method.inputs {
+        match input {
+            Input::Header(name) if name.eq_ignore_ascii_case("authorization") => {
+                security.push(json!({ "authorization": [] }))
+            }
+            // described by the request body and responses instead
+            Input::Header(name)
+                if name.eq_ignore_ascii_case("content-type")
+                    || name.eq_ignore_ascii_case("accept") => {}
+            Input::Header(name) => parameters.push(json!({
+                "name": name,
+                "in": "header",
+                "required": false,
+                "schema": { "type": "string" }
+            })),
+            Input::BearerAuth => security.push(json!({ "bearer": [] })),
+            Input::BasicAuth => security.push(json!({ "basic": [] })),
+            Input::Cookie(name) => parameters.push(json!({
+                "name": name,
+                "in": "cookie",
+                "required": false,
+                "schema": { "type": "string" }
+            })),
+            Input::Session(key) => session.push(key.clone()),
+            Input::RawBody | Input::JsonBody => {}
+        }
+    }
+
+    // the session is a single cookie named after the app so only what's read from it is known
+    if !session.is_empty() {
+        parameters.push(json!({
+            "name": "session",
+            "in": "cookie",
+            "required": false,
+            "description": format!("rails session reading {}", session.join(", ")),
+            "schema": { "type": "string" }
+        }));
+    }
+
+    if !parameters.is_empty() {
+        operation["parameters"] = Value::Array(parameters);
+    }
+
+    if !security.is_empty() {
+        operation["security"] = Value::Array(security);
+    }
+
+    if !body.is_empty() {
+        let mut body = json!({ "type": "object", "properties": body });
+        let required = endpoint
+            .method
+            .strong_params
+            .as_ref()
+            .and_then(|strong_params| strong_params.require.clone());
+        if let Some(root) = &required {
+            body["required"] = json!([root]);
+        }
+        operation["requestBody"] = json!({
+            "required": required.is_some(),
+            "content": { "application/json": { "schema": body } }
+        });
+    } else if endpoint.method.inputs.contains(&Input::JsonBody) {
+        operation["requestBody"] = json!({
+            "content": { "application/json": { "schema": {} } }
+        });
+    } else if endpoint.method.inputs.contains(&Input::RawBody) {
+        operation["requestBody"] = json!({
+            "content": { "*/*": { "schema": { "type": "string" } } }
+        });
+    }
+
+    operation
+}
+
+/// how the credentials in an operation's `security` are sent
+fn security_scheme(name: &str) -> Value {
+    match name {
+        "bearer" => json!({ "type": "http", "scheme": "bearer" }),
+        "basic" => json!({ "type": "http", "scheme": "basic" }),
+        _ => json!({ "type": "apiKey", "in": "header", "name": "Authorization" }),
+    }
+}
+
+/// Builds an OpenAPI document with an operation for every endpoint
+pub fn build(endpoints: &[Endpoint]) -> Value {
+    let mut paths = Map::new();
+    let mut operation_ids = HashSet::new();
+    let mut security_schemes = Map::new();
+
+    for endpoint in endpoints {
+        let method = endpoint.request.method.lowercase();
+
+        // the same action can be routed to by multiple verbs e.g. PUT and PATCH for update, or by
+        // several routes with the same verb which are numbered
+        let mut operation_id = format!(
+            "{}#{}",
+            endpoint.request.controller, endpoint.request.action
+        );
+        if operation_ids.contains(&operation_id) {
+            operation_id = format!("{}_{}", operation_id, method);
+        }
+        let base = operation_id.clone();
+        let mut suffix = 2;
+        while !operation_ids.insert(operation_id.clone()) {
+            operation_id = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+
+        let path = paths
+            .entry(path_template(&endpoint.request))
+            .or_insert_with(|| json!({}));
+        let operation = operation(endpoint, operation_id);
+        if let Some(security) = operation["security"].as_array() {
+            for requirement in security.iter().filter_map(|r| r.as_object()) {
+                for name in requirement.keys() {
+                    security_schemes
+                        .entry(name.clone())
+                        .or_insert_with(|| security_scheme(name));
+                }
+            }
+        }
+        path[method] = operation;
+    }
+
+    let mut document = json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "API",
+            "version": "1.0.0"
+        },
+        "paths": paths
+    });
+    if !security_schemes.is_empty() {
+        document["components"]["securitySchemes"] = Value::Object(security_schemes);
+    }
+    document
+}
+
+/// Writes the OpenAPI document for the endpoints to `output`, as YAML for `.yaml`/`.yml` files otherwise as JSON
+pub fn write(endpoints: &[Endpoint], output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let document = build(endpoints);
+    let contents = match Path::new(output).extension().and_then(|e| e.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::to_string(&document)?,
+        _ => serde_json::to_string_pretty(&document)?,
+    };
+
+    fs::write(output, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod openapi_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{build, path_template, schema};
+    use crate::{
+        inflection::Inflections,
+        match_routes,
+        routes::{parse_routes, Request, RequestMethod},
+        ruby_parser::parse_file,
+        types::{Constraints, Type},
+    };
+
+    fn helper(controller: &str, routes: &str) -> serde_json::Value {
+        let file = parse_file(
+            Parser::new(controller.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+        )
+        .unwrap();
+        let routes = parse_routes(routes).unwrap();
+
+        build(&match_routes(&[file], &routes, &Inflections::default()))
+    }
+
+    #[test]
+    fn path_params() {
+        let request = |uri: &str| Request {
+            method: RequestMethod::GET,
+            prefix: "".to_string(),
+            uri: uri.to_string(),
+            controller: "dogs".to_string(),
+            action: "index".to_string(),
+        };
+
+        assert_eq!(
+            path_template(&request("/dogs/:dog_id/styles")),
+            "/dogs/{dog_id}/styles"
+        );
+        assert_eq!(path_template(&request("/dog/form")), "/dog/form");
+        assert_eq!(path_template(&request("/files/*path")), "/files/{path}");
+    }
+
+    #[test]
+    fn operations() {
+        let controller = "
+        class DogFormsController < ApplicationController
+            def show
+                @name = params[:name]
+                render json: @name
+            end
+
+            def update
+                @form = DogForm.first
+                render json: @form.errors, status: :unprocessable_entity
+            end
+        end
+        ";
+        let routes = "Prefix Verb    URI Pattern                 Controller#Action
+            dog_form GET     /dogs/:dog_id/form(.:format)       dog_forms#show
+                     PATCH   /dogs/:dog_id/form(.:format)       dog_forms#update
+                     PUT     /dogs/:dog_id/form(.:format)       dog_forms#update
+        ";
+
+        let path_param = json!({
+            "name": "dog_id",
+            "in": "path",
+            "required": true,
+            "schema": { "type": "string" }
+        });
+
+        let update_responses = json!({
+            "422": {
+                "description": "render json: @form.errors 422",
+                "content": { "application/json": { "schema": {} } }
+            }
+        });
+
+        assert_eq!(
+            helper(controller, routes),
+            json!({
+                "openapi": "3.1.0",
+                "info": { "title": "API", "version": "1.0.0" },
+                "paths": {
+                    "/dogs/{dog_id}/form": {
+                        "get": {
+                            "operationId": "dog_forms#show",
+                            "tags": ["dog_forms"],
+                            "parameters": [
+                                path_param,
+                                {
+                                    "name": "name",
+                                    "in": "query",
+                                    "required": false,
+                                    "schema": { "type": "string" }
+                                }
+                            ],
+                            "responses": {
+                                "200": {
+                                    "description": "render json: @name 200",
+                                    "content": { "application/json": { "schema": {} } }
+                                }
+                            }
+                        },
+                        "patch": {
+                            "operationId": "dog_forms#update",
+                            "tags": ["dog_forms"],
+                            "parameters": [path_param],
+                            "responses": update_responses
+                        },
+                        "put": {
+                            "operationId": "dog_forms#update_put",
+                            "tags": ["dog_forms"],
+                            "parameters": [path_param],
+                            "responses": update_responses
+                        }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn operation_ids() {
+        let controller = "
+        class DogsController < ApplicationController
+            def show
+                render json: {}
+            end
+        end
+        ";
+        let routes = "Prefix Verb    URI Pattern                 Controller#Action
+                 dog GET     /dogs/:id(.:format)         dogs#show
+              pooch GET     /pooches/:id(.:format)      dogs#show
+              puppy GET     /puppies/:id(.:format)      dogs#show
+        ";
+
+        let document = helper(controller, routes);
+        let ids = ["/dogs/{id}", "/pooches/{id}", "/puppies/{id}"]
+            .iter()
+            .map(|path| document["paths"][path]["get"]["operationId"].clone())
+            .collect::<Vec<serde_json::Value>>();
+        assert_eq!(
+            ids,
+            vec![
+                json!("dogs#show"),
+                json!("dogs#show_get"),
+                json!("dogs#show_get_2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn request_body() {
+        let controller = "
+        class DogsController < ApplicationController
+            def create
+                @dog = Dog.new(params.require(:dog).permit(:name, toys: [], owner: [:email]))
+                head :created
+            end
+        end
+        ";
+        let routes = "Prefix Verb    URI Pattern                 Controller#Action
+            dogs POST    /dogs(.:format)             dogs#create
+        ";
+
+        assert_eq!(
+            helper(controller, routes)["paths"]["/dogs"]["post"]["requestBody"],
+            json!({
+                "required": true,
+                "content": {
+                    "application/json": {
+                        "schema": {
+                            "type": "object",
+                            "properties": {
+                                "dog": {
+                                    "type": "object",
+                                    "properties": {
+                                        "name": { "type": "string" },
+                                        "toys": { "type": "array", "items": { "type": "string" } },
+                                        "owner": {
+                                            "type": "object",
+                                            "properties": { "email": { "type": "string" } }
+                                        }
+                                    }
+                                }
+                            },
+                            "required": ["dog"]
+                        }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn inputs() {
+        let controller = "
+        class WebhooksController < ApplicationController
+            def create
+                authenticate_or_request_with_http_token { |token| token == ENV['TOKEN'] }
+                Event.create(source: request.headers['X-Source'], payload: JSON.parse(request.raw_post))
+                head :accepted
+            end
+        end
+        ";
+        let routes = "Prefix Verb    URI Pattern                 Controller#Action
+            webhooks POST    /webhooks(.:format)         webhooks#create
+        ";
+        let document = helper(controller, routes);
+
+        assert_eq!(
+            document["paths"]["/webhooks"]["post"]["parameters"],
+            json!([{
+                "name": "X-Source",
+                "in": "header",
+                "required": false,
+                "schema": { "type": "string" }
+            }])
+        );
+        assert_eq!(
+            document["paths"]["/webhooks"]["post"]["security"],
+            json!([{ "bearer": [] }])
+        );
+        assert_eq!(
+            document["paths"]["/webhooks"]["post"]["requestBody"],
+            json!({ "content": { "application/json": { "schema": {} } } })
+        );
+        assert_eq!(
+            document["components"]["securitySchemes"],
+            json!({ "bearer": { "type": "http", "scheme": "bearer" } })
+        );
+    }
+
+    #[test]
+    fn view_schema() {
+        let view = Type::Array(Box::new(Type::Object(vec![
+            ("id".to_string(), Type::Integer),
+            (
+                "name".to_string(),
+                Type::Expression("@dogs[].name".to_string()),
+            ),
+        ])));
+
+        assert_eq!(
+            schema(&view),
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "name": {}
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn model_schema() {
+        let params = Type::Object(vec![
+            (
+                "name".to_string(),
+                Type::Constrained(
+                    Box::new(Type::String),
+                    Constraints {
+                        required: true,
+                        max_length: Some(50),
+                        pattern: Some("^[a-z]+$".to_string()),
+                        ..Constraints::default()
+                    },
+                ),
+            ),
+            (
+                "status".to_string(),
+                Type::Nullable(Box::new(Type::Enum(vec![
+                    "active".to_string(),
+                    "archived".to_string(),
+                ]))),
+            ),
+        ]);
+
+        assert_eq!(
+            schema(&params),
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "maxLength": 50, "pattern": "^[a-z]+$" },
+                    "status": { "type": ["string", "null"], "enum": ["active", "archived", null] }
+                },
+                "required": ["name"]
+            })
+        );
+    }
+
+    #[test]
+    fn shared_status() {
+        let controller = "
+        class DogsController < ApplicationController
+            def show
+                if params[:full]
+                    render json: @dog, status: :ok
+                else
+                    render json: summary, status: :ok
+                end
+            end
+        end
+        ";
+        let file = parse_file(
+            Parser::new(controller.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+        )
+        .unwrap();
+        let routes = parse_routes(
+            "Prefix Verb    URI Pattern                 Controller#Action
+            dog GET     /dogs/:id(.:format)         dogs#show
+        ",
+        )
+        .unwrap();
+        let mut endpoints = match_routes(&[file], &routes, &Inflections::default());
+        let dog = Type::Object(vec![("id".to_string(), Type::Integer)]);
+        let full = endpoints[0].method.returns[0].clone();
+        endpoints[0].bodies = vec![(full, dog.clone())];
+
+        // the unknown `summary` body doesn't replace the known one
+        let document = build(&endpoints);
+        let content = &document["paths"]["/dogs/{id}"]["get"]["responses"]["200"]["content"];
+        assert_eq!(content["application/json"]["schema"], schema(&dog));
+
+        // a view that renders something else is another possible body
+        endpoints[0].view = Some(Type::Array(Box::new(dog.clone())));
+        let document = build(&endpoints);
+        let content = &document["paths"]["/dogs/{id}"]["get"]["responses"]["200"]["content"];
+        assert_eq!(
+            content["application/json"]["schema"],
+            json!({ "oneOf": [schema(&dog), schema(&Type::Array(Box::new(dog)))] })
+        );
+    }
+}
@@ -1,6 +1,382 @@
 use std::collections::{HashSet, VecDeque};
 
-use lib_ruby_parser::Node;
+use lib_ruby_parser::{nodes, traverse::Visitor, Node};
+
+use crate::{routes::Request, ruby_parser::Method, types::Type};
+
+/// where in the request a param is sent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamLocation {
+    /// a dynamic segment of the uri e.g. `:dog_id`
+    Path,
+    Query,
+    Body,
+}
+
+impl std::fmt::Display for ParamLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamLocation::Path => write!(f, "path"),
+            ParamLocation::Query => write!(f, "query"),
+            ParamLocation::Body => write!(f, "body"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub location: ParamLocation,
+    pub param_type: Type,
+}
+
+fn is_params(node: &Node) -> bool {
+    matches!(node, Node::Send(send) if send.recv.is_none() && send.method_name == "params")
+}
+
+/// `params[:id]` or `params.fetch(:id)`
+fn param_name(node: &Node) -> Option<String> {
+    let (recv, key) = match node {
+        Node::Index(index) => (&*index.recv, index.indexes.first()?),
+        Node::Send(send) if send.method_name == "[]" || send.method_name == "fetch" => {
+            (send.recv.as_deref()?, send.args.first()?)
+        }
+        _ => return None,
+    };
+    match key {
+        Node::Sym(sym) if is_params(recv) => Some(sym.name.to_string_lossy()),
+        Node::Str(str) if is_params(recv) => Some(str.value.to_string_lossy()),
+        _ => None,
+    }
+}
+
+fn is_const(node: Option<&Node>, name: &str) -> bool {
+    matches!(node, Some(Node::Const(constant)) if constant.name == name)
+}
+
+/// `Time` or `Time.zone`
+fn is_time(node: Option<&Node>) -> bool {
+    match node {
+        Some(Node::Send(send)) if send.method_name == "zone" => {
+            is_const(send.recv.as_deref(), "Time")
+        }
+        node => is_const(node, "Time") || is_const(node, "DateTime"),
+    }
+}
+
+/// `ActiveModel::Type::Boolean.new.cast(...)`
+fn is_boolean_cast(send: &nodes::Send) -> bool {
+    match send.recv.as_deref() {
+        Some(Node::Send(new)) if send.method_name == "cast" && new.method_name == "new" => {
+            is_const(new.recv.as_deref(), "Boolean")
+        }
+        _ => false,
+    }
+}
+
+/// the type a param has to be for the way it's used e.g. `params[:page].to_i`
+fn param_type(send: &nodes::Send) -> Option<(String, Type)> {
+    let method = send.method_name.as_str();
+
+    if let Some(name) = send.recv.as_deref().and_then(param_name) {
+        let param_type = match method {
+            "to_i" => Type::Integer,
+            "to_f" | "to_d" | "to_r" => Type::Number,
+            "to_date" => Type::Date,
+            "to_datetime" | "to_time" => Type::DateTime,
+            "split" => Type::Array(Box::new(Type::String)),
+            "==" | "!=" => match send.args.first() {
+                Some(Node::Str(str))
+                    if matches!(str.value.to_string_lossy().as_str(), "true" | "false") =>
+                {
+                    Type::Boolean
+                }
+                Some(Node::True(_)) | Some(Node::False(_)) => Type::Boolean,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        return Some((name, param_type));
+    }
+
+    let name = param_name(send.args.first()?)?;
+    let recv = send.recv.as_deref();
+    let param_type = match method {
+        "Integer" if recv.is_none() => Type::Integer,
+        "Float" | "BigDecimal" if recv.is_none() => Type::Number,
+        "parse" | "iso8601" if is_const(recv, "Date") => Type::Date,
+        "parse" | "iso8601" if is_time(recv) => Type::DateTime,
+        "cast" if is_boolean_cast(send) => Type::Boolean,
+        "find" => Type::Id,
+        _ => return None,
+    };
+    Some((name, param_type))
+}
+
+#[derive(Default)]
+struct TypeSearch {
+    types: Vec<(String, Type)>,
+}
+
+impl Visitor for TypeSearch {
+    fn on_send(&mut self, send: &nodes::Send) {
+        if let Some((name, param_type)) = param_type(send) {
+            // the first use wins, a param is rarely converted to different types
+            if !self.types.iter().any(|(existing, _)| existing == &name) {
+                self.types.push((name, param_type));
+            }
+        }
+
+        if let Some(recv) = &send.recv {
+            self.visit(recv);
+        }
+        self.visit_all(&send.args);
+    }
+}
+
+/// Types of the params that are converted before they're used, anything else is left as a string
+pub fn search_for_param_types(body: &Node) -> Vec<(String, Type)> {
+    let mut search = TypeSearch::default();
+    search.visit(body);
+    search.types
+}
+
+/// what an action reads from the request other than its params
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// `request.headers['X-Api-Key']`, `request.authorization` or `request.env['HTTP_X_API_KEY']`
+    Header(String),
+    /// `authenticate_with_http_token`
+    BearerAuth,
+    /// `authenticate_with_http_basic`
+    BasicAuth,
+    /// `cookies[:token]`, `cookies.signed[:token]`
+    Cookie(String),
+    /// `session[:user_id]`, sent as the rails session cookie
+    Session(String),
+    /// `request.raw_post` or `request.body.read`
+    RawBody,
+    /// `JSON.parse(request.raw_post)`
+    JsonBody,
+}
+
+impl std::fmt::Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Header(name) => write!(f, "header {}", name),
+            Input::BearerAuth => write!(f, "bearer auth"),
+            Input::BasicAuth => write!(f, "basic auth"),
+            Input::Cookie(name) => write!(f, "cookie {}", name),
+            Input::Session(name) => write!(f, "session {}", name),
+            Input::RawBody => write!(f, "raw body"),
+            Input::JsonBody => write!(f, "json body"),
+        }
+    }
+}
+
+fn is_request(node: Option<&Node>) -> bool {
+    matches!(node, Some(Node::Send(send)) if send.recv.is_none() && send.method_name == "request")
+}
+
+/// `request.foo`
+fn request_method(node: &Node) -> Option<&str> {
+    match node {
+        Node::Send(send) if is_request(send.recv.as_deref()) => Some(&send.method_name),
+        _ => None,
+    }
+}
+
+/// `cookies`, `cookies.signed`, `cookies.encrypted` or `cookies.permanent.signed`
+fn is_cookies(node: &Node) -> bool {
+    match node {
+        Node::Send(send) if send.recv.is_none() => send.method_name == "cookies",
+        Node::Send(send) => {
+            matches!(
+                send.method_name.as_str(),
+                "signed" | "encrypted" | "permanent"
+            ) && send.recv.as_deref().is_some_and(is_cookies)
+        }
+        _ => false,
+    }
+}
+
+fn string_key(node: Option<&Node>) -> Option<String> {
+    match node? {
+        Node::Sym(sym) => Some(sym.name.to_string_lossy()),
+        Node::Str(str) => Some(str.value.to_string_lossy()),
+        _ => None,
+    }
+}
+
+/// rack names headers `HTTP_X_API_KEY` for `X-Api-Key`
+fn env_header(name: &str) -> Option<String> {
+    let name = name
+        .strip_prefix("HTTP_")
+        .or_else(|| Some(name).filter(|name| *name == "CONTENT_TYPE"))?;
+    Some(
+        name.split('_')
+            .map(|word| {
+                let word = word.to_lowercase();
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("-"),
+    )
+}
+
+/// `request.headers[key]`, `cookies[key]` or `session[key]`
+fn keyed_input(recv: &Node, key: Option<String>) -> Option<Input> {
+    let key = key?;
+    match request_method(recv) {
+        Some("headers") => Some(Input::Header(key)),
+        Some("env") => env_header(&key).map(Input::Header),
+        _ if is_cookies(recv) => Some(Input::Cookie(key)),
+        _ => match recv {
+            Node::Send(send) if send.recv.is_none() && send.method_name == "session" => {
+                Some(Input::Session(key))
+            }
+            _ => None,
+        },
+    }
+}
+
+fn send_input(send: &nodes::Send) -> Option<Input> {
+    let method = send.method_name.as_str();
+    let recv = send.recv.as_deref();
+
+    match recv {
+        Some(recv) if matches!(method, "fetch" | "key?" | "[]") => {
+            keyed_input(recv, string_key(send.args.first()))
+        }
+        Some(recv) if is_request(Some(recv)) => match method {
+            "authorization" => Some(Input::Header("Authorization".to_string())),
+            "user_agent" => Some(Input::Header("User-Agent".to_string())),
+            "raw_post" => Some(Input::RawBody),
+            _ => None,
+        },
+        // `request.body.read`
+        Some(recv) if method == "read" && request_method(recv) == Some("body") => {
+            Some(Input::RawBody)
+        }
+        Some(_) => None,
+        None => match method {
+            "authenticate_with_http_token" | "authenticate_or_request_with_http_token" => {
+                Some(Input::BearerAuth)
+            }
+            "authenticate_with_http_basic" | "authenticate_or_request_with_http_basic" => {
+                Some(Input::BasicAuth)
+            }
+            _ => None,
+        },
+    }
+}
+
+fn input(node: &Node) -> Option<Input> {
+    match node {
+        Node::Send(send) => send_input(send),
+        Node::Index(index) => keyed_input(&index.recv, string_key(index.indexes.first())),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct InputSearch {
+    inputs: Vec<Input>,
+}
+
+impl InputSearch {
+    fn push(&mut self, input: Input) {
+        if !self.inputs.contains(&input) {
+            self.inputs.push(input);
+        }
+    }
+}
+
+impl Visitor for InputSearch {
+    fn on_send(&mut self, send: &nodes::Send) {
+        // `JSON.parse(request.raw_post)` reads the raw body as JSON
+        if send.method_name == "parse" && is_const(send.recv.as_deref(), "JSON") {
+            if let Some(Input::RawBody) = send.args.first().and_then(input) {
+                self.push(Input::JsonBody);
+                return;
+            }
+        }
+
+        if let Some(input) = send_input(send) {
+            self.push(input);
+            return;
+        }
+
+        if let Some(recv) = &send.recv {
+            self.visit(recv);
+        }
+        self.visit_all(&send.args);
+    }
+
+    fn on_index(&mut self, index: &nodes::Index) {
+        match keyed_input(&index.recv, string_key(index.indexes.first())) {
+            Some(input) => self.push(input),
+            None => {
+                self.visit(&index.recv);
+                self.visit_all(&index.indexes);
+            }
+        }
+    }
+}
+
+/// Headers, cookies and raw bodies a method body reads from the request
+pub fn search_for_inputs(body: &Node) -> Vec<Input> {
+    let mut search = InputSearch::default();
+    search.visit(body);
+    search.inputs
+}
+
+/// Works out where each of an action's params comes from for a route, dynamic segments of the uri
+/// are path params and everything else is sent in the query string or body depending on the verb
+pub fn classify_params(request: &Request, method: &Method) -> Vec<Param> {
+    let strong_params = method.strong_params.as_ref();
+    let param_type = |name: &str| {
+        method
+            .param_types
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, param_type)| param_type.clone())
+            .or_else(|| strong_params.and_then(|strong_params| strong_params.key_type(name)))
+            .unwrap_or(Type::String)
+    };
+
+    let mut classified = request
+        .path_params()
+        .into_iter()
+        .map(|name| Param {
+            param_type: param_type(&name),
+            name,
+            location: ParamLocation::Path,
+        })
+        .collect::<Vec<Param>>();
+
+    let location = if request.method.has_body() {
+        ParamLocation::Body
+    } else {
+        ParamLocation::Query
+    };
+    let keys = strong_params.map(|s| s.keys()).unwrap_or_default();
+    for name in method.params.iter().chain(keys.iter()) {
+        if !classified.iter().any(|param| &param.name == name) {
+            classified.push(Param {
+                name: name.clone(),
+                location,
+                param_type: param_type(name),
+            });
+        }
+    }
+
+    classified
+}
 
 fn search_for_param_in_list(statements: Vec<Node>, buf: &mut VecDeque<Box<Node>>) {
     for stat in statements {
@@ -15,7 +391,7 @@ fn optional_thing(body: &Option<Box<Node>>, buf: &mut VecDeque<Box<Node>>) {
 }
 
 // doesn't support inline methods and singleton classes
-// search for: param, payload, headers
+// search for: param, payload and headers are found by search_for_inputs
 pub fn search_for_param(statement: Box<Node>) -> HashSet<String> {
     let mut params = HashSet::new();
     let mut buf = VecDeque::new();
@@ -126,11 +502,8 @@ pub fn search_for_param(statement: Box<Node>) -> HashSet<String> {
                     Node::Const(con) => {
                         if con.name == "params" {
                             for index in stat.indexes {
-                                match index {
-                                    Node::Sym(value) => {
-                                        params.insert(value.name.to_string_lossy());
-                                    }
-                                    _ => {}
+                                if let Node::Sym(value) = index {
+                                    params.insert(value.name.to_string_lossy());
                                 }
                             }
                         }
@@ -138,11 +511,8 @@ pub fn search_for_param(statement: Box<Node>) -> HashSet<String> {
                     Node::Send(send) => {
                         if send.method_name == "params" {
                             for index in stat.indexes {
-                                match index {
-                                    Node::Sym(value) => {
-                                        params.insert(value.name.to_string_lossy());
-                                    }
-                                    _ => {}
+                                if let Node::Sym(value) = index {
+                                    params.insert(value.name.to_string_lossy());
                                 }
                             }
                         } else {
@@ -181,8 +551,6 @@ pub fn search_for_param(statement: Box<Node>) -> HashSet<String> {
             // Node::Line(stat) => {}
 
             // Node::Lvar(stat) => {}
-
-            // specail case for headers and payload!!!!
             Node::Lvasgn(stat) => optional_thing(&stat.value, &mut buf),
 
             Node::Masgn(stat) => {
@@ -266,11 +634,8 @@ pub fn search_for_param(statement: Box<Node>) -> HashSet<String> {
                     if let Node::Send(send_param) = *recv {
                         if send_param.method_name == "params" {
                             for arg in stat.args {
-                                match arg {
-                                    Node::Sym(value) => {
-                                        params.insert(value.name.to_string_lossy());
-                                    }
-                                    _ => {}
+                                if let Node::Sym(value) = arg {
+                                    params.insert(value.name.to_string_lossy());
                                 }
                             }
                         } else {
@@ -329,7 +694,16 @@ mod params_tests {
     use lib_ruby_parser::Parser;
     use pretty_assertions::assert_eq;
 
-    use super::search_for_param;
+    use super::{
+        classify_params, search_for_inputs, search_for_param, search_for_param_types, Input, Param,
+        ParamLocation,
+    };
+    use crate::{
+        routes::{Request, RequestMethod},
+        ruby_parser::Method,
+        strong_params::{Permitted, StrongParams},
+        types::Type,
+    };
 
     fn helper(input: &str) -> String {
         let mut results = search_for_param(Box::new(
@@ -341,7 +715,7 @@ mod params_tests {
         .into_iter()
         .collect::<Vec<String>>();
         results.sort();
-        return results.join(", ");
+        results.join(", ")
     }
 
     #[test]
@@ -403,4 +777,120 @@ mod params_tests {
             "cat, id"
         );
     }
+
+    #[test]
+    fn locations() {
+        let request = |method| Request {
+            method,
+            prefix: "".to_string(),
+            uri: "/dogs/:dog_id/styles/:id".to_string(),
+            controller: "styles".to_string(),
+            action: "update".to_string(),
+        };
+        let method = Method {
+            params: vec!["id".to_string(), "page".to_string()],
+            param_types: vec![("id".to_string(), Type::Id)],
+            ..Default::default()
+        };
+        let param = |name: &str, location, param_type| Param {
+            name: name.to_string(),
+            location,
+            param_type,
+        };
+
+        assert_eq!(
+            classify_params(&request(RequestMethod::GET), &method),
+            vec![
+                param("dog_id", ParamLocation::Path, Type::String),
+                param("id", ParamLocation::Path, Type::Id),
+                param("page", ParamLocation::Query, Type::String),
+            ]
+        );
+
+        let method = Method {
+            strong_params: Some(StrongParams {
+                require: Some("style".to_string()),
+                permitted: vec![("name".to_string(), Permitted::Scalar)],
+            }),
+            ..method
+        };
+        assert_eq!(
+            classify_params(&request(RequestMethod::PATCH), &method),
+            vec![
+                param("dog_id", ParamLocation::Path, Type::String),
+                param("id", ParamLocation::Path, Type::Id),
+                param("page", ParamLocation::Body, Type::String),
+                param(
+                    "style",
+                    ParamLocation::Body,
+                    Type::Object(vec![("name".to_string(), Type::String)])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn types() {
+        let input = "
+        page = params[:page].to_i
+        @price = params[:price].to_f
+        @tags = params[:tags].split(',')
+        @born = Date.parse(params[:born])
+        @seen = Time.zone.parse(params[:seen])
+        @limit = Integer(params.fetch(:limit))
+        @good = ActiveModel::Type::Boolean.new.cast(params[:good])
+        @all = true if params[:all] == 'true'
+        @dog = Dog.find(params[:id])
+        @page = params[:page]
+        ";
+
+        assert_eq!(
+            search_for_param_types(
+                &Parser::new(input.as_bytes(), Default::default())
+                    .do_parse()
+                    .ast
+                    .unwrap()
+            ),
+            vec![
+                ("page".to_string(), Type::Integer),
+                ("price".to_string(), Type::Number),
+                ("tags".to_string(), Type::Array(Box::new(Type::String))),
+                ("born".to_string(), Type::Date),
+                ("seen".to_string(), Type::DateTime),
+                ("limit".to_string(), Type::Integer),
+                ("good".to_string(), Type::Boolean),
+                ("all".to_string(), Type::Boolean),
+                ("id".to_string(), Type::Id),
+            ]
+        );
+    }
+
+    #[test]
+    fn inputs() {
+        let input = "
+        token = request.headers['X-Api-Key'] || request.authorization
+        @agent = request.env['HTTP_USER_AGENT']
+        @user = User.find(cookies.signed[:user_id] || session[:user_id])
+        @payload = JSON.parse(request.body.read)
+        authenticate_or_request_with_http_token { |token, options| token == 'secret' }
+        ";
+
+        assert_eq!(
+            search_for_inputs(
+                &Parser::new(input.as_bytes(), Default::default())
+                    .do_parse()
+                    .ast
+                    .unwrap()
+            ),
+            vec![
+                Input::Header("X-Api-Key".to_string()),
+                Input::Header("Authorization".to_string()),
+                Input::Header("User-Agent".to_string()),
+                Input::Cookie("user_id".to_string()),
+                Input::Session("user_id".to_string()),
+                Input::JsonBody,
+                Input::BearerAuth,
+            ]
+        );
+    }
 }
@@ -1,26 +1,457 @@
-/**
-TODO: second round of parisng
-not sure how to implement quite yet but going to try and get some tests written first and go from there...
+/*
+second round of parsing, each file has been parsed on its own so now controllers can be resolved against the rest of the project:
+- methods inherited through the parent chain or pulled in from included concerns
+- callbacks (before_action etc.) attached to the actions they run for, along with their responses and params
+*/
 
-hopefully refactoring will only be needed for the helper function
+use std::collections::{HashMap, HashSet};
 
-*/
+use crate::{
+    ivars::resolve_ivars,
+    params::Input,
+    responses::Response,
+    ruby_parser::{Action, Controller, Method, Module, RubyFile},
+    strong_params::StrongParams,
+};
+
+struct Project<'a> {
+    classes: Vec<(String, &'a Controller)>,
+    modules: Vec<(String, &'a Module)>,
+}
+
+/// finds a class or module the way ruby resolves constants, from the innermost namespace out
+/// e.g. `ApplicationController` in `Admin` tries `Admin::ApplicationController` then `ApplicationController`,
+/// a leading `::` only looks at the top level
+fn find<'a, T>(items: &[(String, &'a T)], name: &str, namespace: &str) -> Option<(String, &'a T)> {
+    let mut namespace = if name.starts_with("::") {
+        ""
+    } else {
+        namespace
+    };
+    let name = name.trim_start_matches("::");
+    loop {
+        let candidate = match namespace {
+            "" => name.to_string(),
+            namespace => format!("{}::{}", namespace, name),
+        };
+        if let Some((full_name, item)) = items.iter().find(|(full_name, _)| full_name == &candidate)
+        {
+            return Some((full_name.clone(), *item));
+        }
+        if namespace.is_empty() {
+            return None;
+        }
+        namespace = namespace.rfind("::").map_or("", |i| &namespace[..i]);
+    }
+}
+
+/// `Admin` for `Admin::DogsController`
+fn namespace_of(full_name: &str) -> &str {
+    full_name.rfind("::").map_or("", |i| &full_name[..i])
+}
+
+impl<'a> Project<'a> {
+    fn new(files: &'a [RubyFile]) -> Project<'a> {
+        let mut classes = Vec::new();
+        let mut modules = Vec::new();
+        for file in files {
+            for controller in &file.controllers {
+                classes.push((controller.name.clone(), controller));
+            }
+            for module in &file.modules {
+                let module_name = module.name.replace('.', "::");
+                for controller in &module.classes {
+                    classes.push((format!("{}::{}", module_name, controller.name), controller));
+                }
+                modules.push((module_name, module));
+            }
+        }
+
+        Project { classes, modules }
+    }
+}
+
+// methods and callbacks a controller ends up with in the order they were defined
+#[derive(Default)]
+struct Resolved {
+    methods: Vec<Method>,
+    actions: Vec<Action>,
+}
+
+impl Resolved {
+    fn add_methods(&mut self, methods: &[Method]) {
+        for method in methods {
+            // redefining a method overrides the inherited one
+            self.methods.retain(|m| m.name != method.name);
+            self.methods.push(method.clone());
+        }
+    }
+}
+
+/// `namespace` is where the `include` is written, the module is looked up from there outwards
+fn include_module(
+    project: &Project,
+    name: &str,
+    namespace: &str,
+    resolved: &mut Resolved,
+    seen: &mut HashSet<String>,
+) {
+    let (full_name, module) = match find(&project.modules, name, namespace) {
+        Some(found) => found,
+        None => return,
+    };
+    if !seen.insert(format!("module {}", full_name)) {
+        return;
+    }
 
-use crate::ruby_parser::RubyFile;
+    for include in &module.includes {
+        include_module(project, include, &full_name, resolved, seen);
+    }
+    resolved.add_methods(&module.methods);
+    resolved.actions.extend(module.actions.iter().cloned());
+}
+
+/// `full_name` is the class's name along with the modules it's nested in e.g. `Admin::DogsController`,
+/// same named classes in different namespaces are different classes
+fn resolve_class(
+    project: &Project,
+    full_name: &str,
+    controller: &Controller,
+    resolved: &mut Resolved,
+    seen: &mut HashSet<String>,
+) {
+    if !seen.insert(format!("class {}", full_name)) {
+        return;
+    }
+
+    // the superclass is written outside the class body so it's looked up from the enclosing namespace
+    if let Some((parent_name, parent)) = find(
+        &project.classes,
+        &controller.parent,
+        namespace_of(full_name),
+    ) {
+        resolve_class(project, &parent_name, parent, resolved, seen);
+    }
+    for include in &controller.includes {
+        include_module(project, include, full_name, resolved, seen);
+    }
+    resolved.add_methods(&controller.methods);
+    resolved.actions.extend(controller.actions.iter().cloned());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Before,
+    Around,
+    After,
+}
+
+/// callbacks that run for the action in the order they run, before and around callbacks share
+/// a chain while after callbacks run once the action is done in the reverse order they were declared
+pub fn callbacks_for(actions: &[Action], action: &str) -> Vec<String> {
+    let mut chain: Vec<(Kind, String)> = Vec::new();
+    for config in actions {
+        let (kind, callback, skip) = match config {
+            Action::BeforeAction(callback) => (Kind::Before, callback, false),
+            Action::AroundAction(callback) => (Kind::Around, callback, false),
+            Action::AfterAction(callback) => (Kind::After, callback, false),
+            Action::SkipBeforeAction(callback) => (Kind::Before, callback, true),
+            Action::SkipAroundAction(callback) => (Kind::Around, callback, true),
+            Action::SkipAfterAction(callback) => (Kind::After, callback, true),
+            Action::SkipAuthMethods(_) | Action::RescueFrom(_) => continue,
+        };
+        if !callback.applies_to(action) {
+            continue;
+        }
 
-pub fn parse(file: RubyFile, files: &Vec<RubyFile>) -> Result<RubyFile, String> {
-    Ok(file)
+        if skip {
+            // a skip with `if:`/`unless:` might not happen so the callback could still run
+            if !callback.is_conditional() {
+                chain.retain(|(k, method)| !(k == &kind && callback.methods.contains(method)));
+            }
+            continue;
+        }
+
+        for method in &callback.methods {
+            // declaring a callback again moves it rather than running it twice
+            chain.retain(|(k, existing)| !(k == &kind && existing == method));
+            if callback.prepend {
+                chain.insert(0, (kind, method.clone()));
+            } else {
+                chain.push((kind, method.clone()));
+            }
+        }
+    }
+
+    let (after, mut callbacks): (Vec<_>, Vec<_>) = chain
+        .into_iter()
+        .partition(|(kind, _)| kind == &Kind::After);
+    callbacks.extend(after.into_iter().rev());
+    callbacks.into_iter().map(|(_, method)| method).collect()
+}
+
+/// the methods `rescue_from` hands exceptions to, any action could end up rendering their response
+pub fn rescue_handlers(actions: &[Action]) -> Vec<String> {
+    actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::RescueFrom(rescue) => rescue.with.clone(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// swaps calls to helper methods for whatever the helper responds with,
+/// calls to anything that isn't defined on the controller can't respond so are dropped
+fn expand_calls(
+    responses: &[Response],
+    defined: &[Method],
+    seen: &mut Vec<String>,
+) -> Vec<Response> {
+    let mut expanded: Vec<Response> = Vec::new();
+    for response in responses {
+        let responses = match response {
+            Response::Call(name) if seen.contains(name) => continue,
+            Response::Call(name) => match defined.iter().find(|m| &m.name == name) {
+                Some(helper) => {
+                    seen.push(name.clone());
+                    let responses = expand_calls(&helper.returns, defined, seen);
+                    seen.pop();
+                    responses
+                }
+                None => continue,
+            },
+            response => vec![response.clone()],
+        };
+
+        for response in responses {
+            if !expanded.contains(&response) {
+                expanded.push(response);
+            }
+        }
+    }
+
+    expanded
+}
+
+/// the strong params a method permits itself or through the helpers it calls e.g. `dog_params`
+fn strong_params(
+    method: &Method,
+    defined: &[Method],
+    seen: &mut Vec<String>,
+) -> Option<StrongParams> {
+    if method.strong_params.is_some() {
+        return method.strong_params.clone();
+    }
+
+    for name in &method.calls {
+        if seen.contains(name) {
+            continue;
+        }
+        if let Some(helper) = defined.iter().find(|m| &m.name == name) {
+            seen.push(name.clone());
+            let strong_params = strong_params(helper, defined, seen);
+            seen.pop();
+            if strong_params.is_some() {
+                return strong_params;
+            }
+        }
+    }
+    None
+}
+
+/// everything a method reads from the request itself or through the helpers it calls e.g. `current_user`
+fn collect_inputs(
+    method: &Method,
+    defined: &[Method],
+    seen: &mut Vec<String>,
+    inputs: &mut Vec<Input>,
+) {
+    for input in &method.inputs {
+        if !inputs.contains(input) {
+            inputs.push(input.clone());
+        }
+    }
+
+    for name in &method.calls {
+        if seen.contains(name) {
+            continue;
+        }
+        if let Some(helper) = defined.iter().find(|m| &m.name == name) {
+            seen.push(name.clone());
+            collect_inputs(helper, defined, seen, inputs);
+        }
+    }
+}
+
+fn resolve(project: &Project, full_name: &str, controller: &Controller) -> Controller {
+    let mut resolved = Resolved::default();
+    resolve_class(
+        project,
+        full_name,
+        controller,
+        &mut resolved,
+        &mut HashSet::new(),
+    );
+
+    let defined = resolved.methods.clone();
+    for method in resolved.methods.iter_mut().filter(|m| !m.private) {
+        let name = method.name.clone();
+        method.callbacks = callbacks_for(&resolved.actions, &name);
+        method.callbacks.retain(|callback| callback != &name);
+
+        let handlers = rescue_handlers(&resolved.actions);
+        for callback in method.callbacks.iter().chain(handlers.iter()) {
+            if let Some(callback) = defined.iter().find(|m| &m.name == callback) {
+                for response in &callback.returns {
+                    if !method.returns.contains(response) {
+                        method.returns.push(response.clone());
+                    }
+                }
+                // exceptions can be raised before the handler reads any params
+                if handlers.contains(&callback.name) {
+                    continue;
+                }
+                for param in &callback.params {
+                    if !method.params.contains(param) {
+                        method.params.push(param.clone());
+                    }
+                }
+                for (param, param_type) in &callback.param_types {
+                    if !method.param_types.iter().any(|(name, _)| name == param) {
+                        method.param_types.push((param.clone(), param_type.clone()));
+                    }
+                }
+            }
+        }
+        method.params.sort();
+
+        let mut found = Vec::new();
+        let mut seen = vec![name.clone()];
+        collect_inputs(method, &defined, &mut seen, &mut found);
+        for callback in &method.callbacks {
+            if let Some(callback) = defined.iter().find(|m| &m.name == callback) {
+                seen.push(callback.name.clone());
+                collect_inputs(callback, &defined, &mut seen, &mut found);
+            }
+        }
+        method.inputs = found;
+
+        let mut ivars = Vec::new();
+        for callback in &method.callbacks {
+            if let Some(callback) = defined.iter().find(|m| &m.name == callback) {
+                ivars.extend(callback.ivars.iter().cloned());
+            }
+        }
+        ivars.extend(method.ivars.iter().cloned());
+        method.ivars = resolve_ivars(ivars, &[]);
+
+        method.strong_params = strong_params(method, &defined, &mut vec![name.clone()]);
+        method.returns = expand_calls(&method.returns, &defined, &mut vec![name]);
+    }
+
+    Controller {
+        name: controller.name.clone(),
+        parent: controller.parent.clone(),
+        methods: resolved.methods,
+        actions: resolved.actions,
+        includes: controller.includes.clone(),
+    }
+}
+
+fn reopen(class: &mut Controller, reopened: &Controller) {
+    if class.parent.is_empty() {
+        class.parent = reopened.parent.clone();
+    }
+    for method in &reopened.methods {
+        class.methods.retain(|m| m.name != method.name);
+        class.methods.push(method.clone());
+    }
+    class.actions.extend(reopened.actions.iter().cloned());
+    class.includes.extend(reopened.includes.iter().cloned());
+}
+
+/// Folds classes that are opened again, in the same file or another one, into where they were
+/// first defined, later methods replace earlier ones with the same name
+pub fn merge_reopened(mut files: Vec<RubyFile>) -> Vec<RubyFile> {
+    let mut merged: HashMap<String, Controller> = HashMap::new();
+    for (full_name, controller) in Project::new(&files).classes {
+        match merged.get_mut(&full_name) {
+            Some(class) => reopen(class, controller),
+            None => {
+                merged.insert(full_name, controller.clone());
+            }
+        }
+    }
+
+    // the first definition takes the merged class, later ones find it gone and are dropped
+    let mut take = |full_name: String| merged.remove(&full_name);
+    for file in &mut files {
+        file.controllers = std::mem::take(&mut file.controllers)
+            .into_iter()
+            .filter_map(|controller| take(controller.name))
+            .collect();
+        for module in &mut file.modules {
+            let module_name = module.name.replace('.', "::");
+            module.classes = std::mem::take(&mut module.classes)
+                .into_iter()
+                .filter_map(|controller| take(format!("{}::{}", module_name, controller.name)))
+                .collect();
+        }
+    }
+
+    files
+}
+
+/// Resolves the controllers in `file` against every file in the project
+pub fn parse(file: RubyFile, files: &[RubyFile]) -> Result<RubyFile, String> {
+    let project = Project::new(files);
+
+    Ok(RubyFile {
+        path: file.path.clone(),
+        diagnostics: file.diagnostics.clone(),
+        controllers: file
+            .controllers
+            .iter()
+            .map(|controller| resolve(&project, &controller.name, controller))
+            .collect(),
+        modules: file
+            .modules
+            .into_iter()
+            .map(|mut module| {
+                let module_name = module.name.replace('.', "::");
+                module.classes = module
+                    .classes
+                    .iter()
+                    .map(|controller| {
+                        resolve(
+                            &project,
+                            &format!("{}::{}", module_name, controller.name),
+                            controller,
+                        )
+                    })
+                    .collect();
+                module
+            })
+            .collect(),
+        requires: file.requires,
+    })
 }
 
 #[cfg(test)]
 mod second_parser {
-    use std::vec;
     use pretty_assertions::assert_eq;
+    use std::vec;
 
     use lib_ruby_parser::Parser;
 
-    use crate::parser_parser::parse;
-    use crate::ruby_parser::{parse_file, Controller, RubyFile, Method};
+    use crate::parser_parser::{merge_reopened, parse};
+    use crate::{
+        ivars::{Ivar, Value},
+        params::Input,
+        responses::Response,
+        ruby_parser::{parse_file, Method, RubyFile},
+    };
 
     fn helper(subject: &str, files: Vec<&str>) -> Result<RubyFile, String> {
         let key_file = parse_file(
@@ -28,36 +459,46 @@ mod second_parser {
                 .do_parse()
                 .ast
                 .unwrap(),
-        )?;
-        println!("ran key_file");
-        let mut data = Vec::new();
+        )
+        .map_err(|e| e.to_string())?;
+        let mut data = vec![key_file.clone()];
 
         for file in files {
-            println!("doing file: {}", file);
-            data.push(parse_file(
-                Parser::new(file.as_bytes(), Default::default())
-                    .do_parse()
-                    .ast
-                    .unwrap(),
-            )?);
+            data.push(
+                parse_file(
+                    Parser::new(file.as_bytes(), Default::default())
+                        .do_parse()
+                        .ast
+                        .unwrap(),
+                )
+                .map_err(|e| e.to_string())?,
+            );
         }
 
         parse(key_file, &data)
     }
 
-    #[ignore = "WIP"]
+    fn method<'a>(file: &'a RubyFile, name: &str) -> &'a Method {
+        file.controllers[0]
+            .methods
+            .iter()
+            .find(|method| method.name == name)
+            .unwrap()
+    }
+
     #[test]
     fn basic_integration() {
         let main = "
         class VersionController < ApplicationController
 
             def version
-                json(200, 'version', param[:cat])
+                @cat = params[:cat]
+                json(200, 'version', @cat)
             end
         end
       ";
         let files = vec![
-            "class ApplicationConroller < ActionController::API
+            "class ApplicationController < ActionController::API
             include ConResponse
             
             before_action :check_auth
@@ -67,9 +508,8 @@ mod second_parser {
             end
 
             def check_auth
-                if token == 1
-                    json(404, 'error')
-                end
+                return if token == params[:token]
+                render status: 401, json: {}
             end
           end
           ",
@@ -87,39 +527,395 @@ mod second_parser {
           end
           ",
         ];
-        let expected = RubyFile {
-            controllers: vec![Controller {
-                name: "VersionController".to_string(),
-                parent: "ApplicationController".to_string(),
-                actions: Vec::new(),
-                methods: vec![
-                    Method{
-                        name: "version".to_string(),
-                        params: vec!["cat".to_string()],
-                        returns: Vec::new(),
-                        private: false
-                    }
+        let actual = helper(main, files).unwrap();
+
+        assert_eq!(
+            actual.controllers[0]
+                .methods
+                .iter()
+                .map(|method| method.name.clone())
+                .collect::<Vec<String>>(),
+            vec!["json", "token", "check_auth", "version"]
+        );
+        assert_eq!(
+            method(&actual, "version"),
+            &Method {
+                name: "version".to_string(),
+                params: vec!["cat".to_string(), "token".to_string()],
+                param_types: Vec::new(),
+                inputs: vec![Input::Cookie("monoster".to_string())],
+                ivars: vec![Ivar {
+                    name: "@cat".to_string(),
+                    expression: "params[:cat]".to_string(),
+                    value: Value::Unknown,
+                }],
+                returns: vec![
+                    Response::Json {
+                        status: None,
+                        body: Some("json".to_string()),
+                        serializer: None,
+                        include: Vec::new(),
+                        view: None,
+                    },
+                    Response::Json {
+                        status: Some(401),
+                        body: None,
+                        serializer: None,
+                        include: Vec::new(),
+                        view: None,
+                    },
                 ],
-            }],
-            modules: Vec::new(),
-            requires: Vec::new(),
-        };
-        let actual = helper(main, files);
-        assert_eq!(actual.is_ok(), true);
-        assert_ne!(actual.unwrap(), expected, "don't know what the return object should be precisely but they should not be equal");
+                private: false,
+                callbacks: vec!["check_auth".to_string()],
+                strong_params: None,
+                calls: vec!["params".to_string(), "json".to_string()],
+            }
+        );
     }
 
     #[cfg(test)]
     mod helpers_and_concerns {
-        #[ignore = "WIP"]
+        use pretty_assertions::assert_eq;
+
+        use super::{helper, method};
+        use crate::responses::Response;
+
         #[test]
-        fn basic() {}
+        fn basic() {
+            let main = "
+            class DogsController < ApplicationController
+                include Authenticated
+
+                def show
+                    @dog = Dog.find(params[:id])
+                    render json: @dog
+                end
+            end
+            ";
+            let files = vec![
+                "
+                module Authenticated
+                    extend ActiveSupport::Concern
+
+                    included do
+                        before_action :authenticate, except: [:index]
+                    end
+
+                    def authenticate
+                        @user = User.find_by(token: params[:token])
+                        head :unauthorized
+                    end
+                end
+                ",
+            ];
+            let actual = helper(main, files).unwrap();
+
+            assert_eq!(method(&actual, "show").callbacks, vec!["authenticate"]);
+            assert_eq!(method(&actual, "show").params, vec!["id", "token"]);
+            assert_eq!(
+                method(&actual, "show").returns,
+                vec![
+                    Response::Json {
+                        status: Some(200),
+                        body: Some("@dog".to_string()),
+                        serializer: None,
+                        include: Vec::new(),
+                        view: None,
+                    },
+                    Response::Head { status: Some(401) },
+                ]
+            );
+        }
     }
 
     #[cfg(test)]
     mod inheritance_class {
-        #[ignore = "WIP"]
+        use pretty_assertions::assert_eq;
+
+        use super::helper;
+
+        #[test]
+        fn basic() {
+            let main = "
+            module Api
+                class DogsController < BaseController
+                    skip_before_action :check_auth, only: [:index]
+
+                    def index
+                        @dogs = Dog.all
+                        render json: @dogs
+                    end
+
+                    def show
+                        @dog = Dog.find(params[:id])
+                        render json: @dog
+                    end
+
+                    def destroy
+                        @dog = Dog.find(params[:id])
+                        @dog.destroy
+                    end
+                end
+            end
+            ";
+            let files = vec![
+                "
+                module Api
+                    class BaseController < ApplicationController
+                        before_action :set_locale, only: :show
+
+                        def destroy
+                            head :forbidden
+                        end
+                    end
+                end
+                ",
+                "
+                class ApplicationController < ActionController::API
+                    before_action :check_auth
+                end
+                ",
+            ];
+            let actual = helper(main, files).unwrap();
+            let controller = &actual.modules[0].classes[0];
+            let callbacks = |name: &str| {
+                controller
+                    .methods
+                    .iter()
+                    .find(|method| method.name == name)
+                    .unwrap()
+                    .callbacks
+                    .clone()
+            };
+
+            assert_eq!(callbacks("index"), Vec::<String>::new());
+            assert_eq!(callbacks("show"), vec!["check_auth", "set_locale"]);
+            assert_eq!(callbacks("destroy"), vec!["check_auth"]);
+            assert_eq!(
+                controller
+                    .methods
+                    .iter()
+                    .map(|method| method.name.as_str())
+                    .collect::<Vec<&str>>(),
+                vec!["index", "show", "destroy"]
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod callbacks {
+        use pretty_assertions::assert_eq;
+
+        use super::{helper, method};
+        use crate::responses::Response;
+
+        #[test]
+        fn chain_order() {
+            let main = "
+            class DogsController < ApplicationController
+                prepend_before_action :set_locale
+                before_action :load_dog, except: [:index]
+                around_action :wrap_in_transaction, only: [:update]
+                after_action :track, :log
+                skip_before_action :check_auth, only: [:index]
+                skip_after_action :log, if: :admin?
+                rescue_from ActiveRecord::RecordNotFound, with: :not_found
+
+                def index
+                end
+
+                def update
+                end
+
+                private
+
+                def not_found
+                    @error = 'not found'
+                    head :not_found
+                end
+            end
+            ";
+            let files = vec![
+                "class ApplicationController < ActionController::API
+                before_action :check_auth
+                after_action :cleanup
+                end",
+            ];
+            let actual = helper(main, files).unwrap();
+
+            assert_eq!(
+                method(&actual, "index").callbacks,
+                vec!["set_locale", "log", "track", "cleanup"]
+            );
+            assert_eq!(
+                method(&actual, "update").callbacks,
+                vec![
+                    "set_locale",
+                    "check_auth",
+                    "load_dog",
+                    "wrap_in_transaction",
+                    "log",
+                    "track",
+                    "cleanup"
+                ]
+            );
+            assert_eq!(
+                method(&actual, "update").returns,
+                vec![Response::Head { status: Some(404) }]
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod strong_params {
+        use pretty_assertions::assert_eq;
+
+        use super::{helper, method};
+        use crate::strong_params::{Permitted, StrongParams};
+
         #[test]
-        fn basic() {}
+        fn follows_helpers() {
+            let main = "
+            class DogsController < ApplicationController
+                def create
+                    @dog = Dog.new(dog_params)
+                    render json: @dog, status: :created
+                end
+
+                private
+
+                def dog_params
+                    params.require(:dog).permit(:name, toys: [])
+                end
+            end
+            ";
+            let actual = helper(main, vec![]).unwrap();
+
+            assert_eq!(
+                method(&actual, "create").strong_params,
+                Some(StrongParams {
+                    require: Some("dog".to_string()),
+                    permitted: vec![
+                        ("name".to_string(), Permitted::Scalar),
+                        ("toys".to_string(), Permitted::Array),
+                    ],
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn reopened_classes() {
+        let files = vec![
+            "
+            module Api
+                class DogsController < ApplicationController
+                    before_action :set_dog
+
+                    def index
+                    end
+
+                    def show
+                        head :ok
+                    end
+                end
+            end
+            ",
+            "
+            class Api::DogsController
+                def show
+                    head :not_found
+                end
+
+                def destroy
+                end
+            end
+            ",
+        ]
+        .into_iter()
+        .map(|file| {
+            parse_file(
+                Parser::new(file.as_bytes(), Default::default())
+                    .do_parse()
+                    .ast
+                    .unwrap(),
+            )
+            .unwrap()
+        })
+        .collect();
+
+        let files = merge_reopened(files);
+        assert!(files[1].controllers.is_empty());
+        let class = &files[0].modules[0].classes[0];
+        assert_eq!(class.parent, "ApplicationController");
+        assert_eq!(class.actions.len(), 1);
+        assert_eq!(
+            class
+                .methods
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["index", "show", "destroy"]
+        );
+        assert_eq!(class.methods[1].returns[0].status(), Some(404));
+    }
+
+    #[test]
+    fn namespaced_parents() {
+        let main = "
+        module Admin
+            class DogsController < ::DogsController
+                def destroy
+                end
+            end
+
+            class CatsController < ApplicationController
+                def index
+                end
+            end
+        end
+        ";
+        let files = vec![
+            "
+            class DogsController < ApplicationController
+                before_action :set_dog
+
+                def show
+                end
+            end
+            ",
+            "
+            class ApplicationController < ActionController::API
+                def current_user
+                end
+            end
+            ",
+            "
+            module Admin
+                class ApplicationController < ::ApplicationController
+                    before_action :require_admin
+                end
+            end
+            ",
+        ];
+        let file = helper(main, files).unwrap();
+        let names = |i: usize| {
+            file.modules[0].classes[i]
+                .methods
+                .iter()
+                .map(|m| m.name.clone())
+                .collect::<Vec<String>>()
+        };
+
+        // the parent has the same name in another namespace and is still inherited
+        assert_eq!(names(0), vec!["current_user", "show", "destroy"]);
+        assert_eq!(file.modules[0].classes[0].actions.len(), 1);
+
+        // `ApplicationController` inside `Admin` is `Admin::ApplicationController`
+        assert_eq!(names(1), vec!["current_user", "index"]);
+        assert_eq!(
+            file.modules[0].classes[1].methods[1].callbacks,
+            vec!["require_admin".to_string()]
+        );
     }
 }
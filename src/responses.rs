@@ -0,0 +1,641 @@
+use lib_ruby_parser::{nodes, Node};
+
+use crate::{ivars::source, views::expression};
+
+// Rack::Utils::SYMBOL_TO_STATUS_CODE
+const STATUS_CODES: [(&str, u16); 67] = [
+    ("continue", 100),
+    ("switching_protocols", 101),
+    ("processing", 102),
+    ("early_hints", 103),
+    ("ok", 200),
+    ("created", 201),
+    ("accepted", 202),
+    ("non_authoritative_information", 203),
+    ("no_content", 204),
+    ("reset_content", 205),
+    ("partial_content", 206),
+    ("multi_status", 207),
+    ("already_reported", 208),
+    ("im_used", 226),
+    ("multiple_choices", 300),
+    ("moved_permanently", 301),
+    ("found", 302),
+    ("see_other", 303),
+    ("not_modified", 304),
+    ("use_proxy", 305),
+    ("temporary_redirect", 307),
+    ("permanent_redirect", 308),
+    ("bad_request", 400),
+    ("unauthorized", 401),
+    ("payment_required", 402),
+    ("forbidden", 403),
+    ("not_found", 404),
+    ("method_not_allowed", 405),
+    ("not_acceptable", 406),
+    ("proxy_authentication_required", 407),
+    ("request_timeout", 408),
+    ("conflict", 409),
+    ("gone", 410),
+    ("length_required", 411),
+    ("precondition_failed", 412),
+    ("payload_too_large", 413),
+    ("request_entity_too_large", 413),
+    ("content_too_large", 413),
+    ("uri_too_long", 414),
+    ("request_uri_too_long", 414),
+    ("unsupported_media_type", 415),
+    ("range_not_satisfiable", 416),
+    ("requested_range_not_satisfiable", 416),
+    ("expectation_failed", 417),
+    ("misdirected_request", 421),
+    ("unprocessable_entity", 422),
+    ("unprocessable_content", 422),
+    ("locked", 423),
+    ("failed_dependency", 424),
+    ("too_early", 425),
+    ("upgrade_required", 426),
+    ("precondition_required", 428),
+    ("too_many_requests", 429),
+    ("request_header_fields_too_large", 431),
+    ("unavailable_for_legal_reasons", 451),
+    ("internal_server_error", 500),
+    ("not_implemented", 501),
+    ("bad_gateway", 502),
+    ("service_unavailable", 503),
+    ("gateway_timeout", 504),
+    ("http_version_not_supported", 505),
+    ("variant_also_negotiates", 506),
+    ("insufficient_storage", 507),
+    ("loop_detected", 508),
+    ("bandwidth_limit_exceeded", 509),
+    ("not_extended", 510),
+    ("network_authentication_required", 511),
+];
+
+/// `:unprocessable_entity` -> 422
+pub fn status_code(name: &str) -> Option<u16> {
+    STATUS_CODES
+        .iter()
+        .find(|(symbol, _)| *symbol == name)
+        .map(|(_, code)| *code)
+}
+
+/// How an action responds, statuses are `None` when they are only known at runtime
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    /// `render json: @dog, status: :created`
+    Json {
+        status: Option<u16>,
+        body: Option<String>,
+        /// `serializer: DogSerializer` or `each_serializer: DogSerializer`, otherwise it's looked up from the model
+        serializer: Option<String>,
+        /// associations asked for with `include: [:owner]`
+        include: Vec<String>,
+        /// the blueprint view to render with e.g. `DogBlueprint.render(@dog, view: :extended)`
+        view: Option<String>,
+    },
+    /// `head :no_content`
+    Head { status: Option<u16> },
+    /// `redirect_to @dog` or `redirect_back fallback_location: root_path`
+    Redirect {
+        status: Option<u16>,
+        location: Option<String>,
+    },
+    /// `render :edit`, `render template: "dogs/show"` or a plain `render` of the action's template
+    Template {
+        status: Option<u16>,
+        template: Option<String>,
+    },
+    /// `send_file` and `send_data`
+    File {
+        status: Option<u16>,
+        content_type: Option<String>,
+    },
+    /// any other render e.g. `render plain: "ok"`
+    Other { status: Option<u16>, method: String },
+    /// a method on the controller that might respond e.g. a `render_error` helper,
+    /// parser_parser swaps these for the responses of the method
+    Call(String),
+}
+
+impl Response {
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Response::Json { status, .. }
+            | Response::Head { status }
+            | Response::Redirect { status, .. }
+            | Response::Template { status, .. }
+            | Response::File { status, .. }
+            | Response::Other { status, .. } => *status,
+            Response::Call(_) => None,
+        }
+    }
+
+    /// whether anything is sent back other than the status and headers
+    pub fn has_body(&self) -> bool {
+        !matches!(self, Response::Head { .. } | Response::Redirect { .. })
+    }
+}
+
+impl std::fmt::Display for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let or_unknown =
+            |value: &Option<String>| value.clone().unwrap_or_else(|| "...".to_string());
+        match self {
+            Response::Json { body, .. } => write!(f, "render json: {}", or_unknown(body))?,
+            Response::Head { .. } => write!(f, "head")?,
+            Response::Redirect { location, .. } => {
+                write!(f, "redirect_to {}", or_unknown(location))?
+            }
+            Response::Template { template, .. } => match template {
+                Some(template) => write!(f, "render {}", template)?,
+                None => write!(f, "render")?,
+            },
+            Response::File { content_type, .. } => match content_type {
+                Some(content_type) => write!(f, "send_file type: {}", content_type)?,
+                None => write!(f, "send_file")?,
+            },
+            Response::Other { method, .. } => write!(f, "{}", method)?,
+            Response::Call(method) => return write!(f, "{}", method),
+        }
+
+        match self.status() {
+            Some(status) => write!(f, " {}", status),
+            None => write!(f, " unknown"),
+        }
+    }
+}
+
+fn value_str(node: &Node) -> Option<String> {
+    match node {
+        Node::Str(str) => Some(str.value.to_string_lossy()),
+        Node::Sym(sym) => Some(sym.name.to_string_lossy()),
+        Node::Const(_) => Some(source(node)),
+        node => expression(node, &Vec::new()),
+    }
+}
+
+/// `include: [:owner, "toys.owner"]`
+fn value_list(node: &Node) -> Vec<String> {
+    match node {
+        Node::Array(array) => array.elements.iter().filter_map(value_str).collect(),
+        node => value_str(node).into_iter().collect(),
+    }
+}
+
+/// `DogSerializer.new(@dog, include: [:owner]).serializable_hash` renders `@dog` through `DogSerializer`
+/// as does `DogBlueprint.render(@dog, view: :extended)`
+fn serializer_call(node: &Node) -> Option<(&Node, &nodes::Send)> {
+    match node {
+        Node::Send(send) => match (send.recv.as_deref(), send.method_name.as_str()) {
+            (
+                Some(recv @ Node::Const(_)),
+                "new" | "render" | "render_as_hash" | "render_as_json",
+            ) => Some((recv, send)),
+            (Some(recv), "serializable_hash" | "to_json" | "as_json" | "to_h") => {
+                serializer_call(recv)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn status(node: &Node) -> Option<u16> {
+    match node {
+        Node::Int(int) => int.value.parse().ok(),
+        Node::Sym(sym) => status_code(&sym.name.to_string_lossy()),
+        _ => None,
+    }
+}
+
+fn options(args: &[Node]) -> Vec<(String, &Node)> {
+    args.iter()
+        .filter_map(|arg| match arg {
+            Node::Kwargs(nodes::Kwargs { pairs, .. }) | Node::Hash(nodes::Hash { pairs, .. }) => {
+                Some(pairs)
+            }
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|pair| match pair {
+            Node::Pair(pair) => match &*pair.key {
+                Node::Sym(sym) => Some((sym.name.to_string_lossy(), &*pair.value)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// The response a call in an action sends, if it sends one
+pub fn parse_response(send: &nodes::Send) -> Option<Response> {
+    if send.recv.is_some() {
+        return None;
+    }
+
+    let options = options(&send.args);
+    let option = |name: &str| {
+        options
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| *value)
+    };
+    let first = send
+        .args
+        .first()
+        .filter(|arg| !matches!(arg, Node::Kwargs(_) | Node::Hash(_)));
+    let status_or = |default: u16| match option("status") {
+        Some(node) => status(node),
+        None => Some(default),
+    };
+
+    let response = match send.method_name.as_str() {
+        "render" => {
+            if let Some(body) = option("json") {
+                match serializer_call(body) {
+                    Some((serializer, call)) => {
+                        let call_options = self::options(&call.args);
+                        let call_option = |name: &str| {
+                            call_options
+                                .iter()
+                                .find(|(key, _)| key == name)
+                                .map(|(_, value)| *value)
+                        };
+                        Response::Json {
+                            status: status_or(200),
+                            body: call.args.first().and_then(value_str),
+                            serializer: Some(source(serializer)),
+                            include: call_option("include").map_or(Vec::new(), value_list),
+                            view: call_option("view").and_then(value_str),
+                        }
+                    }
+                    None => Response::Json {
+                        status: status_or(200),
+                        body: value_str(body),
+                        serializer: option("serializer")
+                            .or_else(|| option("each_serializer"))
+                            .and_then(value_str),
+                        include: option("include").map_or(Vec::new(), value_list),
+                        view: None,
+                    },
+                }
+            } else if let Some(template) = first
+                .or_else(|| option("template"))
+                .or_else(|| option("action"))
+            {
+                Response::Template {
+                    status: status_or(200),
+                    template: value_str(template),
+                }
+            } else if let Some((method, _)) = options.iter().find(|(key, _)| {
+                [
+                    "plain", "html", "body", "xml", "js", "inline", "partial", "file",
+                ]
+                .contains(&key.as_str())
+            }) {
+                Response::Other {
+                    status: status_or(200),
+                    method: format!("render {}:", method),
+                }
+            } else if option("nothing").is_some()
+                || (option("status").is_some() && options.len() == 1)
+            {
+                Response::Head {
+                    status: status_or(200),
+                }
+            } else {
+                Response::Template {
+                    status: status_or(200),
+                    template: None,
+                }
+            }
+        }
+        "head" => Response::Head {
+            status: first.and_then(status),
+        },
+        "redirect_to" => Response::Redirect {
+            status: status_or(302),
+            location: first.and_then(value_str),
+        },
+        "redirect_back" | "redirect_back_or_to" => Response::Redirect {
+            status: status_or(302),
+            location: first
+                .or_else(|| option("fallback_location"))
+                .and_then(value_str),
+        },
+        "send_file" | "send_data" => Response::File {
+            status: status_or(200),
+            content_type: option("type").and_then(value_str),
+        },
+        method => Response::Call(method.to_string()),
+    };
+
+    Some(response)
+}
+
+fn push_responses(node: &Node, responses: &mut Vec<Response>) {
+    match node {
+        Node::Send(send) => {
+            if let Some(response) = parse_response(send) {
+                if !responses.contains(&response) {
+                    responses.push(response);
+                }
+            }
+        }
+        Node::Begin(nodes::Begin { statements, .. })
+        | Node::KwBegin(nodes::KwBegin { statements, .. }) => {
+            for statement in statements {
+                push_responses(statement, responses);
+            }
+        }
+        // `render json: @dog and return`
+        Node::And(nodes::And { lhs, rhs, .. }) | Node::Or(nodes::Or { lhs, rhs, .. }) => {
+            push_responses(lhs, responses);
+            push_responses(rhs, responses);
+        }
+        Node::Return(ret) => {
+            for arg in &ret.args {
+                push_responses(arg, responses);
+            }
+        }
+        // each branch is a response the action could send
+        Node::If(nodes::If {
+            if_true, if_false, ..
+        })
+        | Node::IfMod(nodes::IfMod {
+            if_true, if_false, ..
+        }) => {
+            push_optional(if_true, responses);
+            push_optional(if_false, responses);
+        }
+        Node::IfTernary(ternary) => {
+            push_responses(&ternary.if_true, responses);
+            push_responses(&ternary.if_false, responses);
+        }
+        Node::Case(case) => {
+            for when in &case.when_bodies {
+                push_responses(when, responses);
+            }
+            push_optional(&case.else_body, responses);
+        }
+        Node::When(when) => push_optional(&when.body, responses),
+        Node::CaseMatch(case) => {
+            for in_body in &case.in_bodies {
+                push_responses(in_body, responses);
+            }
+            push_optional(&case.else_body, responses);
+        }
+        Node::InPattern(in_pattern) => push_optional(&in_pattern.body, responses),
+        Node::Rescue(rescue) => {
+            push_optional(&rescue.body, responses);
+            for rescue_body in &rescue.rescue_bodies {
+                push_responses(rescue_body, responses);
+            }
+            push_optional(&rescue.else_, responses);
+        }
+        Node::RescueBody(rescue_body) => push_optional(&rescue_body.body, responses),
+        Node::Ensure(ensure) => {
+            push_optional(&ensure.body, responses);
+            push_optional(&ensure.ensure, responses);
+        }
+        Node::While(nodes::While { body, .. }) | Node::Until(nodes::Until { body, .. }) => {
+            push_optional(body, responses)
+        }
+        // `respond_to do |format| format.json { render json: @dog } end`
+        Node::Block(block) => push_optional(&block.body, responses),
+        Node::Numblock(block) => push_responses(&block.body, responses),
+        _ => {}
+    }
+}
+
+fn push_optional(node: &Option<Box<Node>>, responses: &mut Vec<Response>) {
+    if let Some(node) = node {
+        push_responses(node, responses);
+    }
+}
+
+/// Every response an action body could send, searching through conditionals, blocks and rescues
+pub fn search_for_responses(body: &Node) -> Vec<Response> {
+    let mut responses = Vec::new();
+    push_responses(body, &mut responses);
+    responses
+}
+
+#[cfg(test)]
+mod responses_tests {
+    use lib_ruby_parser::{Node, Parser};
+    use pretty_assertions::assert_eq;
+
+    use super::{parse_response, search_for_responses, Response};
+
+    fn helper(input: &str) -> Option<Response> {
+        match Parser::new(input.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap()
+        {
+            Node::Send(send) => parse_response(&send),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn renders() {
+        assert_eq!(
+            helper("render json: @dog, status: :created"),
+            Some(Response::Json {
+                status: Some(201),
+                body: Some("@dog".to_string()),
+                serializer: None,
+                include: Vec::new(),
+                view: None,
+            })
+        );
+        assert_eq!(
+            helper("render :status => 422, :json => @dog.errors"),
+            Some(Response::Json {
+                status: Some(422),
+                body: Some("@dog.errors".to_string()),
+                serializer: None,
+                include: Vec::new(),
+                view: None,
+            })
+        );
+        assert_eq!(
+            helper("render json: { error: 'missing' }, status: code"),
+            Some(Response::Json {
+                status: None,
+                body: None,
+                serializer: None,
+                include: Vec::new(),
+                view: None,
+            })
+        );
+        assert_eq!(
+            helper("render json: @dogs, each_serializer: Api::DogSerializer"),
+            Some(Response::Json {
+                status: Some(200),
+                body: Some("@dogs".to_string()),
+                serializer: Some("Api::DogSerializer".to_string()),
+                include: Vec::new(),
+                view: None,
+            })
+        );
+        assert_eq!(
+            helper("render json: DogSerializer.new(@dog, include: [:owner, 'toys.owner']).serializable_hash"),
+            Some(Response::Json {
+                status: Some(200),
+                body: Some("@dog".to_string()),
+                serializer: Some("DogSerializer".to_string()),
+                include: vec!["owner".to_string(), "toys.owner".to_string()],
+                view: None,
+            })
+        );
+        assert_eq!(
+            helper("render json: DogBlueprint.render(@dogs, view: :extended), status: :ok"),
+            Some(Response::Json {
+                status: Some(200),
+                body: Some("@dogs".to_string()),
+                serializer: Some("DogBlueprint".to_string()),
+                include: Vec::new(),
+                view: Some("extended".to_string()),
+            })
+        );
+        assert_eq!(
+            helper("render :edit, status: :unprocessable_entity"),
+            Some(Response::Template {
+                status: Some(422),
+                template: Some("edit".to_string())
+            })
+        );
+        assert_eq!(
+            helper("render template: 'dogs/show'"),
+            Some(Response::Template {
+                status: Some(200),
+                template: Some("dogs/show".to_string())
+            })
+        );
+        assert_eq!(
+            helper("render plain: 'ok'"),
+            Some(Response::Other {
+                status: Some(200),
+                method: "render plain:".to_string()
+            })
+        );
+        assert_eq!(
+            helper("render status: 401"),
+            Some(Response::Head { status: Some(401) })
+        );
+    }
+
+    #[test]
+    fn other_responses() {
+        assert_eq!(
+            helper("head :no_content"),
+            Some(Response::Head { status: Some(204) })
+        );
+        assert_eq!(
+            helper("redirect_to @dog, status: :see_other"),
+            Some(Response::Redirect {
+                status: Some(303),
+                location: Some("@dog".to_string())
+            })
+        );
+        assert_eq!(
+            helper("send_data csv, type: 'text/csv', filename: 'dogs.csv'"),
+            Some(Response::File {
+                status: Some(200),
+                content_type: Some("text/csv".to_string())
+            })
+        );
+        assert_eq!(
+            helper("render_error 'missing'"),
+            Some(Response::Call("render_error".to_string()))
+        );
+        assert_eq!(helper("@dog.save"), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            helper("render json: @dog, status: :created")
+                .unwrap()
+                .to_string(),
+            "render json: @dog 201"
+        );
+        assert_eq!(helper("head code").unwrap().to_string(), "head unknown");
+    }
+
+    #[test]
+    fn nested_responses() {
+        let input = "
+        def update
+            @dog = Dog.find(params[:id])
+            head :forbidden and return unless current_user.admin?
+
+            respond_to do |format|
+                if @dog.update(dog_params)
+                    format.html { redirect_to @dog }
+                    format.json { render :show, status: :ok }
+                else
+                    format.json { render json: @dog.errors, status: :unprocessable_entity }
+                end
+            end
+
+            case @dog.kind
+            when 'puppy' then render json: @dog, status: :created
+            else head :no_content
+            end
+        rescue ActiveRecord::RecordNotFound
+            render json: { error: 'not found' }, status: 404
+        end
+        ";
+        let body = match Parser::new(input.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap()
+        {
+            Node::Def(def) => def.body.unwrap(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            search_for_responses(&body),
+            vec![
+                Response::Head { status: Some(403) },
+                Response::Redirect {
+                    status: Some(302),
+                    location: Some("@dog".to_string())
+                },
+                Response::Template {
+                    status: Some(200),
+                    template: Some("show".to_string())
+                },
+                Response::Json {
+                    status: Some(422),
+                    body: Some("@dog.errors".to_string()),
+                    serializer: None,
+                    include: Vec::new(),
+                    view: None,
+                },
+                Response::Json {
+                    status: Some(201),
+                    body: Some("@dog".to_string()),
+                    serializer: None,
+                    include: Vec::new(),
+                    view: None,
+                },
+                Response::Head { status: Some(204) },
+                Response::Json {
+                    status: Some(404),
+                    body: None,
+                    serializer: None,
+                    include: Vec::new(),
+                    view: None,
+                },
+            ]
+        );
+    }
+}
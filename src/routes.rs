@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RequestMethod {
     GET,
     POST,
@@ -21,20 +21,70 @@ impl FromStr for RequestMethod {
             "PUT" => RequestMethod::PUT,
             "PATCH" => RequestMethod::PATCH,
             "OPTIONS" => RequestMethod::OPTIONS,
-            _ => Err(format!("unknown Request method '{}'", s.clone()))?,
+            _ => Err(format!("unknown Request method '{}'", s))?,
         })
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl RequestMethod {
+    pub fn lowercase(&self) -> &'static str {
+        match self {
+            RequestMethod::GET => "get",
+            RequestMethod::POST => "post",
+            RequestMethod::DELETE => "delete",
+            RequestMethod::PUT => "put",
+            RequestMethod::PATCH => "patch",
+            RequestMethod::OPTIONS => "options",
+        }
+    }
+
+    /// whether params are sent in the request body rather than the query string
+    pub fn has_body(&self) -> bool {
+        matches!(
+            self,
+            RequestMethod::POST | RequestMethod::PUT | RequestMethod::PATCH
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Request {
-    method: RequestMethod,
-    prefix: String,
-    uri: String,
+    pub method: RequestMethod,
+    pub prefix: String,
+    pub uri: String,
     pub controller: String,
     pub action: String,
 }
 
+fn path_segment_name(segment: &str) -> Option<&str> {
+    segment
+        .strip_prefix(':')
+        .or_else(|| segment.strip_prefix('*'))
+}
+
+impl Request {
+    /// names of the dynamic segments in the uri e.g. `dog_id` for `/dogs/:dog_id/styles`
+    pub fn path_params(&self) -> Vec<String> {
+        self.uri
+            .split('/')
+            .filter_map(path_segment_name)
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    /// rebuilds the uri with every dynamic segment replaced by `f(name)`
+    pub fn map_path_params<F: Fn(&str) -> String>(&self, f: F) -> String {
+        self.uri
+            .split('/')
+            .map(|segment| match path_segment_name(segment) {
+                Some(name) => f(name),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+}
+
 impl std::fmt::Display for Request {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?} {}", self.method, self.uri)
@@ -47,10 +97,9 @@ pub fn parse_routes(input: &str) -> Result<Vec<Request>, String> {
     } else {
         let mut routes = Vec::new();
         println!("lines {}", input.lines().count());
-        let foo: Vec<Vec<String>> = input
+        let rows: Vec<Vec<String>> = input
             .lines()
             .skip(1)
-            .into_iter()
             .map(|f| {
                 f.split_whitespace()
                     .map(|e| e.to_string())
@@ -60,60 +109,60 @@ pub fn parse_routes(input: &str) -> Result<Vec<Request>, String> {
             .collect();
 
         // this ugly mess is grabbing the valid feilds but ignoring the last one if an extra resource thing is added on to the end as I don't know what it does
-        for i in 0..foo.len() {
-            if foo[i].len() == 5 {
-            } else if foo[i].len() == 4 {
-                if let Ok(temp2) = RequestMethod::from_str(&foo[i][0]) {
-                    let temp = foo[i][2].split("#").collect::<Vec<&str>>();
+        for row in &rows {
+            if row.len() == 5 {
+            } else if row.len() == 4 {
+                if let Ok(temp2) = RequestMethod::from_str(&row[0]) {
+                    let temp = row[2].split("#").collect::<Vec<&str>>();
                     if temp.len() != 2 {
                         Err(format!(
                             "could not find action on the contorller {}",
-                            foo[i][2]
+                            row[2]
                         ))?;
                     }
 
                     routes.push(Request {
                         method: temp2,
                         prefix: "".to_string(),
-                        uri: foo[i][1].replace("(.:format)", ""),
+                        uri: row[1].replace("(.:format)", ""),
                         controller: temp[0].to_string(),
                         action: temp[1].to_string(),
                     })
                 } else {
-                    let temp = foo[i][3].split("#").collect::<Vec<&str>>();
+                    let temp = row[3].split("#").collect::<Vec<&str>>();
                     if temp.len() != 2 {
                         Err(format!(
                             "could not find action on the contorller {}",
-                            foo[i][3]
+                            row[3]
                         ))?;
                     }
 
                     routes.push(Request {
-                        method: RequestMethod::from_str(&foo[i][1])?,
-                        prefix: foo[i][0].clone(),
-                        uri: foo[i][2].replace("(.:format)", ""),
+                        method: RequestMethod::from_str(&row[1])?,
+                        prefix: row[0].clone(),
+                        uri: row[2].replace("(.:format)", ""),
                         controller: temp[0].to_string(),
                         action: temp[1].to_string(),
                     })
                 }
-            } else if foo[i].len() == 3 {
-                let temp = foo[i][2].split("#").collect::<Vec<&str>>();
+            } else if row.len() == 3 {
+                let temp = row[2].split("#").collect::<Vec<&str>>();
                 if temp.len() != 2 {
                     Err(format!(
                         "could not find action on the contorller {}",
-                        foo[i][2]
+                        row[2]
                     ))?;
                 }
 
                 routes.push(Request {
-                    method: RequestMethod::from_str(&foo[i][0])?,
+                    method: RequestMethod::from_str(&row[0])?,
                     prefix: "".to_string(),
-                    uri: foo[i][1].replace("(.:format)", ""),
+                    uri: row[1].replace("(.:format)", ""),
                     controller: temp[0].to_string(),
                     action: temp[1].to_string(),
                 })
             } else {
-                println!("panic {:?}", foo[i]);
+                println!("panic {:?}", row);
             }
         }
 
@@ -140,7 +189,7 @@ mod routes_parsing {
        new_dog_style GET     /dogs/:dog_id/styles/new(.:format)                                                 dogs/styles#new
         ";
 
-        assert_eq!(parse_routes(input).is_ok(), true, "successfully parse");
+        assert!(parse_routes(input).is_ok(), "successfully parse");
         assert_eq!(parse_routes(input).unwrap().len(), 8);
         assert_eq!(
             parse_routes(input).unwrap()[0],
@@ -0,0 +1,980 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use lib_ruby_parser::{nodes, traverse::Visitor, Node, Parser};
+
+use crate::{
+    diagnostics::{Diagnostic, UNSUPPORTED_ROUTE},
+    inflection::{pluralize, singularize},
+    routes::{Request, RequestMethod},
+};
+
+const RESOURCES_ACTIONS: [&str; 7] = [
+    "index", "create", "new", "edit", "show", "update", "destroy",
+];
+const RESOURCE_ACTIONS: [&str; 6] = ["create", "new", "edit", "show", "update", "destroy"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Level {
+    Scope,
+    Resource,
+    Member,
+    Collection,
+}
+
+#[derive(Debug, Clone)]
+struct Resource {
+    controller: String,
+    collection_path: String,
+    member_path: String,
+    nested_path: String,
+    collection_name: String,
+    member_name: String,
+}
+
+#[derive(Debug, Clone)]
+struct Scope {
+    path: String,
+    module: String,
+    name: String,
+    controller: Option<String>,
+    resource: Option<Resource>,
+    level: Level,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Scope {
+            path: "".to_string(),
+            module: "".to_string(),
+            name: "".to_string(),
+            controller: None,
+            resource: None,
+            level: Level::Scope,
+        }
+    }
+}
+
+impl Scope {
+    /// path and name that anything declared at this point is nested under
+    fn base(&self) -> (String, String) {
+        match (&self.resource, self.level) {
+            (None, _) | (_, Level::Scope) => (self.path.clone(), self.name.clone()),
+            (Some(resource), Level::Resource) => {
+                (resource.nested_path.clone(), resource.member_name.clone())
+            }
+            (Some(resource), Level::Member) => {
+                (resource.member_path.clone(), resource.member_name.clone())
+            }
+            (Some(resource), Level::Collection) => (
+                resource.collection_path.clone(),
+                resource.collection_name.clone(),
+            ),
+        }
+    }
+}
+
+/// positional arguments and keyword options of a route call
+struct Args {
+    positional: Vec<Node>,
+    options: Vec<(String, Node)>,
+    // `get 'dogs/:id' => 'dogs#show'`
+    rocket: Option<(Node, Node)>,
+}
+
+impl Args {
+    fn new(args: &[Node]) -> Args {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut rocket = None;
+        for arg in args {
+            match arg {
+                Node::Kwargs(nodes::Kwargs { pairs, .. })
+                | Node::Hash(nodes::Hash { pairs, .. }) => {
+                    for pair in pairs {
+                        if let Node::Pair(pair) = pair {
+                            match &*pair.key {
+                                Node::Sym(sym) => options
+                                    .push((sym.name.to_string_lossy(), (*pair.value).clone())),
+                                key => rocket = Some((key.clone(), (*pair.value).clone())),
+                            }
+                        }
+                    }
+                }
+                _ => positional.push(arg.clone()),
+            }
+        }
+
+        Args {
+            positional,
+            options,
+            rocket,
+        }
+    }
+
+    fn option(&self, name: &str) -> Option<&Node> {
+        self.options
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    fn option_str(&self, name: &str) -> Option<String> {
+        self.option(name).and_then(value_str)
+    }
+
+    fn option_list(&self, name: &str) -> Option<Vec<String>> {
+        self.option(name).map(value_list)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.positional.iter().filter_map(value_str).collect()
+    }
+}
+
+fn value_str(node: &Node) -> Option<String> {
+    match node {
+        Node::Sym(sym) => Some(sym.name.to_string_lossy()),
+        Node::Str(str) => Some(str.value.to_string_lossy()),
+        _ => None,
+    }
+}
+
+fn value_list(node: &Node) -> Vec<String> {
+    match node {
+        Node::Array(array) => array.elements.iter().filter_map(value_str).collect(),
+        node => value_str(node).into_iter().collect(),
+    }
+}
+
+fn join_path(base: &str, segment: &str) -> String {
+    let segment = segment.trim_matches('/');
+    if segment.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}/{}", base.trim_end_matches('/'), segment)
+    }
+}
+
+fn join_name(base: &str, name: &str) -> String {
+    let name = name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "_");
+    match (base.is_empty(), name.is_empty()) {
+        (true, _) => name,
+        (_, true) => base.to_string(),
+        _ => format!("{}_{}", base, name),
+    }
+}
+
+fn join_module(base: &str, module: &str) -> String {
+    if base.is_empty() {
+        module.to_string()
+    } else if module.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}/{}", base, module)
+    }
+}
+
+fn verbs(method: &str, args: &Args) -> Result<Vec<RequestMethod>, String> {
+    if method != "match" {
+        return Ok(vec![RequestMethod::from_str(&method.to_uppercase())?]);
+    }
+
+    let via = args
+        .option_list("via")
+        .ok_or("match is missing a via: option")?;
+    if via.iter().any(|verb| verb == "all") {
+        return Ok(vec![
+            RequestMethod::GET,
+            RequestMethod::POST,
+            RequestMethod::PUT,
+            RequestMethod::PATCH,
+            RequestMethod::DELETE,
+        ]);
+    }
+    via.iter()
+        .map(|verb| RequestMethod::from_str(&verb.to_uppercase()))
+        .collect()
+}
+
+#[derive(Default)]
+struct Interpreter {
+    /// `config.api_only = true` leaves `new` and `edit` out of `resources`
+    api_only: bool,
+    routes: Vec<Request>,
+    names: HashSet<String>,
+    concerns: HashMap<String, Option<Node>>,
+    /// calls that couldn't be evaluated, the routes they'd draw are left out
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Interpreter {
+    fn push(
+        &mut self,
+        method: RequestMethod,
+        name: &str,
+        uri: &str,
+        controller: &str,
+        action: &str,
+    ) {
+        // rails only names a route the first time the name is used
+        let prefix = if !name.is_empty() && self.names.insert(name.to_string()) {
+            name.to_string()
+        } else {
+            "".to_string()
+        };
+
+        self.routes.push(Request {
+            method,
+            prefix,
+            uri: if uri.is_empty() {
+                "/".to_string()
+            } else {
+                uri.to_string()
+            },
+            controller: controller.to_string(),
+            action: action.to_string(),
+        });
+    }
+
+    fn visit_body(&mut self, body: &Option<Node>, scope: &Scope) -> Result<(), String> {
+        if let Some(body) = body {
+            self.visit(body, scope)?;
+        }
+        Ok(())
+    }
+
+    fn visit(&mut self, node: &Node, scope: &Scope) -> Result<(), String> {
+        match node {
+            Node::Begin(begin) => {
+                for statement in &begin.statements {
+                    self.visit(statement, scope)?;
+                }
+                Ok(())
+            }
+            Node::Block(block) => {
+                if let Node::Send(send) = &*block.call {
+                    let body = block.body.as_ref().map(|b| (**b).clone());
+                    let result = self.call(send, &body, scope);
+                    self.skip_failed(node, result);
+                }
+                Ok(())
+            }
+            Node::Send(send) => {
+                let result = self.call(send, &None, scope);
+                self.skip_failed(node, result);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // a call that can't be evaluated is skipped so the rest of the file is still read
+    fn skip_failed(&mut self, node: &Node, result: Result<(), String>) {
+        if let Err(message) = result {
+            self.diagnostics
+                .push(Diagnostic::warning(UNSUPPORTED_ROUTE, message).at(node));
+        }
+    }
+
+    fn call(
+        &mut self,
+        send: &nodes::Send,
+        body: &Option<Node>,
+        scope: &Scope,
+    ) -> Result<(), String> {
+        let args = Args::new(&send.args);
+
+        match send.method_name.as_str() {
+            "draw" | "constraints" | "defaults" | "nested" => self.visit_body(body, scope),
+            "namespace" => {
+                let name = args.names().pop().ok_or("namespace is missing a name")?;
+                let (path, base_name) = scope.base();
+                let scope = Scope {
+                    path: join_path(
+                        &path,
+                        &args.option_str("path").unwrap_or_else(|| name.clone()),
+                    ),
+                    module: join_module(
+                        &scope.module,
+                        &args.option_str("module").unwrap_or_else(|| name.clone()),
+                    ),
+                    name: join_name(&base_name, &args.option_str("as").unwrap_or(name)),
+                    controller: None,
+                    resource: None,
+                    level: Level::Scope,
+                };
+                self.visit_body(body, &scope)
+            }
+            "scope" => {
+                let (path, base_name) = scope.base();
+                let segment = args
+                    .option_str("path")
+                    .or_else(|| args.names().pop())
+                    .unwrap_or_default();
+                let scope = Scope {
+                    path: join_path(&path, &segment),
+                    module: join_module(
+                        &scope.module,
+                        &args.option_str("module").unwrap_or_default(),
+                    ),
+                    name: join_name(&base_name, &args.option_str("as").unwrap_or_default()),
+                    controller: args
+                        .option_str("controller")
+                        .or_else(|| scope.controller.clone()),
+                    resource: None,
+                    level: Level::Scope,
+                };
+                self.visit_body(body, &scope)
+            }
+            "controller" => {
+                let mut scope = scope.clone();
+                scope.controller = args.names().pop();
+                self.visit_body(body, &scope)
+            }
+            "resources" | "resource" => {
+                let singular = send.method_name == "resource";
+                for name in args.names() {
+                    self.resources(&name, singular, &args, body, scope)?;
+                }
+                Ok(())
+            }
+            "member" | "collection" => {
+                if scope.resource.is_none() {
+                    Err(format!("{} used outside of resources", send.method_name))?
+                }
+                let mut scope = scope.clone();
+                scope.level = if send.method_name == "member" {
+                    Level::Member
+                } else {
+                    Level::Collection
+                };
+                self.visit_body(body, &scope)
+            }
+            "concern" => {
+                let name = args.names().pop().ok_or("concern is missing a name")?;
+                self.concerns.insert(name, body.clone());
+                Ok(())
+            }
+            "concerns" => self.concerns(&args.names(), scope),
+            "root" => {
+                let to = args
+                    .option_str("to")
+                    .or_else(|| args.names().pop())
+                    .ok_or("root is missing a controller#action")?;
+                let (controller, action) = self.to(&to, scope)?;
+                let (path, name) = scope.base();
+                self.push(
+                    RequestMethod::GET,
+                    &join_name(&name, "root"),
+                    &path,
+                    &controller,
+                    &action,
+                );
+                Ok(())
+            }
+            "get" | "post" | "put" | "patch" | "delete" | "options" | "match" => {
+                self.route(&send.method_name, &args, scope)
+            }
+            // mount, direct, resolve etc. don't map onto controllers
+            _ => Ok(()),
+        }
+    }
+
+    fn concerns(&mut self, names: &[String], scope: &Scope) -> Result<(), String> {
+        for name in names {
+            let body = self
+                .concerns
+                .get(name)
+                .cloned()
+                .ok_or(format!("unknown concern '{}'", name))?;
+            self.visit_body(&body, scope)?;
+        }
+        Ok(())
+    }
+
+    /// splits a `to:` of `controller#action` into the full controller name and action
+    fn to(&self, to: &str, scope: &Scope) -> Result<(String, String), String> {
+        let parts = to.split('#').collect::<Vec<&str>>();
+        match parts.as_slice() {
+            [controller, action] if !controller.is_empty() => {
+                Ok((join_module(&scope.module, controller), action.to_string()))
+            }
+            [_, action] | [action] => {
+                let controller = match (&scope.resource, &scope.controller) {
+                    (Some(resource), _) if scope.level != Level::Scope => {
+                        resource.controller.clone()
+                    }
+                    (_, Some(controller)) => join_module(&scope.module, controller),
+                    _ => Err(format!("could not find controller for '{}'", to))?,
+                };
+                Ok((controller, action.to_string()))
+            }
+            _ => Err(format!("could not find action on the controller {}", to)),
+        }
+    }
+
+    fn route(&mut self, method: &str, args: &Args, scope: &Scope) -> Result<(), String> {
+        let verbs = verbs(method, args)?;
+
+        let mut scope = scope.clone();
+        match args.option_str("on").as_deref() {
+            Some("member") => scope.level = Level::Member,
+            Some("collection") => scope.level = Level::Collection,
+            _ => {}
+        }
+
+        let (path, to) = match &args.rocket {
+            Some((path, to)) => (value_str(path), value_str(to)),
+            None => (args.names().pop(), args.option_str("to")),
+        };
+        let path = path.ok_or(format!("{} route is missing a path", method))?;
+        let (base_path, base_name) = scope.base();
+        let uri = join_path(&base_path, &path);
+
+        let (controller, action) = match (to, &scope.resource) {
+            (Some(to), _) => self.to(&to, &scope)?,
+            (None, Some(resource)) if scope.level != Level::Scope => (
+                resource.controller.clone(),
+                args.option_str("action").unwrap_or_else(|| path.clone()),
+            ),
+            (None, _) => {
+                let segments = path.trim_matches('/').split('/').collect::<Vec<&str>>();
+                let action = args
+                    .option_str("action")
+                    .unwrap_or_else(|| segments[segments.len() - 1].to_string());
+                let controller = args
+                    .option_str("controller")
+                    .or_else(|| scope.controller.clone())
+                    .or_else(|| {
+                        if segments.len() > 1 {
+                            Some(segments[..segments.len() - 1].join("/"))
+                        } else {
+                            None
+                        }
+                    })
+                    .ok_or(format!("could not find controller for '{}'", path))?;
+                (join_module(&scope.module, &controller), action)
+            }
+        };
+
+        let name = match args.option_str("as") {
+            Some(name) => Some(name),
+            None if path.contains([':', '*', '(']) => None,
+            None => Some(path.trim_matches('/').replace('/', "_")),
+        };
+        let name = match (name, scope.level) {
+            (None, _) => "".to_string(),
+            // `preview_dog` and `search_dogs`, the same with `as:`
+            (Some(name), Level::Member | Level::Collection) => join_name(&name, &base_name),
+            (Some(name), Level::Resource | Level::Scope) => join_name(&base_name, &name),
+        };
+
+        for verb in verbs {
+            self.push(verb, &name, &uri, &controller, &action);
+        }
+        Ok(())
+    }
+
+    fn resources(
+        &mut self,
+        name: &str,
+        singular: bool,
+        args: &Args,
+        body: &Option<Node>,
+        scope: &Scope,
+    ) -> Result<(), String> {
+        let defaults: &[&str] = if singular {
+            &RESOURCE_ACTIONS
+        } else {
+            &RESOURCES_ACTIONS
+        };
+        // `only` can still ask for `new` and `edit` in an api only app
+        let api_only = |action: &&&str| !self.api_only || !["new", "edit"].contains(action);
+        let actions = match (args.option_list("only"), args.option_list("except")) {
+            (Some(only), _) => defaults
+                .iter()
+                .filter(|action| only.iter().any(|o| o == *action))
+                .collect::<Vec<&&str>>(),
+            (None, Some(except)) => defaults
+                .iter()
+                .filter(api_only)
+                .filter(|action| !except.iter().any(|e| e == *action))
+                .collect(),
+            (None, None) => defaults.iter().filter(api_only).collect(),
+        };
+
+        let (base_path, base_name) = scope.base();
+        let controller = args.option_str("controller").unwrap_or_else(|| {
+            if singular {
+                pluralize(name)
+            } else {
+                name.to_string()
+            }
+        });
+        let module = join_module(
+            &scope.module,
+            &args.option_str("module").unwrap_or_default(),
+        );
+        let as_name = args.option_str("as").unwrap_or_else(|| name.to_string());
+        let param = args.option_str("param").unwrap_or_else(|| "id".to_string());
+
+        let collection_path = join_path(
+            &base_path,
+            &args.option_str("path").unwrap_or_else(|| name.to_string()),
+        );
+        let resource = if singular {
+            Resource {
+                controller: join_module(&module, &controller),
+                member_path: collection_path.clone(),
+                nested_path: collection_path.clone(),
+                collection_path,
+                collection_name: join_name(&base_name, &as_name),
+                member_name: join_name(&base_name, &as_name),
+            }
+        } else {
+            Resource {
+                controller: join_module(&module, &controller),
+                member_path: join_path(&collection_path, &format!(":{}", param)),
+                nested_path: join_path(
+                    &collection_path,
+                    &format!(":{}_{}", singularize(name), param),
+                ),
+                collection_path,
+                collection_name: join_name(&base_name, &as_name),
+                member_name: join_name(&base_name, &singularize(&as_name)),
+            }
+        };
+
+        // rails draws the block before the default actions so custom routes take priority
+        let nested = Scope {
+            path: scope.path.clone(),
+            module: module.clone(),
+            name: scope.name.clone(),
+            controller: None,
+            resource: Some(resource.clone()),
+            level: Level::Resource,
+        };
+        self.visit_body(body, &nested)?;
+        if let Some(concerns) = args.option_list("concerns") {
+            self.concerns(&concerns, &nested)?;
+        }
+
+        let Resource {
+            controller,
+            collection_path,
+            member_path,
+            collection_name,
+            member_name,
+            ..
+        } = &resource;
+        for action in actions {
+            match *action {
+                "index" => self.push(
+                    RequestMethod::GET,
+                    collection_name,
+                    collection_path,
+                    controller,
+                    "index",
+                ),
+                "create" => self.push(
+                    RequestMethod::POST,
+                    collection_name,
+                    collection_path,
+                    controller,
+                    "create",
+                ),
+                "new" => self.push(
+                    RequestMethod::GET,
+                    &join_name("new", member_name),
+                    &join_path(collection_path, "new"),
+                    controller,
+                    "new",
+                ),
+                "edit" => self.push(
+                    RequestMethod::GET,
+                    &join_name("edit", member_name),
+                    &join_path(member_path, "edit"),
+                    controller,
+                    "edit",
+                ),
+                "show" => self.push(
+                    RequestMethod::GET,
+                    member_name,
+                    member_path,
+                    controller,
+                    "show",
+                ),
+                "update" => {
+                    self.push(
+                        RequestMethod::PATCH,
+                        member_name,
+                        member_path,
+                        controller,
+                        "update",
+                    );
+                    self.push(
+                        RequestMethod::PUT,
+                        member_name,
+                        member_path,
+                        controller,
+                        "update",
+                    );
+                }
+                "destroy" => self.push(
+                    RequestMethod::DELETE,
+                    member_name,
+                    member_path,
+                    controller,
+                    "destroy",
+                ),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct ApiOnlySearch {
+    api_only: bool,
+}
+
+// `config.api_only = true` inside `class Application < Rails::Application`
+impl Visitor for ApiOnlySearch {
+    fn on_send(&mut self, send: &nodes::Send) {
+        if send.method_name == "api_only=" {
+            self.api_only = matches!(&send.args[..], [Node::True(_)]);
+        }
+        if let Some(recv) = &send.recv {
+            self.visit(recv);
+        }
+        self.visit_all(&send.args);
+    }
+}
+
+/// Whether `config/application.rb` makes the app api only
+pub fn api_only(application: &str) -> bool {
+    let mut search = ApiOnlySearch::default();
+    if let Some(ast) = Parser::new(application.as_bytes(), Default::default())
+        .do_parse()
+        .ast
+    {
+        search.visit(&ast);
+    }
+    search.api_only
+}
+
+/// Evaluates a `config/routes.rb` file into the same routes `bundle exec rails routes` would list,
+/// along with a warning for each call that couldn't be evaluated
+pub fn parse_routes_rb(
+    input: &str,
+    api_only: bool,
+) -> Result<(Vec<Request>, Vec<Diagnostic>), String> {
+    let ast = Parser::new(input.as_bytes(), Default::default())
+        .do_parse()
+        .ast
+        .ok_or("routes file is empty")?;
+
+    let mut interpreter = Interpreter {
+        api_only,
+        ..Interpreter::default()
+    };
+    interpreter.visit(&ast, &Scope::default())?;
+
+    Ok((interpreter.routes, interpreter.diagnostics))
+}
+
+#[cfg(test)]
+mod routes_rb_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{api_only, parse_routes_rb};
+    use crate::{
+        diagnostics::UNSUPPORTED_ROUTE,
+        routes::{Request, RequestMethod},
+    };
+
+    fn route(method: RequestMethod, prefix: &str, uri: &str, to: &str) -> Request {
+        let to = to.split('#').collect::<Vec<&str>>();
+        Request {
+            method,
+            prefix: prefix.to_string(),
+            uri: uri.to_string(),
+            controller: to[0].to_string(),
+            action: to[1].to_string(),
+        }
+    }
+
+    fn helper(body: &str) -> Vec<Request> {
+        parse_routes_rb(
+            &format!("Rails.application.routes.draw do\n{}\nend", body),
+            false,
+        )
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn resources() {
+        use RequestMethod::*;
+        assert_eq!(
+            helper("resources :dogs"),
+            vec![
+                route(GET, "dogs", "/dogs", "dogs#index"),
+                route(POST, "", "/dogs", "dogs#create"),
+                route(GET, "new_dog", "/dogs/new", "dogs#new"),
+                route(GET, "edit_dog", "/dogs/:id/edit", "dogs#edit"),
+                route(GET, "dog", "/dogs/:id", "dogs#show"),
+                route(PATCH, "", "/dogs/:id", "dogs#update"),
+                route(PUT, "", "/dogs/:id", "dogs#update"),
+                route(DELETE, "", "/dogs/:id", "dogs#destroy"),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_and_except() {
+        use RequestMethod::*;
+        assert_eq!(
+            helper(
+                "resources :dogs, only: [:index, :show]
+                resources :cats, except: %i[new edit update destroy index]"
+            ),
+            vec![
+                route(GET, "dogs", "/dogs", "dogs#index"),
+                route(GET, "dog", "/dogs/:id", "dogs#show"),
+                route(POST, "cats", "/cats", "cats#create"),
+                route(GET, "cat", "/cats/:id", "cats#show"),
+            ]
+        );
+    }
+
+    #[test]
+    fn api_only_resources() {
+        use RequestMethod::*;
+        let application = "
+        module Dogs
+            class Application < Rails::Application
+                config.load_defaults 7.1
+                config.api_only = true
+            end
+        end
+        ";
+        assert!(api_only(application));
+        assert!(!api_only("config.api_only = false"));
+
+        let (routes, _) = parse_routes_rb(
+            "Rails.application.routes.draw do
+                resources :dogs, except: [:destroy]
+                resources :cats, only: [:new]
+            end",
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            routes,
+            vec![
+                route(GET, "dogs", "/dogs", "dogs#index"),
+                route(POST, "", "/dogs", "dogs#create"),
+                route(GET, "dog", "/dogs/:id", "dogs#show"),
+                route(PATCH, "", "/dogs/:id", "dogs#update"),
+                route(PUT, "", "/dogs/:id", "dogs#update"),
+                route(GET, "new_cat", "/cats/new", "cats#new"),
+            ]
+        );
+    }
+
+    #[test]
+    fn singular_resource() {
+        use RequestMethod::*;
+        assert_eq!(
+            helper("resource :profile, only: [:show, :update]"),
+            vec![
+                route(GET, "profile", "/profile", "profiles#show"),
+                route(PATCH, "", "/profile", "profiles#update"),
+                route(PUT, "", "/profile", "profiles#update"),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_namespace() {
+        use RequestMethod::*;
+        assert_eq!(
+            helper(
+                "namespace :api do
+                    namespace :v1 do
+                        resources :dogs, only: :show do
+                            resources :styles, only: [:index, :create]
+                            member do
+                                get :preview
+                            end
+                            get :search, on: :collection
+                        end
+                    end
+                end"
+            ),
+            vec![
+                route(
+                    GET,
+                    "api_v1_dog_styles",
+                    "/api/v1/dogs/:dog_id/styles",
+                    "api/v1/styles#index"
+                ),
+                route(
+                    POST,
+                    "",
+                    "/api/v1/dogs/:dog_id/styles",
+                    "api/v1/styles#create"
+                ),
+                route(
+                    GET,
+                    "preview_api_v1_dog",
+                    "/api/v1/dogs/:id/preview",
+                    "api/v1/dogs#preview"
+                ),
+                route(
+                    GET,
+                    "search_api_v1_dogs",
+                    "/api/v1/dogs/search",
+                    "api/v1/dogs#search"
+                ),
+                route(GET, "api_v1_dog", "/api/v1/dogs/:id", "api/v1/dogs#show"),
+            ]
+        );
+    }
+
+    #[test]
+    fn scopes_and_verbs() {
+        use RequestMethod::*;
+        assert_eq!(
+            helper(
+                "root to: 'home#index'
+                scope module: 'admin', path: 'manage', as: 'manage' do
+                    get 'reports', to: 'reports#index'
+                    post 'reports/:id/run', to: 'reports#run', as: 'run_report'
+                end
+                get 'dogs/:id/bark' => 'dogs#bark'
+                get 'photos/search'
+                match 'ping', to: 'health#ping', via: [:get, :post]"
+            ),
+            vec![
+                route(GET, "root", "/", "home#index"),
+                route(
+                    GET,
+                    "manage_reports",
+                    "/manage/reports",
+                    "admin/reports#index"
+                ),
+                route(
+                    POST,
+                    "manage_run_report",
+                    "/manage/reports/:id/run",
+                    "admin/reports#run"
+                ),
+                route(GET, "", "/dogs/:id/bark", "dogs#bark"),
+                route(GET, "photos_search", "/photos/search", "photos#search"),
+                route(GET, "ping", "/ping", "health#ping"),
+                route(POST, "", "/ping", "health#ping"),
+            ]
+        );
+    }
+
+    #[test]
+    fn concerns() {
+        use RequestMethod::*;
+        assert_eq!(
+            helper(
+                "concern :commentable do
+                    resources :comments, only: :index
+                end
+                resources :posts, only: [], concerns: :commentable
+                resources :videos, only: [] do
+                    concerns :commentable
+                end"
+            ),
+            vec![
+                route(
+                    GET,
+                    "post_comments",
+                    "/posts/:post_id/comments",
+                    "comments#index"
+                ),
+                route(
+                    GET,
+                    "video_comments",
+                    "/videos/:video_id/comments",
+                    "comments#index"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_module() {
+        use RequestMethod::*;
+        assert_eq!(
+            helper(
+                "resources :dogs, only: [:index], module: :admin do
+                    resources :toys, only: [:index]
+                end"
+            ),
+            vec![
+                route(GET, "dog_toys", "/dogs/:dog_id/toys", "admin/toys#index"),
+                route(GET, "dogs", "/dogs", "admin/dogs#index"),
+            ]
+        );
+    }
+
+    #[test]
+    fn member_as() {
+        use RequestMethod::*;
+        assert_eq!(
+            helper(
+                "resources :statuses, only: [] do
+                    member do
+                        patch :archive, as: :archive
+                    end
+                    get :recent, on: :collection, as: :latest
+                end"
+            ),
+            vec![
+                route(
+                    PATCH,
+                    "archive_status",
+                    "/statuses/:id/archive",
+                    "statuses#archive"
+                ),
+                route(
+                    GET,
+                    "latest_statuses",
+                    "/statuses/recent",
+                    "statuses#recent"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_calls() {
+        use RequestMethod::*;
+        let (routes, diagnostics) = parse_routes_rb(
+            "Rails.application.routes.draw do
+                member do
+                    get :preview
+                end
+                resources :dogs, only: [:index]
+            end",
+            false,
+        )
+        .unwrap();
+        assert_eq!(routes, vec![route(GET, "dogs", "/dogs", "dogs#index")]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, UNSUPPORTED_ROUTE);
+        assert_eq!(diagnostics[0].message, "member used outside of resources");
+    }
+}
@@ -1,23 +1,38 @@
-use std::{
-    collections::{HashSet, VecDeque},
-    iter::FromIterator,
-};
+use std::{collections::HashSet, iter::FromIterator};
 
-use lib_ruby_parser::{nodes, Node};
+use lib_ruby_parser::{nodes, traverse::Visitor, Node, Parser};
 
-use crate::params::search_for_param;
+use crate::{
+    diagnostics::{Diagnostic, EMPTY_FILE, SKIPPED_STATEMENT, UNSUPPORTED_CLASS},
+    ivars::{search_for_ivars, source, Ivar},
+    params::{search_for_inputs, search_for_param, search_for_param_types, Input},
+    responses::{search_for_responses, Response},
+    strong_params::{search_for_strong_params, StrongParams},
+    types::Type,
+};
 
 #[derive(Debug)]
 enum AstError {
     NoName,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Method {
     pub name: String,
     pub params: Vec<String>,
-    pub returns: Vec<String>,
+    // params converted before they're used e.g. `params[:page].to_i`, the rest are strings
+    pub param_types: Vec<(String, Type)>,
+    // headers, cookies and raw bodies read from the request
+    pub inputs: Vec<Input>,
+    // instance variables assigned by the method and the callbacks that run before it, read by views
+    pub ivars: Vec<Ivar>,
+    pub returns: Vec<Response>,
     pub private: bool,
+    // callbacks that run for the action in order, only known after parser_parser has resolved the controller
+    pub callbacks: Vec<String>,
+    pub strong_params: Option<StrongParams>,
+    // methods called without a receiver, strong params and request inputs are followed through them
+    pub calls: Vec<String>,
 }
 
 impl std::fmt::Display for Method {
@@ -32,7 +47,16 @@ impl std::fmt::Display for Method {
             }
 
             if !self.returns.is_empty() {
-                write!(f, "responses: {} ", self.returns.join(","))?;
+                let returns = self
+                    .returns
+                    .iter()
+                    .map(|response| response.to_string())
+                    .collect::<Vec<String>>();
+                write!(f, "responses: {} ", returns.join(","))?;
+            }
+
+            if !self.callbacks.is_empty() {
+                write!(f, "callbacks: {} ", self.callbacks.join(","))?;
             }
         }
 
@@ -40,28 +64,70 @@ impl std::fmt::Display for Method {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// the methods a callback names along with the actions and conditions it is limited to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Callback {
+    pub methods: Vec<String>,
+    pub only: Vec<String>,
+    pub except: Vec<String>,
+    // methods checked at runtime, procs and lambdas show up as unknown
+    pub if_conditions: Vec<String>,
+    pub unless_conditions: Vec<String>,
+    // `prepend_before_action` or `prepend: true` puts the callback at the start of the chain
+    pub prepend: bool,
+}
+
+impl Callback {
+    pub fn applies_to(&self, action: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|a| a == action))
+            && !self.except.iter().any(|a| a == action)
+    }
+
+    /// whether `if:`/`unless:` means it can only be known at runtime if the callback runs
+    pub fn is_conditional(&self) -> bool {
+        !self.if_conditions.is_empty() || !self.unless_conditions.is_empty()
+    }
+}
+
+/// `rescue_from ActiveRecord::RecordNotFound, with: :not_found`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rescue {
+    pub exceptions: Vec<String>,
+    // the handler method, none when the handler is a block
+    pub with: Option<String>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Action {
-    BeforeAction(String),
-    AroundAction(String),
-    SkipAuthMethods(String),
-    SkipBeforeAction(String),
+    BeforeAction(Callback),
+    AroundAction(Callback),
+    AfterAction(Callback),
+    SkipAuthMethods(Callback),
+    SkipBeforeAction(Callback),
+    SkipAroundAction(Callback),
+    SkipAfterAction(Callback),
+    RescueFrom(Rescue),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Controller {
     pub name: String,
     pub parent: String,
     pub methods: Vec<Method>,
     pub actions: Vec<Action>,
+    pub includes: Vec<String>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Module {
     pub name: String,
     pub classes: Vec<Controller>,
     pub requires: Vec<String>,
     pub methods: Vec<Method>,
+    // callbacks declared in an `included do ... end` block of a concern
+    pub actions: Vec<Action>,
+    pub includes: Vec<String>,
 }
 
 impl std::fmt::Display for Module {
@@ -99,11 +165,15 @@ impl std::fmt::Display for Controller {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RubyFile {
+    /// where the file was read from, empty until `parse_files` fills it in
+    pub path: String,
     pub controllers: Vec<Controller>,
     pub modules: Vec<Module>,
     pub requires: Vec<String>,
+    /// statements that were skipped while the rest of the file was read
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl std::fmt::Display for RubyFile {
@@ -124,7 +194,10 @@ impl std::fmt::Display for RubyFile {
 fn get_node_name(name: &Node) -> Result<String, AstError> {
     match name {
         Node::Const(node_const_name) => {
-            if let Some(scope) = &node_const_name.scope {
+            if let Some(Node::Cbase(_)) = node_const_name.scope.as_deref() {
+                // `::DogsController` is looked up from the top level
+                Ok(format!("::{}", node_const_name.name))
+            } else if let Some(scope) = &node_const_name.scope {
                 Ok(format!(
                     "{}::{}",
                     get_node_name(scope)?,
@@ -148,258 +221,368 @@ fn pretty_print(e: Node) -> String {
     }
 }
 
-// a lot of this work is around how do we get the AST to represent the actual code...
-// however in the future config_parse_list should give a Config object with all things in it in types....
-fn config_parse(node: Node) -> String {
-    let mut results = "".to_string();
-
-    let mut buf = VecDeque::new();
-
-    buf.push_back(node);
+fn config_list(node: &Node) -> Vec<String> {
+    match node {
+        Node::Array(array) => array.elements.iter().cloned().map(pretty_print).collect(),
+        node => vec![pretty_print(node.clone())],
+    }
+}
 
-    while !buf.is_empty() {
-        let temp = buf.pop_front().unwrap();
-        results += &match temp {
-            Node::Sym(sym) => sym.name.to_string_lossy(),
-            Node::Ivar(ivar) => ivar.name.to_string(),
-            Node::Str(str) => str.value.to_string_lossy().clone(),
-            Node::Int(int) => int.value.to_string(),
-            Node::Pair(pair) => {
-                buf.push_back(*pair.key);
-                buf.push_back(*pair.value);
-                ":".to_string()
-            }
-            Node::Hash(hash) => {
-                for pair in hash.pairs {
-                    buf.push_back(pair);
+// e.g. `before_action :check_auth, :load_user, only: [:show], if: :signed_in?`
+fn parse_callback(args: &[Node], prepend: bool) -> Callback {
+    let mut callback = Callback {
+        prepend,
+        ..Callback::default()
+    };
+    for arg in args {
+        match arg {
+            Node::Kwargs(nodes::Kwargs { pairs, .. }) | Node::Hash(nodes::Hash { pairs, .. }) => {
+                for pair in pairs {
+                    if let Node::Pair(pair) = pair {
+                        match pretty_print((*pair.key).clone()).as_str() {
+                            "only" => callback.only = config_list(&pair.value),
+                            "except" => callback.except = config_list(&pair.value),
+                            "if" => callback.if_conditions = config_list(&pair.value),
+                            "unless" => callback.unless_conditions = config_list(&pair.value),
+                            "prepend" => callback.prepend = matches!(*pair.value, Node::True(_)),
+                            _ => {}
+                        }
+                    }
                 }
-                " ".to_string()
             }
-            Node::Kwargs(kwargs) => {
-                for pair in kwargs.pairs {
-                    buf.push_back(pair);
+            arg => callback.methods.push(pretty_print(arg.clone())),
+        }
+    }
+
+    callback
+}
+
+// e.g. `rescue_from ActiveRecord::RecordNotFound, ActiveRecord::RecordInvalid, with: :not_found`
+fn parse_rescue(args: &[Node]) -> Rescue {
+    let mut rescue = Rescue::default();
+    for arg in args {
+        match arg {
+            Node::Kwargs(nodes::Kwargs { pairs, .. }) | Node::Hash(nodes::Hash { pairs, .. }) => {
+                for pair in pairs {
+                    if let Node::Pair(pair) = pair {
+                        if pretty_print((*pair.key).clone()) == "with" {
+                            if let Node::Sym(_) = *pair.value {
+                                rescue.with = Some(pretty_print((*pair.value).clone()));
+                            }
+                        }
+                    }
                 }
-                " ".to_string()
             }
-            Node::Array(array) => {
-                for elem in array.elements {
-                    buf.push_back(elem);
+            arg => rescue
+                .exceptions
+                .push(get_node_name(arg).unwrap_or_else(|_| pretty_print(arg.clone()))),
+        }
+    }
+
+    rescue
+}
+
+// the macros that can be called in a class body or a concern's included block
+fn parse_class_send(
+    send: &nodes::Send,
+    actions: &mut Vec<Action>,
+    includes: &mut Vec<String>,
+    private: &mut bool,
+) {
+    let args = &send.args;
+    match send.method_name.as_str() {
+        "private" => {
+            *private = true;
+        }
+        "include" => {
+            for arg in args {
+                if let Ok(name) = get_node_name(arg) {
+                    includes.push(name);
                 }
-                "[array] ".to_string()
             }
-            _ => {
-                // println!("{:?}", temp);
-                "".to_string()
-            }
-        };
+        }
+        "skip_auth_methods" => actions.push(Action::SkipAuthMethods(parse_callback(args, false))),
+        "before_action" => actions.push(Action::BeforeAction(parse_callback(args, false))),
+        "around_action" => actions.push(Action::AroundAction(parse_callback(args, false))),
+        "after_action" => actions.push(Action::AfterAction(parse_callback(args, false))),
+        "prepend_before_action" => actions.push(Action::BeforeAction(parse_callback(args, true))),
+        "prepend_around_action" => actions.push(Action::AroundAction(parse_callback(args, true))),
+        "prepend_after_action" => actions.push(Action::AfterAction(parse_callback(args, true))),
+        "skip_before_action" => actions.push(Action::SkipBeforeAction(parse_callback(args, false))),
+        "skip_around_action" => actions.push(Action::SkipAroundAction(parse_callback(args, false))),
+        "skip_after_action" => actions.push(Action::SkipAfterAction(parse_callback(args, false))),
+        "rescue_from" => actions.push(Action::RescueFrom(parse_rescue(args))),
+        _ => {}
     }
+}
 
-    // println!("result: {} {:?}", results, buf);
-    results
+#[derive(Default)]
+struct Calls {
+    calls: Vec<String>,
 }
-//TODO: work out how to parse ruby config!
-// dry language but everything is configured with all these bits of strange code all over the place
-// good for a single file but it's hard to piece together everything specailly without nice tooling (atm - from what I have found)
-fn config_parse_list(args: &Vec<Node>) -> String {
-    let mut results = "".to_string();
-    for arg in args {
-        results += &config_parse((*arg).clone());
+
+impl Visitor for Calls {
+    fn on_send(&mut self, send: &nodes::Send) {
+        if send.recv.is_none() && !self.calls.contains(&send.method_name) {
+            self.calls.push(send.method_name.clone());
+        }
+
+        if let Some(recv) = &send.recv {
+            self.visit(recv);
+        }
+        self.visit_all(&send.args);
     }
+}
 
-    results
+/// methods called without a receiver e.g. `dog_params` in `Dog.new(dog_params)`
+fn search_for_calls(body: &Node) -> Vec<String> {
+    let mut search = Calls::default();
+    search.visit(body);
+    search.calls
 }
 
 fn parse_def(def: &lib_ruby_parser::nodes::Def, private: bool) -> Result<Method, String> {
     let mut params = HashSet::new();
     let mut returns = Vec::new();
+    let mut param_types = Vec::new();
+    let mut inputs = Vec::new();
+    let mut ivars = Vec::new();
+    let mut strong_params = None;
+    let mut calls = Vec::new();
     if let Some(body) = def.body.clone() {
+        returns = search_for_responses(&body);
+        param_types = search_for_param_types(&body);
+        inputs = search_for_inputs(&body);
+        ivars = search_for_ivars(&body);
+        strong_params = search_for_strong_params(&body);
+        calls = search_for_calls(&body);
         match *body {
             Node::Begin(begin) => {
                 for i in 0..begin.statements.len() {
-                    match &begin.statements[i] {
-                        // return statement - currently doesn't support another statement doing a return e.g. a if statement
-                        Node::Send(send) => {
-                            let recv = if let Some(recv) = &send.recv {
-                                pretty_print((**recv).clone())
-                            } else {
-                                "none".to_string()
-                            };
-                            let args = send
-                                .args
-                                .clone()
-                                .into_iter()
-                                .map(|e| pretty_print(e))
-                                .collect::<Vec<String>>();
-
-                            let mut return_msg = send.method_name.clone();
-                            if !recv.is_empty() {
-                                return_msg += &format!(" {}", recv);
-                            }
-                            if !args.is_empty() {
-                                return_msg += &format!(" {}", args.join(" "));
-                            }
-
-                            returns.push(return_msg);
-                        }
-
-                        // otherwise search for use of params!
-                        _ => {
-                            let foo = search_for_param(Box::new(begin.statements[i].clone()));
-                            params.extend::<Vec<String>>(foo.into_iter().collect::<Vec<String>>());
-                        }
-                    }
+                    params.extend(search_for_param(Box::new(begin.statements[i].clone())));
                 }
             }
-            Node::OrAsgn(or_asign) => {
-                // orAsign.recv -- just need the name maybe... or could just ignore
-                match *or_asign.recv {
-                    Node::Ivasgn(ivasgn) => {
-                        println!("or assign: {}", ivasgn.name);
-                        search_for_param(or_asign.value);
-                    }
-                    _ => println!("error unknown node for orAsign"),
-                }
-                // orAsign.value -- run get params on this
-            }
-            _ => {}
+            body => params.extend(search_for_param(Box::new(body))),
         }
     }
 
+    let mut params = Vec::from_iter(params);
+    params.sort();
+
     Ok(Method {
         name: def.name.clone(),
-        params: Vec::from_iter(params),
+        params,
+        param_types,
+        inputs,
+        ivars,
         returns,
         private,
+        callbacks: Vec::new(),
+        strong_params,
+        calls,
     })
 }
 
-fn parse_class(class: nodes::Class) -> Result<Controller, String> {
-    let name = get_node_name(&class.name).unwrap();
-    let parent = get_node_name(&class.superclass.unwrap()).unwrap();
-
+/// the methods and callbacks declared in a class body, `class << self` only holds class methods
+/// which can't be actions so it's passed over
+fn parse_class_body(
+    name: String,
+    parent: String,
+    body: Option<Box<Node>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Controller {
     let mut methods = Vec::new();
     let mut actions = Vec::new();
+    let mut includes = Vec::new();
+    let mut private = false;
 
-    if parent == "ActionController::API" {
-        println!("oooh boy {} {}", name, parent);
-    }
-
-    if let Some(body) = class.body {
-        match *body {
-            Node::Begin(begin) => {
-                let mut private = false;
-                for i in 0..begin.statements.len() {
-                    match &begin.statements[i] {
-                        Node::Def(def) => {
-                            if let Ok(method) = parse_def(&def, private) {
-                                methods.push(method);
-                            }
-                        }
-                        Node::Send(send) => {
-                            let action_name = config_parse_list(&send.args);
-
-                            match send.method_name.as_str() {
-                                "private" => {
-                                    private = true;
-                                }
-                                "skip_auth_methods" => {
-                                    actions.push(Action::SkipAuthMethods(action_name));
-                                }
-                                "around_action" => {
-                                    actions.push(Action::AroundAction(action_name));
-                                }
-                                "before_action" => {
-                                    actions.push(Action::BeforeAction(action_name));
-                                }
-                                "skip_before_action" => {
-                                    // println!("{:?}", send);
-                                    actions.push(Action::SkipBeforeAction(action_name));
-                                }
-                                _ => {}
-                            }
-                        }
-                        _ => println!("error unknown statement found in class"),
-                    }
-                }
-
-                Ok(Controller {
-                    name,
-                    parent,
-                    methods: methods,
-                    actions,
-                })
-            }
+    let statements = match body.map(|body| *body) {
+        Some(Node::Begin(begin)) => begin.statements,
+        Some(statement) => vec![statement],
+        None => Vec::new(),
+    };
+    for statement in &statements {
+        match statement {
             Node::Def(def) => {
-                if let Ok(method) = parse_def(&def, false) {
+                if let Ok(method) = parse_def(def, private) {
                     methods.push(method);
                 }
-
-                Ok(Controller {
-                    name,
-                    parent,
-                    methods: methods,
-                    actions,
-                })
             }
-            _ => Err("no class body found".to_string()),
+            Node::Send(send) => {
+                parse_class_send(send, &mut actions, &mut includes, &mut private);
+            }
+            // e.g. `rescue_from ActiveRecord::RecordNotFound do |e| ... end`
+            Node::Block(block) => {
+                if let Node::Send(send) = &*block.call {
+                    parse_class_send(send, &mut actions, &mut includes, &mut private);
+                }
+            }
+            Node::SClass(_) => {}
+            statement => diagnostics.push(
+                Diagnostic::warning(
+                    SKIPPED_STATEMENT,
+                    format!("skipped a statement in the body of `{}`", name),
+                )
+                .at(statement),
+            ),
+        }
+    }
+
+    Controller {
+        name,
+        parent,
+        methods,
+        actions,
+        includes,
+    }
+}
+
+/// the name of a superclass, classes without one have an empty parent
+fn parent_name(name: &str, superclass: Option<&Node>, diagnostics: &mut Vec<Diagnostic>) -> String {
+    match superclass.map(|superclass| (superclass, get_node_name(superclass))) {
+        Some((_, Ok(parent))) => parent,
+        Some((superclass, Err(_))) => {
+            diagnostics.push(
+                Diagnostic::warning(
+                    UNSUPPORTED_CLASS,
+                    format!(
+                        "the superclass of `{}` isn't a constant, its callbacks and methods aren't inherited",
+                        name
+                    ),
+                )
+                .at(superclass),
+            );
+            String::new()
         }
-    } else {
-        Err("no class body found".to_string())
+        None => String::new(),
     }
 }
 
-fn parse_module(module: nodes::Module, parent_name: &str) -> Result<Vec<Module>, String> {
-    let module_name = if parent_name.is_empty() {
-        get_node_name(&module.name).unwrap()
-    } else {
-        format!("{}.{}", parent_name, get_node_name(&module.name).unwrap())
+/// `class DogsController < ApplicationController`, `class Api::V1::DogsController` or a plain `class Dog`,
+/// classes named by an expression e.g. `class self::Dogs` are skipped
+fn parse_class(class: nodes::Class, diagnostics: &mut Vec<Diagnostic>) -> Option<Controller> {
+    let name = match get_node_name(&class.name) {
+        Ok(name) => name.trim_start_matches("::").to_string(),
+        Err(_) => {
+            diagnostics.push(
+                Diagnostic::warning(
+                    UNSUPPORTED_CLASS,
+                    "skipped a class that isn't named by a constant",
+                )
+                .at(&class.name),
+            );
+            return None;
+        }
+    };
+    let parent = parent_name(&name, class.superclass.as_deref(), diagnostics);
+    Some(parse_class_body(name, parent, class.body, diagnostics))
+}
+
+/// `DogsController = Class.new(ApplicationController) do ... end`
+fn parse_class_new(casgn: &nodes::Casgn, diagnostics: &mut Vec<Diagnostic>) -> Option<Controller> {
+    let (call, body) = match casgn.value.as_deref()? {
+        Node::Block(block) => (&*block.call, block.body.clone()),
+        value => (value, None),
+    };
+    let superclass = match call {
+        Node::Send(send)
+            if send.method_name == "new"
+                && matches!(send.recv.as_deref(), Some(Node::Const(constant)) if constant.name == "Class" && constant.scope.is_none()) =>
+        {
+            send.args.first()
+        }
+        _ => return None,
+    };
+
+    let name = match &casgn.scope {
+        Some(scope) => format!("{}::{}", get_node_name(scope).ok()?, casgn.name),
+        None => casgn.name.clone(),
+    };
+    let parent = parent_name(&name, superclass, diagnostics);
+    Some(parse_class_body(name, parent, body, diagnostics))
+}
+
+/// a module along with the modules nested in it, modules named by an expression are skipped
+fn parse_module(
+    module: nodes::Module,
+    parent_name: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<Module> {
+    let module_name = match get_node_name(&module.name) {
+        Ok(name) if parent_name.is_empty() => name.trim_start_matches("::").to_string(),
+        Ok(name) => format!("{}.{}", parent_name, name.trim_start_matches("::")),
+        Err(_) => {
+            diagnostics.push(
+                Diagnostic::warning(
+                    UNSUPPORTED_CLASS,
+                    "skipped a module that isn't named by a constant",
+                )
+                .at(&module.name),
+            );
+            return Vec::new();
+        }
     };
     let mut requires = Vec::new();
     let mut classes = Vec::new();
     let mut modules = Vec::new();
     let mut methods = Vec::new();
+    let mut actions = Vec::new();
+    let mut includes = Vec::new();
     let mut private = false;
-    if let Some(body) = module.body {
-        match *body {
-            Node::Class(class) => classes.push(parse_class(class)?),
-            Node::Begin(begin) => {
-                for i in 0..begin.statements.len() {
-                    match begin.statements[i].clone() {
-                        Node::Module(module) => {
-                            modules.append(&mut parse_module(module, &module_name)?);
-                        }
-                        Node::Class(class) => classes.push(parse_class(class)?),
-                        Node::Send(send) => {
-                            if send.method_name == "require" {
-                                for i in 0..send.args.len() {
-                                    requires.push(pretty_print(send.args[i].clone()));
-                                }
-                            }
-                            if send.method_name == "private" {
-                                private = true;
+    let statements = match module.body.map(|body| *body) {
+        Some(Node::Begin(begin)) => begin.statements,
+        Some(statement) => vec![statement],
+        None => Vec::new(),
+    };
+    for statement in statements {
+        match statement {
+            Node::Module(module) => {
+                modules.append(&mut parse_module(module, &module_name, diagnostics));
+            }
+            Node::Class(class) => classes.extend(parse_class(class, diagnostics)),
+            Node::Send(send) => {
+                if send.method_name == "require" {
+                    for i in 0..send.args.len() {
+                        requires.push(pretty_print(send.args[i].clone()));
+                    }
+                }
+                parse_class_send(&send, &mut actions, &mut includes, &mut private);
+            }
+
+            // `DogsController = Class.new(ApplicationController)`, other constants aren't needed
+            Node::Casgn(casgn) => classes.extend(parse_class_new(&casgn, diagnostics)),
+
+            // concerns declare their callbacks in an `included do ... end` block
+            Node::Block(block) => {
+                if let (Node::Send(call), Some(body)) = (*block.call, block.body) {
+                    if call.method_name == "included" {
+                        let statements = match *body {
+                            Node::Begin(begin) => begin.statements,
+                            statement => vec![statement],
+                        };
+                        for statement in statements {
+                            if let Node::Send(send) = statement {
+                                parse_class_send(&send, &mut actions, &mut includes, &mut false);
                             }
                         }
+                    }
+                }
+            }
 
-                        // assignment outside the controller isn't supported
-                        Node::Casgn(_) => {}
-
-                        //NOTE: block is currently not supported, however doesn't look cruical atm until we do authentication handling for routes
-                        Node::Block(_) => {}
-
-                        Node::Def(def) => {
-                            methods.push(parse_def(&def, private)?);
-                        }
-                        _ => {
-                            println!("{:?}", begin.statements[i]);
-                            Err("Unexpected node type found")?
-                        }
-                    };
+            Node::Def(def) => {
+                if let Ok(method) = parse_def(&def, private) {
+                    methods.push(method);
                 }
             }
-            _ => {}
-        }
-    } else {
-        Err("no module body found")?
+            Node::SClass(_) => {}
+            statement => diagnostics.push(
+                Diagnostic::warning(
+                    SKIPPED_STATEMENT,
+                    format!("skipped a statement in module `{}`", module_name),
+                )
+                .at(&statement),
+            ),
+        };
     }
 
     modules.push(Module {
@@ -407,63 +590,157 @@ fn parse_module(module: nodes::Module, parent_name: &str) -> Result<Vec<Module>,
         classes,
         requires,
         methods,
+        actions,
+        includes,
     });
 
-    Ok(modules)
+    modules
 }
 
-pub fn parse_file(ast: Node) -> Result<RubyFile, String> {
-    let mut requires = Vec::new();
-    let mut classes = Vec::new();
-    let mut modules = Vec::new();
-    match ast {
+/// every class in a file along with the modules it's nested in
+pub(crate) fn classes<'a>(
+    node: &'a Node,
+    namespace: &str,
+    found: &mut Vec<(String, &'a nodes::Class)>,
+) {
+    let name = |name: &Node| match namespace {
+        "" => source(name),
+        namespace => format!("{}::{}", namespace, source(name)),
+    };
+
+    match node {
+        Node::Begin(begin) => {
+            for statement in &begin.statements {
+                classes(statement, namespace, found);
+            }
+        }
         Node::Module(module) => {
-            modules.append(&mut parse_module(module, "")?);
+            if let Some(body) = &module.body {
+                classes(body, &name(&module.name), found);
+            }
         }
-        Node::Class(class) => classes.push(parse_class(class)?),
-        Node::Begin(begin) => {
-            for i in 0..begin.statements.len() {
-                match begin.statements[i].clone() {
-                    Node::Module(module) => {
-                        modules.append(&mut parse_module(module, "")?);
-                    }
-                    Node::Class(class) => classes.push(parse_class(class)?),
-                    Node::Send(send) => {
-                        if send.method_name == "require" {
-                            for i in 0..send.args.len() {
-                                requires.push(pretty_print(send.args[i].clone()));
-                            }
-                        }
-                    }
-                    _ => Err("error no module found")?,
-                };
+        Node::Class(class) => {
+            let name = name(&class.name);
+            if let Some(body) = &class.body {
+                classes(body, &name, found);
+            }
+            found.push((name, class));
+        }
+        _ => {}
+    }
+}
+
+/// Parses what's left of a file with syntax errors by blanking out the line the first error is on
+/// until it parses, errors after the first are often caused by it so they're left alone. The lines
+/// are filled with spaces so every other node keeps its offsets
+pub fn recover(source: &[u8], errors: &[lib_ruby_parser::Diagnostic]) -> Option<Node> {
+    let mut source = source.to_vec();
+    let mut error = errors.iter().find(|e| e.is_error())?.range.begin_pos;
+
+    loop {
+        // errors at the end of the file or on a blanked line are down to the line before
+        let mut end = error.min(source.len());
+        let start = loop {
+            let start = source[..end]
+                .iter()
+                .rposition(|&c| c == b'\n')
+                .map_or(0, |i| i + 1);
+            let line = source[start..]
+                .iter()
+                .position(|&c| c == b'\n')
+                .map_or(source.len(), |i| start + i);
+            if source[start..line].iter().any(|c| !c.is_ascii_whitespace()) || start == 0 {
+                break start;
+            }
+            end = start - 1;
+        };
+        let mut blanked = false;
+        for c in source[start..].iter_mut().take_while(|c| **c != b'\n') {
+            if !c.is_ascii_whitespace() {
+                *c = b' ';
+                blanked = true;
             }
         }
-        _ => Err("error no module found")?,
+        // nothing left to blank
+        if !blanked {
+            return None;
+        }
+
+        let parsed = Parser::new(&source, Default::default()).do_parse();
+        if parsed.ast.is_some() {
+            return parsed.ast;
+        }
+        error = parsed
+            .diagnostics
+            .iter()
+            .find(|e| e.is_error())?
+            .range
+            .begin_pos;
+    }
+}
+
+/// Reads the controllers and modules out of a file, the diagnostic says why a file couldn't be read
+pub fn parse_file(ast: Node) -> Result<RubyFile, Diagnostic> {
+    let mut requires = Vec::new();
+    let mut classes = Vec::new();
+    let mut modules = Vec::new();
+    let mut diagnostics = Vec::new();
+    let statements = match ast {
+        Node::Begin(begin) => begin.statements,
+        statement => vec![statement],
+    };
+    for statement in statements {
+        match statement {
+            Node::Module(module) => {
+                modules.append(&mut parse_module(module, "", &mut diagnostics));
+            }
+            Node::Class(class) => classes.extend(parse_class(class, &mut diagnostics)),
+            Node::Casgn(casgn) => classes.extend(parse_class_new(&casgn, &mut diagnostics)),
+            Node::Send(send) if send.method_name == "require" => {
+                for i in 0..send.args.len() {
+                    requires.push(pretty_print(send.args[i].clone()));
+                }
+            }
+            statement => diagnostics.push(
+                Diagnostic::warning(
+                    SKIPPED_STATEMENT,
+                    "skipped a statement at the top of the file",
+                )
+                .at(&statement),
+            ),
+        };
     }
     if classes.is_empty() && modules.is_empty() {
-        Err("no classes and modules found - as empty")?
+        Err(Diagnostic::error(EMPTY_FILE, "no classes or modules found"))?
     }
     Ok(RubyFile {
+        path: String::new(),
         controllers: classes,
-        modules: modules,
+        modules,
         requires,
+        diagnostics,
     })
 }
 
 #[cfg(test)]
 mod file_tests {
 
-    use crate::ruby_parser::{parse_file, Action, Controller, Method, Module, RubyFile};
+    use crate::{
+        diagnostics::{Severity, EMPTY_FILE, SKIPPED_STATEMENT, UNSUPPORTED_CLASS},
+        responses::Response,
+        ruby_parser::{
+            parse_file, recover, Action, Callback, Controller, Method, Module, Rescue, RubyFile,
+        },
+    };
     use lib_ruby_parser::{Node, Parser};
     use pretty_assertions::assert_eq;
     use std::vec;
 
     fn helper(input: &str) -> Node {
-        return Parser::new(input.as_bytes(), Default::default())
+        Parser::new(input.as_bytes(), Default::default())
             .do_parse()
             .ast
-            .unwrap();
+            .unwrap()
     }
 
     #[test]
@@ -484,24 +761,44 @@ mod file_tests {
       end";
 
         let expected = RubyFile {
+            path: String::new(),
+            diagnostics: Vec::new(),
             controllers: vec![Controller {
                 name: "VersionController".to_string(),
                 parent: "ApplicationController".to_string(),
-                actions: vec![Action::SkipBeforeAction(
-                    "check_auth_token :only[array] version".to_string(),
-                )],
+                actions: vec![Action::SkipBeforeAction(Callback {
+                    methods: vec!["check_auth_token".to_string()],
+                    only: vec!["version".to_string()],
+                    ..Callback::default()
+                })],
+                includes: Vec::new(),
                 methods: vec![Method {
                     name: "version".to_string(),
                     params: Vec::new(),
-                    returns: vec!["render none unknown".to_string()],
+                    param_types: Vec::new(),
+                    inputs: Vec::new(),
+                    ivars: Vec::new(),
+                    returns: vec![
+                        Response::Call("not_found".to_string()),
+                        Response::Json {
+                            status: Some(200),
+                            body: None,
+                            serializer: None,
+                            include: Vec::new(),
+                            view: None,
+                        },
+                    ],
                     private: false,
+                    callbacks: Vec::new(),
+                    strong_params: None,
+                    calls: vec!["not_found".to_string(), "render".to_string()],
                 }],
             }],
             modules: Vec::new(),
             requires: Vec::new(),
         };
         let actual = parse_file(helper(input));
-        assert_eq!(actual.is_ok(), true);
+        assert!(actual.is_ok());
         assert_eq!(actual.unwrap(), expected);
     }
 
@@ -525,22 +822,43 @@ mod file_tests {
       end";
 
         let expected = RubyFile {
+            path: String::new(),
+            diagnostics: Vec::new(),
             controllers: vec![Controller {
                 name: "VersionController".to_string(),
                 parent: "ApplicationController".to_string(),
-                actions: vec![Action::SkipBeforeAction("check_auth_token".to_string())],
+                actions: vec![Action::SkipBeforeAction(Callback {
+                    methods: vec!["check_auth_token".to_string()],
+                    ..Callback::default()
+                })],
+                includes: Vec::new(),
                 methods: vec![Method {
                     name: "version".to_string(),
                     params: Vec::new(),
-                    returns: vec!["render none unknown".to_string()],
+                    param_types: Vec::new(),
+                    inputs: Vec::new(),
+                    ivars: Vec::new(),
+                    returns: vec![
+                        Response::Call("not_found".to_string()),
+                        Response::Json {
+                            status: Some(200),
+                            body: None,
+                            serializer: None,
+                            include: Vec::new(),
+                            view: None,
+                        },
+                    ],
                     private: false,
+                    callbacks: Vec::new(),
+                    strong_params: None,
+                    calls: vec!["not_found".to_string(), "render".to_string()],
                 }],
             }],
             modules: Vec::new(),
             requires: vec!["jwt".to_string()],
         };
         let actual = parse_file(helper(input));
-        assert_eq!(actual.is_ok(), true);
+        assert!(actual.is_ok());
         assert_eq!(actual.unwrap(), expected);
     }
 
@@ -565,27 +883,42 @@ mod file_tests {
             end
         ";
         let expected = RubyFile {
+            path: String::new(),
+            diagnostics: Vec::new(),
             controllers: Vec::new(),
             modules: vec![Module {
                 name: "Widget".to_string(),
                 methods: Vec::new(),
                 classes: vec![Controller {
-                    actions: vec![Action::SkipAuthMethods("".to_string())],
+                    actions: vec![Action::SkipAuthMethods(Callback::default())],
+                    includes: vec!["Widget::SnippetParser".to_string()],
                     name: "IntegrationSettingsController".to_string(),
                     parent: "ApplicationController".to_string(),
                     methods: vec![Method {
                         name: "index".to_string(),
                         params: Vec::new(),
-                        returns: Vec::new(),
+                        param_types: Vec::new(),
+                        inputs: Vec::new(),
+                        ivars: Vec::new(),
+                        returns: vec![Response::Call("json".to_string())],
                         private: false,
+                        callbacks: Vec::new(),
+                        strong_params: None,
+                        calls: vec![
+                            "parse_snippet".to_string(),
+                            "params".to_string(),
+                            "json".to_string(),
+                        ],
                     }],
                 }],
                 requires: Vec::new(),
+                actions: Vec::new(),
+                includes: Vec::new(),
             }],
             requires: Vec::new(),
         };
         let actual = parse_file(helper(input));
-        assert_eq!(actual.is_ok(), true);
+        assert!(actual.is_ok());
         assert_eq!(actual.unwrap(), expected);
     }
 
@@ -611,6 +944,8 @@ mod file_tests {
         ";
 
         let expected = RubyFile {
+            path: String::new(),
+            diagnostics: Vec::new(),
             controllers: Vec::new(),
             modules: vec![Module {
                 name: "Widget".to_string(),
@@ -618,21 +953,34 @@ mod file_tests {
                 classes: vec![Controller {
                     name: "Vendors::IntegrationSettingsController".to_string(),
                     parent: "ApplicationController".to_string(),
-                    actions: vec![Action::SkipAuthMethods("".to_string())],
+                    actions: vec![Action::SkipAuthMethods(Callback::default())],
+                    includes: vec!["Widget::SnippetParser".to_string()],
                     methods: vec![Method {
                         name: "index".to_string(),
                         params: Vec::new(),
-                        returns: Vec::new(),
+                        param_types: Vec::new(),
+                        inputs: Vec::new(),
+                        ivars: Vec::new(),
+                        returns: vec![Response::Call("json".to_string())],
                         private: false,
+                        callbacks: Vec::new(),
+                        strong_params: None,
+                        calls: vec![
+                            "parse_snippet".to_string(),
+                            "params".to_string(),
+                            "json".to_string(),
+                        ],
                     }],
                 }],
                 requires: Vec::new(),
+                actions: Vec::new(),
+                includes: Vec::new(),
             }],
             requires: Vec::new(),
         };
         let actual = parse_file(helper(input));
         println!("{:?}", actual);
-        assert_eq!(actual.is_ok(), true);
+        assert!(actual.is_ok());
         assert_eq!(actual.unwrap(), expected);
     }
 
@@ -649,27 +997,36 @@ mod file_tests {
         ";
 
         let expected = RubyFile {
+            path: String::new(),
+            diagnostics: Vec::new(),
             controllers: Vec::new(),
             modules: vec![Module {
                 name: "SortParams".to_string(),
                 methods: vec![Method {
                     name: "sorted_fields".to_string(),
                     params: Vec::new(),
-                    returns: Vec::new(),
+                    param_types: Vec::new(),
+                    inputs: Vec::new(),
+                    ivars: Vec::new(),
+                    returns: vec![Response::Call("foo".to_string())],
                     private: false,
+                    callbacks: Vec::new(),
+                    strong_params: None,
+                    calls: vec!["foo".to_string()],
                 }],
                 classes: Vec::new(),
                 requires: Vec::new(),
+                actions: Vec::new(),
+                includes: Vec::new(),
             }],
             requires: Vec::new(),
         };
         let actual = parse_file(helper(input));
         println!("{:?}", actual);
-        assert_eq!(actual.is_ok(), true);
+        assert!(actual.is_ok());
         assert_eq!(actual.unwrap(), expected);
     }
 
-
     #[test]
     fn parse_test6() {
         let input = "
@@ -682,25 +1039,218 @@ mod file_tests {
         ";
 
         let expected = RubyFile {
+            path: String::new(),
+            diagnostics: Vec::new(),
             controllers: vec![Controller {
                 name: "VersionController".to_string(),
                 parent: "ApplicationController".to_string(),
                 actions: Vec::new(),
-                methods: vec![
-                    Method{
-                        name: "version".to_string(),
-                        params: vec!["cat".to_string()],
-                        returns: Vec::new(),
-                        private: false
-                    }
-                ],
+                includes: Vec::new(),
+                methods: vec![Method {
+                    name: "version".to_string(),
+                    params: Vec::new(),
+                    param_types: Vec::new(),
+                    inputs: Vec::new(),
+                    ivars: Vec::new(),
+                    returns: vec![Response::Call("json".to_string())],
+                    private: false,
+                    callbacks: Vec::new(),
+                    strong_params: None,
+                    calls: vec!["json".to_string(), "param".to_string()],
+                }],
             }],
             modules: Vec::new(),
             requires: Vec::new(),
         };
         let actual = parse_file(helper(input));
         println!("{:?}", actual);
-        assert_eq!(actual.is_ok(), true);
+        assert!(actual.is_ok());
         assert_eq!(actual.unwrap(), expected);
     }
+
+    #[test]
+    fn parse_callbacks() {
+        let input = "
+        class DogsController < ApplicationController
+            before_action :load_dog, only: :show, if: :signed_in?, unless: -> { admin? }
+            prepend_before_action :set_locale
+            after_action :track, except: [:index]
+            skip_around_action :wrap
+            rescue_from ActiveRecord::RecordNotFound, ActiveRecord::RecordInvalid, with: :not_found
+            rescue_from Pundit::NotAuthorizedError do |e|
+                head :forbidden
+            end
+        end
+        ";
+
+        let actual = parse_file(helper(input)).unwrap();
+        assert_eq!(
+            actual.controllers[0].actions,
+            vec![
+                Action::BeforeAction(Callback {
+                    methods: vec!["load_dog".to_string()],
+                    only: vec!["show".to_string()],
+                    if_conditions: vec!["signed_in?".to_string()],
+                    unless_conditions: vec!["unknown".to_string()],
+                    ..Callback::default()
+                }),
+                Action::BeforeAction(Callback {
+                    methods: vec!["set_locale".to_string()],
+                    prepend: true,
+                    ..Callback::default()
+                }),
+                Action::AfterAction(Callback {
+                    methods: vec!["track".to_string()],
+                    except: vec!["index".to_string()],
+                    ..Callback::default()
+                }),
+                Action::SkipAroundAction(Callback {
+                    methods: vec!["wrap".to_string()],
+                    ..Callback::default()
+                }),
+                Action::RescueFrom(Rescue {
+                    exceptions: vec![
+                        "ActiveRecord::RecordNotFound".to_string(),
+                        "ActiveRecord::RecordInvalid".to_string()
+                    ],
+                    with: Some("not_found".to_string()),
+                }),
+                Action::RescueFrom(Rescue {
+                    exceptions: vec!["Pundit::NotAuthorizedError".to_string()],
+                    with: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_diagnostics() {
+        let input = "
+        class DogsController < ApplicationController
+            def index
+            end
+
+            @count = 1
+        end
+        ";
+        let file = parse_file(helper(input)).unwrap();
+        assert_eq!(file.diagnostics.len(), 1);
+        assert_eq!(file.diagnostics[0].code, SKIPPED_STATEMENT);
+        assert_eq!(file.diagnostics[0].severity, Severity::Warning);
+
+        let error = parse_file(helper("FOO = 1")).unwrap_err();
+        assert_eq!(error.code, EMPTY_FILE);
+        assert_eq!(error.severity, Severity::Error);
+    }
+
+    #[test]
+    fn parse_class_shapes() {
+        let input = "
+        class ApplicationController
+        end
+
+        class Api::V1::DogsController < ApplicationController
+            class << self
+                def permitted
+                end
+            end
+
+            def index
+            end
+        end
+
+        CatsController = Class.new(ApplicationController) do
+            before_action :set_cat
+
+            def show
+            end
+        end
+
+        Admin::BirdsController = Class.new(self.base)
+
+        class self::Plain < Base
+        end
+        ";
+        let file = parse_file(helper(input)).unwrap();
+        let names = file
+            .controllers
+            .iter()
+            .map(|c| (c.name.as_str(), c.parent.as_str(), c.methods.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("ApplicationController", "", 0),
+                ("Api::V1::DogsController", "ApplicationController", 1),
+                ("CatsController", "ApplicationController", 1),
+                ("Admin::BirdsController", "", 0),
+            ]
+        );
+        assert_eq!(file.controllers[2].actions.len(), 1);
+        assert_eq!(
+            file.diagnostics
+                .iter()
+                .map(|d| d.code)
+                .collect::<Vec<&str>>(),
+            vec![UNSUPPORTED_CLASS, UNSUPPORTED_CLASS]
+        );
+    }
+
+    #[test]
+    fn recover_syntax_errors() {
+        let input = "
+        class DogsController < ApplicationController
+            def index
+                class Inner; end
+                head :ok
+            end
+        end
+        ";
+        let parsed = Parser::new(input.as_bytes(), Default::default()).do_parse();
+        assert!(parsed.ast.is_none());
+        let file = parse_file(recover(input.as_bytes(), &parsed.diagnostics).unwrap()).unwrap();
+        assert_eq!(file.controllers[0].name, "DogsController");
+        assert_eq!(file.controllers[0].methods[0].name, "index");
+        assert_eq!(
+            file.controllers[0].methods[0].returns[0].status(),
+            Some(200)
+        );
+
+        let input = "class DogsController <\n";
+        let parsed = Parser::new(input.as_bytes(), Default::default()).do_parse();
+        assert!(recover(input.as_bytes(), &parsed.diagnostics).is_none());
+    }
+
+    #[test]
+    fn or_assign_params() {
+        let input = "
+        class DogsController < ApplicationController
+            def dog
+                @dog ||= Dog.find(params[:id])
+            end
+        end
+        ";
+        let file = parse_file(helper(input)).unwrap();
+        assert_eq!(
+            file.controllers[0].methods[0].params,
+            vec!["id".to_string()]
+        );
+    }
+
+    #[test]
+    fn single_statement_params() {
+        // a body with one statement isn't a Begin node, it's searched directly
+        let input = "
+        class VersionController < ApplicationController
+            def version
+                json(200, 'version', params[:cat])
+            end
+        end
+        ";
+        let file = parse_file(helper(input)).unwrap();
+        assert_eq!(
+            file.controllers[0].methods[0].params,
+            vec!["cat".to_string()]
+        );
+    }
 }
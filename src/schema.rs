@@ -0,0 +1,488 @@
+// the tables in db/schema.rb, used to give the attributes of models real types
+
+use lib_ruby_parser::{nodes, Node, Parser};
+
+use crate::{
+    inflection::{pluralize, singularize},
+    types::Type,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnType {
+    String,
+    Text,
+    Integer,
+    BigInt,
+    Float,
+    Decimal,
+    Boolean,
+    Date,
+    DateTime,
+    Time,
+    Json,
+    Uuid,
+    Binary,
+    Other(String),
+}
+
+impl ColumnType {
+    /// a column type in `schema.rb` or an attribute type in a model e.g. `attribute :price, :big_integer`
+    pub fn new(name: &str) -> ColumnType {
+        match name {
+            "string" | "citext" | "immutable_string" => ColumnType::String,
+            "text" => ColumnType::Text,
+            "integer" => ColumnType::Integer,
+            "bigint" | "big_integer" => ColumnType::BigInt,
+            "float" => ColumnType::Float,
+            "decimal" | "numeric" => ColumnType::Decimal,
+            "boolean" => ColumnType::Boolean,
+            "date" => ColumnType::Date,
+            "datetime" | "timestamp" | "timestamptz" => ColumnType::DateTime,
+            "time" => ColumnType::Time,
+            "json" | "jsonb" => ColumnType::Json,
+            "uuid" => ColumnType::Uuid,
+            "binary" => ColumnType::Binary,
+            other => ColumnType::Other(other.to_string()),
+        }
+    }
+
+    /// how the column is written out by `as_json`, decimals are strings to keep their precision
+    pub fn json_type(&self) -> Type {
+        match self {
+            ColumnType::Integer | ColumnType::BigInt => Type::Integer,
+            ColumnType::Float => Type::Number,
+            ColumnType::Boolean => Type::Boolean,
+            ColumnType::Date => Type::Date,
+            ColumnType::DateTime => Type::DateTime,
+            ColumnType::Json | ColumnType::Other(_) => Type::Unknown,
+            ColumnType::String
+            | ColumnType::Text
+            | ColumnType::Decimal
+            | ColumnType::Time
+            | ColumnType::Uuid
+            | ColumnType::Binary => Type::String,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub null: bool,
+    pub default: Option<String>,
+    pub array: bool,
+}
+
+impl Column {
+    fn new(name: &str, column_type: ColumnType) -> Column {
+        Column {
+            name: name.to_string(),
+            column_type,
+            null: true,
+            default: None,
+            array: false,
+        }
+    }
+
+    pub fn json_type(&self) -> Type {
+        let mut json_type = self.column_type.json_type();
+        if self.array {
+            json_type = Type::Array(Box::new(json_type));
+        }
+        if self.null {
+            json_type = Type::Nullable(Box::new(json_type));
+        }
+        json_type
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    pub columns: Vec<String>,
+    pub name: Option<String>,
+    pub unique: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub indexes: Vec<Index>,
+}
+
+impl Table {
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// `t.string "name", null: false`, `t.references :owner`, `t.timestamps` and `t.index`
+    fn column_definition(&mut self, send: &nodes::Send) {
+        let definition = options(&send.args);
+        let names = send
+            .args
+            .iter()
+            .filter_map(value_str)
+            .collect::<Vec<String>>();
+
+        let columns = match send.method_name.as_str() {
+            "index" => {
+                self.indexes.extend(index(&send.args));
+                return;
+            }
+            "timestamps" => vec![
+                Column::new("created_at", ColumnType::DateTime),
+                Column::new("updated_at", ColumnType::DateTime),
+            ],
+            "references" | "belongs_to" => names
+                .iter()
+                .flat_map(|name| {
+                    let column_type = option(&definition, "type")
+                        .and_then(value_str)
+                        .map_or(ColumnType::BigInt, |t| ColumnType::new(&t));
+                    let mut columns = vec![Column::new(&format!("{}_id", name), column_type)];
+                    if matches!(option(&definition, "polymorphic"), Some(Node::True(_))) {
+                        columns.push(Column::new(&format!("{}_type", name), ColumnType::String));
+                    }
+                    columns
+                })
+                .collect(),
+            // `t.column "name", :string`
+            "column" => match names.as_slice() {
+                [name, column_type, ..] => vec![Column::new(name, ColumnType::new(column_type))],
+                _ => vec![],
+            },
+            column_type => names
+                .iter()
+                .map(|name| Column::new(name, ColumnType::new(column_type)))
+                .collect(),
+        };
+
+        // timestamps are `null: false` since rails 5
+        let timestamps = send.method_name == "timestamps";
+        for mut column in columns {
+            column.null = match option(&definition, "null") {
+                Some(Node::False(_)) => false,
+                Some(_) => true,
+                None => !timestamps,
+            };
+            column.default = option(&definition, "default").and_then(literal);
+            column.array = matches!(option(&definition, "array"), Some(Node::True(_)));
+
+            if let Some(index) = option(&definition, "index") {
+                if !matches!(index, Node::False(_)) {
+                    // `index: { unique: true }`
+                    let index_options = options(std::slice::from_ref(index));
+                    self.indexes.push(Index {
+                        columns: vec![column.name.clone()],
+                        name: option(&index_options, "name").and_then(value_str),
+                        unique: matches!(option(&index_options, "unique"), Some(Node::True(_))),
+                    });
+                }
+            }
+
+            self.columns.retain(|existing| existing.name != column.name);
+            self.columns.push(column);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    pub tables: Vec<Table>,
+}
+
+fn value_str(node: &Node) -> Option<String> {
+    match node {
+        Node::Str(str) => Some(str.value.to_string_lossy()),
+        Node::Sym(sym) => Some(sym.name.to_string_lossy()),
+        _ => None,
+    }
+}
+
+fn value_list(node: &Node) -> Vec<String> {
+    match node {
+        Node::Array(array) => array.elements.iter().filter_map(value_str).collect(),
+        node => value_str(node).into_iter().collect(),
+    }
+}
+
+/// the literal a default is set to, defaults computed by the database e.g. `-> { "now()" }` are skipped
+fn literal(node: &Node) -> Option<String> {
+    match node {
+        Node::Int(int) => Some(int.value.clone()),
+        Node::Float(float) => Some(float.value.clone()),
+        Node::True(_) => Some("true".to_string()),
+        Node::False(_) => Some("false".to_string()),
+        node => value_str(node),
+    }
+}
+
+fn options(args: &[Node]) -> Vec<(String, &Node)> {
+    args.iter()
+        .filter_map(|arg| match arg {
+            Node::Kwargs(nodes::Kwargs { pairs, .. }) | Node::Hash(nodes::Hash { pairs, .. }) => {
+                Some(pairs)
+            }
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|pair| match pair {
+            Node::Pair(pair) => value_str(&pair.key).map(|key| (key, &*pair.value)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn option<'a>(options: &[(String, &'a Node)], name: &str) -> Option<&'a Node> {
+    options
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| *value)
+}
+
+fn index(args: &[Node]) -> Option<Index> {
+    let options = options(args);
+    Some(Index {
+        columns: args.first().map(value_list)?,
+        name: option(&options, "name").and_then(value_str),
+        unique: matches!(option(&options, "unique"), Some(Node::True(_))),
+    })
+}
+
+fn create_table(send: &nodes::Send, body: Option<&Node>) -> Option<Table> {
+    let name = send.args.first().and_then(value_str)?;
+    let options = options(&send.args);
+    let mut table = Table {
+        name,
+        columns: Vec::new(),
+        indexes: Vec::new(),
+    };
+
+    // rails adds a bigint primary key called id unless told otherwise
+    let primary_key = option(&options, "primary_key")
+        .and_then(value_str)
+        .unwrap_or_else(|| "id".to_string());
+    match option(&options, "id") {
+        Some(Node::False(_)) => {}
+        Some(id) => {
+            let column_type = value_str(id).map_or(ColumnType::BigInt, |id| ColumnType::new(&id));
+            table.columns.push(Column {
+                null: false,
+                ..Column::new(&primary_key, column_type)
+            });
+        }
+        None => table.columns.push(Column {
+            null: false,
+            ..Column::new(&primary_key, ColumnType::BigInt)
+        }),
+    }
+
+    let statements = match body {
+        Some(Node::Begin(begin)) => begin.statements.iter().collect(),
+        Some(node) => vec![node],
+        None => vec![],
+    };
+    for statement in statements {
+        if let Node::Send(column) = statement {
+            table.column_definition(column);
+        }
+    }
+
+    Some(table)
+}
+
+impl Schema {
+    fn visit(&mut self, node: &Node) {
+        match node {
+            Node::Begin(begin) => {
+                for statement in &begin.statements {
+                    self.visit(statement);
+                }
+            }
+            Node::Block(block) => {
+                if let Node::Send(send) = &*block.call {
+                    match send.method_name.as_str() {
+                        "define" => {
+                            if let Some(body) = &block.body {
+                                self.visit(body);
+                            }
+                        }
+                        "create_table" => {
+                            self.tables
+                                .extend(create_table(send, block.body.as_deref()));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Node::Send(send) => match send.method_name.as_str() {
+                "create_table" => self.tables.extend(create_table(send, None)),
+                "add_index" => {
+                    let table = send.args.first().and_then(value_str);
+                    let index = send.args.get(1..).and_then(index);
+                    if let (Some(table), Some(index)) = (table, index) {
+                        if let Some(table) = self.tables.iter_mut().find(|t| t.name == table) {
+                            table.indexes.push(index);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+
+    /// the table for a record held in a variable or association e.g. `@dog`, `dogs` or `owner`
+    fn table_for(&self, name: &str) -> Option<&Table> {
+        let name = name.trim_start_matches('@').trim_end_matches("[]");
+        self.table(&pluralize(&singularize(name)))
+    }
+
+    /// the type of an attribute read in a view e.g. `@dog.name` or `@dogs[].owner.created_at`
+    pub fn type_of(&self, expression: &str) -> Option<Type> {
+        let mut segments = expression.split('.');
+        let mut table = self.table_for(segments.next()?)?;
+        let mut segments = segments.peekable();
+
+        while let Some(segment) = segments.next() {
+            if segments.peek().is_none() {
+                return table.column(segment).map(Column::json_type);
+            }
+            table = self.table_for(segment)?;
+        }
+
+        None
+    }
+}
+
+/// Parses the tables out of `ActiveRecord::Schema.define`
+pub fn parse_schema(input: &str) -> Result<Schema, String> {
+    let ast = Parser::new(input.as_bytes(), Default::default())
+        .do_parse()
+        .ast
+        .ok_or("unable to parse schema")?;
+    let mut schema = Schema::default();
+    schema.visit(&ast);
+    Ok(schema)
+}
+
+#[cfg(test)]
+mod schema_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{parse_schema, Column, ColumnType, Index, Schema, Table};
+    use crate::types::Type;
+
+    fn helper() -> Schema {
+        parse_schema(
+            r#"
+            ActiveRecord::Schema[7.0].define(version: 2021_01_01_000000) do
+                enable_extension "plpgsql"
+
+                create_table "dogs", force: :cascade do |t|
+                    t.string "name", null: false
+                    t.integer "age", default: 0
+                    t.decimal "weight", precision: 5, scale: 2
+                    t.string "tags", array: true
+                    t.references :owner, null: false, foreign_key: true
+                    t.timestamps
+                    t.index ["name"], name: "index_dogs_on_name", unique: true
+                end
+
+                create_table "owners", id: :uuid do |t|
+                    t.string "name"
+                    t.boolean "verified", default: false, null: false
+                end
+
+                add_index "dogs", ["owner_id"]
+            end
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn column(name: &str, column_type: ColumnType, null: bool) -> Column {
+        Column {
+            null,
+            ..Column::new(name, column_type)
+        }
+    }
+
+    #[test]
+    fn tables() {
+        let schema = helper();
+
+        assert_eq!(
+            schema.table("dogs"),
+            Some(&Table {
+                name: "dogs".to_string(),
+                columns: vec![
+                    column("id", ColumnType::BigInt, false),
+                    column("name", ColumnType::String, false),
+                    Column {
+                        default: Some("0".to_string()),
+                        ..column("age", ColumnType::Integer, true)
+                    },
+                    column("weight", ColumnType::Decimal, true),
+                    Column {
+                        array: true,
+                        ..column("tags", ColumnType::String, true)
+                    },
+                    column("owner_id", ColumnType::BigInt, false),
+                    column("created_at", ColumnType::DateTime, false),
+                    column("updated_at", ColumnType::DateTime, false),
+                ],
+                indexes: vec![
+                    Index {
+                        columns: vec!["name".to_string()],
+                        name: Some("index_dogs_on_name".to_string()),
+                        unique: true,
+                    },
+                    Index {
+                        columns: vec!["owner_id".to_string()],
+                        name: None,
+                        unique: false,
+                    },
+                ],
+            })
+        );
+
+        assert_eq!(
+            schema.table("owners").unwrap().columns,
+            vec![
+                column("id", ColumnType::Uuid, false),
+                column("name", ColumnType::String, true),
+                Column {
+                    default: Some("false".to_string()),
+                    ..column("verified", ColumnType::Boolean, false)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn expression_types() {
+        let schema = helper();
+
+        assert_eq!(schema.type_of("@dog.name"), Some(Type::String));
+        assert_eq!(
+            schema.type_of("@dogs[].age"),
+            Some(Type::Nullable(Box::new(Type::Integer)))
+        );
+        assert_eq!(schema.type_of("@dog.owner.verified"), Some(Type::Boolean));
+        assert_eq!(schema.type_of("dog.created_at"), Some(Type::DateTime));
+        assert_eq!(
+            schema.type_of("@dog.tags"),
+            Some(Type::Nullable(Box::new(Type::Array(Box::new(
+                Type::String
+            )))))
+        );
+        assert_eq!(schema.type_of("@dog.bark"), None);
+        assert_eq!(schema.type_of("@cat.name"), None);
+    }
+}
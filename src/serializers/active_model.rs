@@ -0,0 +1,345 @@
+use lib_ruby_parser::{nodes, Node};
+
+use super::{on, Association, AssociationKind, Serializer, Serializers};
+use crate::{
+    inflection::camelize,
+    ivars::source,
+    types::Type,
+    views::{block_args, expression, key, option, value_type, Env},
+};
+
+/// the value of a block or method, the last statement it runs
+pub(crate) fn last(node: &Node) -> &Node {
+    match node {
+        Node::Begin(nodes::Begin { statements, .. }) => statements.last().map_or(node, last),
+        node => node,
+    }
+}
+
+/// a block's first argument is the record being serialized e.g. `attribute :name do |dog| dog.name end`
+pub(crate) fn block_env(block: &nodes::Block) -> Env {
+    block_args(block)
+        .into_iter()
+        .take(1)
+        .map(|arg| (arg, "object".to_string()))
+        .collect()
+}
+
+/// the type of an attribute worked out in ruby, only reads off `object` can be followed
+pub(crate) fn computed(body: Option<&Node>, env: &Env) -> Type {
+    match body.map(|body| value_type(last(body), env)) {
+        Some(Type::Expression(expression))
+            if expression == "object" || expression.starts_with("object.") =>
+        {
+            Type::Expression(expression)
+        }
+        Some(Type::Expression(_)) | None => Type::Unknown,
+        Some(value) => value,
+    }
+}
+
+pub(crate) struct Class<'a> {
+    // methods defined on the serializer, attributes named after one are read from it instead of the object
+    methods: Vec<(&'a str, Option<&'a Node>)>,
+    pub(crate) serializer: Serializer,
+}
+
+impl<'a> Class<'a> {
+    fn attribute(&self, name: &str) -> Type {
+        match self.methods.iter().find(|(method, _)| *method == name) {
+            Some((_, body)) => computed(*body, &Vec::new()),
+            None => Type::Expression(format!("object.{}", name)),
+        }
+    }
+
+    fn push_attribute(&mut self, name: String, value: Type) {
+        let attributes = &mut self.serializer.attributes;
+        attributes.retain(|(existing, _)| existing != &name);
+        attributes.push((name, value));
+    }
+
+    /// `attributes :id, :name`, `attribute :name, key: :title` and `attribute(:name) { object.name.titleize }`
+    fn send(&mut self, send: &nodes::Send, block: Option<&nodes::Block>) {
+        let name = match send.args.first().and_then(key) {
+            Some(name) => name,
+            None => return,
+        };
+        let renamed = option(&send.args, "key")
+            .and_then(key)
+            .unwrap_or_else(|| name.clone());
+
+        match send.method_name.as_str() {
+            "attributes" => {
+                for name in send.args.iter().filter_map(key) {
+                    let value = self.attribute(&name);
+                    self.push_attribute(name, value);
+                }
+            }
+            "attribute" => {
+                let value = match block {
+                    Some(block) => computed(block.body.as_deref(), &block_env(block)),
+                    None => self.attribute(&name),
+                };
+                self.push_attribute(renamed, value);
+            }
+            "has_many" | "has_one" | "belongs_to" => {
+                let kind = match send.method_name.as_str() {
+                    "has_many" => AssociationKind::HasMany,
+                    "has_one" => AssociationKind::HasOne,
+                    _ => AssociationKind::BelongsTo,
+                };
+                // `has_many :toys do object.toys.where(good: true) end`
+                let expression = block
+                    .and_then(|block| Some((block.body.as_deref()?, block_env(block))))
+                    .and_then(|(body, env)| expression(last(body), &env))
+                    .filter(|expression| expression.starts_with("object."))
+                    .unwrap_or_else(|| format!("object.{}", name));
+
+                let associations = &mut self.serializer.associations;
+                associations.retain(|association| association.key != renamed);
+                associations.push(Association {
+                    key: renamed,
+                    name,
+                    kind,
+                    expression,
+                    view: None,
+                    // jsonapi-serializer also takes the serializer's name e.g. `serializer: :toy`
+                    serializer: option(&send.args, "serializer").and_then(|serializer| {
+                        match serializer {
+                            Node::Const(_) => Some(source(serializer)),
+                            serializer => {
+                                Some(format!("{}Serializer", camelize(&key(serializer)?)))
+                            }
+                        }
+                    }),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// the statements in a class body along with the serializer they're declaring
+    pub(crate) fn new(
+        name: &str,
+        parent: Option<String>,
+        body: Option<&'a Node>,
+    ) -> (Class<'a>, Vec<&'a Node>) {
+        let statements = match body {
+            Some(Node::Begin(begin)) => begin.statements.iter().collect(),
+            Some(node) => vec![node],
+            None => Vec::new(),
+        };
+
+        let class = Class {
+            methods: statements
+                .iter()
+                .filter_map(|statement| match statement {
+                    Node::Def(def) => Some((def.name.as_str(), def.body.as_deref())),
+                    _ => None,
+                })
+                .collect(),
+            serializer: Serializer {
+                name: name.to_string(),
+                parent,
+                ..Serializer::default()
+            },
+        };
+        (class, statements)
+    }
+
+    /// `attributes`, `attribute` and the associations whether they're given a block or not
+    pub(crate) fn statement(&mut self, statement: &Node) {
+        match statement {
+            Node::Send(send) if send.recv.is_none() => self.send(send, None),
+            Node::Block(block) => {
+                if let Node::Send(send) = &*block.call {
+                    self.send(send, Some(block));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The attributes and associations an `ActiveModel::Serializer` class body declares
+pub fn parse_serializer(name: &str, parent: Option<String>, body: Option<&Node>) -> Serializer {
+    let (mut class, statements) = Class::new(name, parent, body);
+    for statement in statements {
+        class.statement(statement);
+    }
+    class.serializer
+}
+
+/// The JSON a serializer renders for the record held in `object`, associations are nested
+/// through their own serializers
+pub fn render(
+    serializers: &Serializers,
+    serializer: &Serializer,
+    object: &str,
+    seen: &mut Vec<String>,
+) -> Type {
+    let (attributes, associations) = serializers.fields(serializer);
+    let locals = vec![("object".to_string(), object.to_string())];
+    let mut fields = attributes
+        .into_iter()
+        .map(|(name, value)| (name, value.substitute(&locals)))
+        .collect::<Vec<(String, Type)>>();
+
+    for association in &associations {
+        let expression = on(&association.expression, object);
+        let mut nested = |object: &str| match serializers.associated(serializer, association) {
+            // serializers nested in themselves e.g. a dog's friends are left unknown
+            Some(associated) if !seen.contains(&associated.name) => {
+                seen.push(associated.name.clone());
+                let value = render(serializers, associated, object, seen);
+                seen.pop();
+                value
+            }
+            _ => Type::Unknown,
+        };
+
+        let value = match association.kind {
+            AssociationKind::HasMany => Type::Array(Box::new(nested(&format!("{}[]", expression)))),
+            AssociationKind::HasOne => Type::Nullable(Box::new(nested(&expression))),
+            AssociationKind::BelongsTo => nested(&expression),
+        };
+        fields.retain(|(existing, _)| existing != &association.key);
+        fields.push((association.key.clone(), value));
+    }
+
+    Type::Object(fields)
+}
+
+#[cfg(test)]
+mod active_model_tests {
+    use lib_ruby_parser::{Node, Parser};
+    use pretty_assertions::assert_eq;
+
+    use super::parse_serializer;
+    use crate::{
+        ivars::source,
+        serializers::{Association, AssociationKind, Serializer},
+        types::Type,
+    };
+
+    fn helper(input: &str) -> Serializer {
+        match Parser::new(input.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap()
+        {
+            Node::Class(class) => parse_serializer(
+                &source(&class.name),
+                class.superclass.as_deref().map(source),
+                class.body.as_deref(),
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    fn attribute(name: &str, value: Type) -> (String, Type) {
+        (name.to_string(), value)
+    }
+
+    fn expr(expression: &str) -> Type {
+        Type::Expression(expression.to_string())
+    }
+
+    #[test]
+    fn attributes() {
+        let input = "
+        class DogSerializer < ActiveModel::Serializer
+            attributes :id, :name, :nickname
+            attribute :breed, key: :kind
+            attribute :good_boy do
+                true
+            end
+            attribute(:owner_name) { object.owner.name }
+            attribute :greeting do
+                \"woof #{object.name}\"
+            end
+
+            def nickname
+                helpers.nickname_for(object)
+            end
+        end
+        ";
+
+        assert_eq!(
+            helper(input),
+            Serializer {
+                name: "DogSerializer".to_string(),
+                parent: Some("ActiveModel::Serializer".to_string()),
+                format: None,
+                attributes: vec![
+                    attribute("id", expr("object.id")),
+                    attribute("name", expr("object.name")),
+                    attribute("nickname", Type::Unknown),
+                    attribute("kind", expr("object.breed")),
+                    attribute("good_boy", Type::Boolean),
+                    attribute("owner_name", expr("object.owner.name")),
+                    attribute("greeting", Type::String),
+                ],
+                associations: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn associations() {
+        let input = "
+        class Api::DogSerializer < BaseSerializer
+            has_many :toys, serializer: Api::ToySerializer
+            has_many :friends do
+                object.friends.where(good: true)
+            end
+            belongs_to :owner, key: :human
+            has_one :collar
+        end
+        ";
+
+        let association =
+            |key: &str, name: &str, kind, expression: &str, serializer: Option<&str>| Association {
+                key: key.to_string(),
+                name: name.to_string(),
+                kind,
+                expression: expression.to_string(),
+                serializer: serializer.map(str::to_string),
+                view: None,
+            };
+
+        assert_eq!(
+            helper(input).associations,
+            vec![
+                association(
+                    "toys",
+                    "toys",
+                    AssociationKind::HasMany,
+                    "object.toys",
+                    Some("Api::ToySerializer")
+                ),
+                association(
+                    "friends",
+                    "friends",
+                    AssociationKind::HasMany,
+                    "object.friends",
+                    None
+                ),
+                association(
+                    "human",
+                    "owner",
+                    AssociationKind::BelongsTo,
+                    "object.owner",
+                    None
+                ),
+                association(
+                    "collar",
+                    "collar",
+                    AssociationKind::HasOne,
+                    "object.collar",
+                    None
+                ),
+            ]
+        );
+    }
+}
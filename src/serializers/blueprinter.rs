@@ -0,0 +1,323 @@
+use lib_ruby_parser::Node;
+
+use super::{
+    active_model::{block_env, computed, last},
+    on, Association, AssociationKind, Format, Serializer, Serializers, View,
+};
+use crate::{
+    inflection::is_plural,
+    ivars::source,
+    types::Type,
+    views::{expression, key, option},
+};
+
+/// `class DogBlueprint < Blueprinter::Base` or a class inheriting from another blueprint
+pub fn is_blueprint(name: &str, parent: Option<&str>) -> bool {
+    name.ends_with("Blueprint")
+        || parent.is_some_and(|parent| {
+            parent.trim_start_matches("::") == "Blueprinter::Base" || parent.ends_with("Blueprint")
+        })
+}
+
+fn statements(body: Option<&Node>) -> Vec<&Node> {
+    match body {
+        Some(Node::Begin(begin)) => begin.statements.iter().collect(),
+        Some(node) => vec![node],
+        None => Vec::new(),
+    }
+}
+
+fn push_attribute(view: &mut View, name: String, value: Type) {
+    view.attributes.retain(|(existing, _)| existing != &name);
+    view.attributes.push((name, value));
+}
+
+/// `identifier`, `field(s)`, `association`, `include_view` and `exclude` in the blueprint or one of its views,
+/// views are only declared at the top of the blueprint
+fn parse_view(body: Option<&Node>, view: &mut View, mut views: Option<&mut Vec<View>>) {
+    for statement in statements(body) {
+        let (send, block) = match statement {
+            Node::Send(send) => (send, None),
+            Node::Block(block) => match &*block.call {
+                Node::Send(send) => (send, Some(block)),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if send.recv.is_some() {
+            continue;
+        }
+        let name = match send.args.first().and_then(key) {
+            Some(name) => name,
+            None => continue,
+        };
+        // `field :birthday, name: :born_on`
+        let renamed = option(&send.args, "name")
+            .and_then(key)
+            .unwrap_or_else(|| name.clone());
+
+        match send.method_name.as_str() {
+            "identifier" | "field" => {
+                let value = match block {
+                    Some(block) => computed(block.body.as_deref(), &block_env(block)),
+                    None => Type::Expression(format!("object.{}", name)),
+                };
+                push_attribute(view, renamed, value);
+            }
+            "fields" => {
+                for name in send.args.iter().filter_map(key) {
+                    let value = Type::Expression(format!("object.{}", name));
+                    push_attribute(view, name, value);
+                }
+            }
+            // blueprints don't say whether an association is a collection so it's guessed from the name
+            "association" => {
+                let expression = block
+                    .and_then(|block| Some((block.body.as_deref()?, block_env(block))))
+                    .and_then(|(body, env)| expression(last(body), &env))
+                    .filter(|expression| expression.starts_with("object."))
+                    .unwrap_or_else(|| format!("object.{}", name));
+
+                view.associations
+                    .retain(|association| association.key != renamed);
+                view.associations.push(Association {
+                    key: renamed,
+                    kind: if is_plural(&name) {
+                        AssociationKind::HasMany
+                    } else {
+                        AssociationKind::HasOne
+                    },
+                    name,
+                    expression,
+                    serializer: option(&send.args, "blueprint")
+                        .filter(|blueprint| matches!(blueprint, Node::Const(_)))
+                        .map(source),
+                    view: option(&send.args, "view").and_then(key),
+                });
+            }
+            "include_view" | "include_views" => {
+                view.include_views.extend(send.args.iter().filter_map(key))
+            }
+            "exclude" | "excludes" => view.excludes.extend(send.args.iter().filter_map(key)),
+            "view" => {
+                if let (Some(views), Some(block)) = (views.as_deref_mut(), block) {
+                    let mut named = View {
+                        name,
+                        ..View::default()
+                    };
+                    parse_view(block.body.as_deref(), &mut named, None);
+                    views.retain(|existing| existing.name != named.name);
+                    views.push(named);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The default fields and the views a `Blueprinter::Base` class body declares
+pub fn parse_blueprint(name: &str, parent: Option<String>, body: Option<&Node>) -> Serializer {
+    let mut default = View::default();
+    let mut views = Vec::new();
+    parse_view(body, &mut default, Some(&mut views));
+
+    Serializer {
+        name: name.to_string(),
+        parent,
+        format: Some(Format::Blueprinter { views }),
+        attributes: default.attributes,
+        associations: default.associations,
+    }
+}
+
+/// adds a view's fields on top of the ones already rendered, along with the views it includes
+fn apply_view(
+    serializers: &Serializers,
+    serializer: &Serializer,
+    name: &str,
+    fields: &mut (Vec<(String, Type)>, Vec<Association>),
+    applied: &mut Vec<String>,
+) {
+    if applied.iter().any(|view| view == name) {
+        return;
+    }
+    applied.push(name.to_string());
+
+    // views are inherited from parent blueprints
+    let view = serializers
+        .ancestors(serializer)
+        .into_iter()
+        .find_map(|ancestor| match &ancestor.format {
+            Some(Format::Blueprinter { views }) => views.iter().find(|view| view.name == name),
+            _ => None,
+        });
+    let view = match view {
+        Some(view) => view,
+        None => return,
+    };
+
+    for included in &view.include_views {
+        apply_view(serializers, serializer, included, fields, applied);
+    }
+
+    let (attributes, associations) = fields;
+    for (name, value) in &view.attributes {
+        attributes.retain(|(existing, _)| existing != name);
+        attributes.push((name.clone(), value.clone()));
+    }
+    for association in &view.associations {
+        associations.retain(|existing| existing.key != association.key);
+        associations.push(association.clone());
+    }
+    attributes.retain(|(name, _)| !view.excludes.contains(name));
+    associations.retain(|association| !view.excludes.contains(&association.key));
+}
+
+/// The JSON a blueprint renders for the record held in `object` with the view asked for,
+/// without a view only the default fields are rendered
+pub fn render(
+    serializers: &Serializers,
+    serializer: &Serializer,
+    object: &str,
+    view: Option<&str>,
+    seen: &mut Vec<String>,
+) -> Type {
+    let mut fields = serializers.fields(serializer);
+    if let Some(view) = view {
+        apply_view(serializers, serializer, view, &mut fields, &mut Vec::new());
+    }
+    let (attributes, associations) = fields;
+
+    let locals = vec![("object".to_string(), object.to_string())];
+    let mut fields = attributes
+        .into_iter()
+        .map(|(name, value)| (name, value.substitute(&locals)))
+        .collect::<Vec<(String, Type)>>();
+
+    for association in &associations {
+        let expression = on(&association.expression, object);
+        let mut nested = |object: &str| match serializers.associated(serializer, association) {
+            // blueprints rendering themselves e.g. a dog's friends are left unknown
+            Some(associated) if !seen.contains(&associated.name) => {
+                seen.push(associated.name.clone());
+                let value = render(
+                    serializers,
+                    associated,
+                    object,
+                    association.view.as_deref(),
+                    seen,
+                );
+                seen.pop();
+                value
+            }
+            _ => Type::Unknown,
+        };
+
+        let value = match association.kind {
+            AssociationKind::HasMany => Type::Array(Box::new(nested(&format!("{}[]", expression)))),
+            _ => Type::Nullable(Box::new(nested(&expression))),
+        };
+        fields.push((association.key.clone(), value));
+    }
+
+    Type::Object(fields)
+}
+
+#[cfg(test)]
+mod blueprinter_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::render;
+    use crate::{
+        ruby_parser::classes,
+        serializers::{parse_serializer, Format, Serializers},
+        types::Type,
+    };
+
+    fn helper(input: &str) -> Serializers {
+        let ast = Parser::new(input.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap();
+        let mut found = Vec::new();
+        classes(&ast, "", &mut found);
+
+        let mut serializers = Serializers::default();
+        for (name, class) in found {
+            serializers.insert(parse_serializer(&name, class));
+        }
+        serializers
+    }
+
+    fn field(name: &str, value: Type) -> (String, Type) {
+        (name.to_string(), value)
+    }
+
+    fn expr(expression: &str) -> Type {
+        Type::Expression(expression.to_string())
+    }
+
+    #[test]
+    fn views() {
+        let serializers = helper(
+            "
+            class DogBlueprint < Blueprinter::Base
+                identifier :id
+                fields :name, :age
+                field :birthday, name: :born_on
+
+                view :normal do
+                    field :nickname do |dog, options|
+                        \"#{dog.name}!\"
+                    end
+                    association :owner, blueprint: OwnerBlueprint
+                end
+
+                view :extended do
+                    include_view :normal
+                    association :toys, blueprint: ToyBlueprint, view: :extended
+                    exclude :age
+                end
+            end
+
+            class ToyBlueprint < Blueprinter::Base
+                identifier :id
+                view :extended do
+                    field :squeaky
+                end
+            end
+            ",
+        );
+        let dog = serializers.lookup("DogBlueprint", "").unwrap();
+        assert!(matches!(dog.format, Some(Format::Blueprinter { .. })));
+
+        let render = |view| render(&serializers, dog, "@dog", view, &mut Vec::new());
+        assert_eq!(
+            render(None),
+            Type::Object(vec![
+                field("id", expr("@dog.id")),
+                field("name", expr("@dog.name")),
+                field("age", expr("@dog.age")),
+                field("born_on", expr("@dog.birthday")),
+            ])
+        );
+        assert_eq!(
+            render(Some("extended")),
+            Type::Object(vec![
+                field("id", expr("@dog.id")),
+                field("name", expr("@dog.name")),
+                field("born_on", expr("@dog.birthday")),
+                field("nickname", Type::String),
+                field("owner", Type::Nullable(Box::new(Type::Unknown))),
+                field(
+                    "toys",
+                    Type::Array(Box::new(Type::Object(vec![
+                        field("id", expr("@dog.toys[].id")),
+                        field("squeaky", expr("@dog.toys[].squeaky")),
+                    ])))
+                ),
+            ])
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use lib_ruby_parser::Node;
+
+use super::{active_model::Class, on, AssociationKind, Format, Serializer, Serializers};
+use crate::{
+    inflection::{singularize, underscore},
+    ivars::source,
+    types::Type,
+    views::key,
+};
+
+/// `include JSONAPI::Serializer` or the older `include FastJsonapi::ObjectSerializer`
+pub fn is_jsonapi(body: Option<&Node>) -> bool {
+    let statements = match body {
+        Some(Node::Begin(begin)) => begin.statements.iter().collect(),
+        Some(node) => vec![node],
+        None => Vec::new(),
+    };
+
+    statements.into_iter().any(|statement| match statement {
+        Node::Send(send) if send.recv.is_none() && send.method_name == "include" => {
+            send.args.iter().any(|arg| {
+                matches!(
+                    source(arg).trim_start_matches("::"),
+                    "JSONAPI::Serializer" | "FastJsonapi::ObjectSerializer"
+                )
+            })
+        }
+        _ => false,
+    })
+}
+
+/// The attributes, relationships, type and links a jsonapi-serializer class body declares
+pub fn parse_serializer(name: &str, parent: Option<String>, body: Option<&Node>) -> Serializer {
+    let (mut class, statements) = Class::new(name, parent, body);
+    let mut record_type = None;
+    let mut links = Vec::new();
+
+    for statement in statements {
+        let send = match statement {
+            Node::Send(send) if send.recv.is_none() => send,
+            Node::Block(block) => match &*block.call {
+                Node::Send(send) => send,
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        match send.method_name.as_str() {
+            "set_type" => record_type = send.args.first().and_then(key),
+            // `link :self, :url` or `link(:self) { |dog| "/dogs/#{dog.id}" }`
+            "link" => links.extend(send.args.first().and_then(key)),
+            _ => class.statement(statement),
+        }
+    }
+
+    let mut serializer = class.serializer;
+    serializer.format = Some(Format::JsonApi { record_type, links });
+    serializer
+}
+
+/// `set_type :dog`, otherwise the serializer's name e.g. `dog_toy` for `Api::DogToySerializer`
+fn record_type(serializer: &Serializer) -> Type {
+    match &serializer.format {
+        Some(Format::JsonApi {
+            record_type: Some(record_type),
+            ..
+        }) => Type::Literal(record_type.clone()),
+        _ => {
+            let name = serializer.name.rsplit("::").next().unwrap_or_default();
+            Type::Literal(underscore(name.trim_end_matches("Serializer")))
+        }
+    }
+}
+
+fn identifier(record_type: Type) -> Type {
+    Type::Object(vec![
+        ("id".to_string(), Type::String),
+        ("type".to_string(), record_type),
+    ])
+}
+
+/// `{ id, type, attributes, relationships, links }` for the record held in `object`
+fn resource(serializers: &Serializers, serializer: &Serializer, object: &str) -> Type {
+    let (attributes, associations) = serializers.fields(serializer);
+    let locals = vec![("object".to_string(), object.to_string())];
+
+    // ids are always written as strings
+    let mut fields = vec![
+        ("id".to_string(), Type::String),
+        ("type".to_string(), record_type(serializer)),
+        (
+            "attributes".to_string(),
+            Type::Object(
+                attributes
+                    .into_iter()
+                    .map(|(name, value)| (name, value.substitute(&locals)))
+                    .collect(),
+            ),
+        ),
+    ];
+
+    if !associations.is_empty() {
+        let relationships = associations
+            .iter()
+            .map(|association| {
+                let identifier =
+                    identifier(Type::Literal(underscore(&singularize(&association.name))));
+                let data = match association.kind {
+                    AssociationKind::HasMany => Type::Array(Box::new(identifier)),
+                    AssociationKind::HasOne => Type::Nullable(Box::new(identifier)),
+                    AssociationKind::BelongsTo => identifier,
+                };
+                (
+                    association.key.clone(),
+                    Type::Object(vec![("data".to_string(), data)]),
+                )
+            })
+            .collect();
+        fields.push(("relationships".to_string(), Type::Object(relationships)));
+    }
+
+    let mut links = Vec::new();
+    for ancestor in serializers.ancestors(serializer).into_iter().rev() {
+        if let Some(Format::JsonApi {
+            links: declared, ..
+        }) = &ancestor.format
+        {
+            for link in declared {
+                links.retain(|(existing, _)| existing != link);
+                links.push((link.clone(), Type::String));
+            }
+        }
+    }
+    if !links.is_empty() {
+        fields.push(("links".to_string(), Type::Object(links)));
+    }
+
+    Type::Object(fields)
+}
+
+/// The JSON:API document a serializer renders for `object` with the records asked for in
+/// `include` e.g. `toys` or `toys.owner` listed under `included`
+pub fn document(
+    serializers: &Serializers,
+    serializer: &Serializer,
+    object: &str,
+    many: bool,
+    include: &[String],
+) -> Type {
+    let (object, data) = if many {
+        let object = format!("{}[]", object);
+        let data = Type::Array(Box::new(resource(serializers, serializer, &object)));
+        (object, data)
+    } else {
+        (
+            object.to_string(),
+            resource(serializers, serializer, object),
+        )
+    };
+
+    let mut included: Vec<(String, Type)> = Vec::new();
+    for path in include {
+        let mut current = serializer;
+        let mut object = object.clone();
+        for segment in path.split('.') {
+            let (_, associations) = serializers.fields(current);
+            let association = match associations
+                .iter()
+                .find(|association| association.key == segment || association.name == segment)
+            {
+                Some(association) => association,
+                None => break,
+            };
+            let associated = match serializers.associated(current, association) {
+                Some(associated) => associated,
+                None => break,
+            };
+
+            object = on(&association.expression, &object);
+            if association.kind == AssociationKind::HasMany {
+                object += "[]";
+            }
+            if !included.iter().any(|(name, _)| name == &associated.name) {
+                included.push((
+                    associated.name.clone(),
+                    resource(serializers, associated, &object),
+                ));
+            }
+            current = associated;
+        }
+    }
+
+    let mut fields = vec![("data".to_string(), data)];
+    match included.len() {
+        0 => {}
+        1 => fields.push((
+            "included".to_string(),
+            Type::Array(Box::new(included.remove(0).1)),
+        )),
+        _ => fields.push((
+            "included".to_string(),
+            Type::Array(Box::new(Type::OneOf(
+                included.into_iter().map(|(_, resource)| resource).collect(),
+            ))),
+        )),
+    }
+    Type::Object(fields)
+}
+
+#[cfg(test)]
+mod jsonapi_tests {
+    use lib_ruby_parser::{Node, Parser};
+    use pretty_assertions::assert_eq;
+
+    use super::{document, is_jsonapi, parse_serializer};
+    use crate::{
+        ivars::source,
+        serializers::{Format, Serializers},
+        types::Type,
+    };
+
+    fn helper(inputs: &[&str]) -> Serializers {
+        let mut serializers = Serializers::default();
+        for input in inputs {
+            if let Node::Class(class) = Parser::new(input.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap()
+            {
+                assert!(is_jsonapi(class.body.as_deref()));
+                serializers.insert(parse_serializer(
+                    &source(&class.name),
+                    class.superclass.as_deref().map(source),
+                    class.body.as_deref(),
+                ));
+            }
+        }
+        serializers
+    }
+
+    fn field(name: &str, value: Type) -> (String, Type) {
+        (name.to_string(), value)
+    }
+
+    fn expr(expression: &str) -> Type {
+        Type::Expression(expression.to_string())
+    }
+
+    fn identifier(record_type: &str) -> Type {
+        Type::Object(vec![
+            field("id", Type::String),
+            field("type", Type::Literal(record_type.to_string())),
+        ])
+    }
+
+    #[test]
+    fn parse() {
+        let serializers = helper(&["
+            class DogSerializer
+                include JSONAPI::Serializer
+                set_type :pet
+                attributes :name
+                attribute :shouty_name do |dog|
+                    dog.name.upcase
+                end
+                has_many :toys, serializer: :toy
+                link :self do |dog|
+                    \"/dogs/#{dog.id}\"
+                end
+            end
+            "]);
+        let dog = serializers.lookup("DogSerializer", "").unwrap();
+
+        assert_eq!(
+            dog.format,
+            Some(Format::JsonApi {
+                record_type: Some("pet".to_string()),
+                links: vec!["self".to_string()],
+            })
+        );
+        assert_eq!(
+            dog.attributes,
+            vec![
+                field("name", expr("object.name")),
+                field("shouty_name", expr("object.name.upcase")),
+            ]
+        );
+        assert_eq!(
+            dog.associations[0].serializer,
+            Some("ToySerializer".to_string())
+        );
+    }
+
+    #[test]
+    fn documents() {
+        let serializers = helper(&[
+            "
+            class DogSerializer
+                include JSONAPI::Serializer
+                attributes :name
+                has_many :toys
+                belongs_to :owner
+            end
+            ",
+            "
+            class ToySerializer
+                include JSONAPI::Serializer
+                attributes :squeaky
+                belongs_to :owner
+            end
+            ",
+            "
+            class OwnerSerializer
+                include JSONAPI::Serializer
+                attributes :email
+            end
+            ",
+        ]);
+        let dog = serializers.lookup("DogSerializer", "").unwrap();
+
+        assert_eq!(
+            document(&serializers, dog, "@dogs", true, &[]),
+            Type::Object(vec![field(
+                "data",
+                Type::Array(Box::new(Type::Object(vec![
+                    field("id", Type::String),
+                    field("type", Type::Literal("dog".to_string())),
+                    field(
+                        "attributes",
+                        Type::Object(vec![field("name", expr("@dogs[].name"))])
+                    ),
+                    field(
+                        "relationships",
+                        Type::Object(vec![
+                            field(
+                                "toys",
+                                Type::Object(vec![field(
+                                    "data",
+                                    Type::Array(Box::new(identifier("toy")))
+                                )])
+                            ),
+                            field(
+                                "owner",
+                                Type::Object(vec![field("data", identifier("owner"))])
+                            ),
+                        ])
+                    ),
+                ])))
+            )])
+        );
+
+        let included = match document(
+            &serializers,
+            dog,
+            "@dog",
+            false,
+            &["toys.owner".to_string()],
+        ) {
+            Type::Object(mut fields) => fields.pop().unwrap(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            included,
+            field(
+                "included",
+                Type::Array(Box::new(Type::OneOf(vec![
+                    Type::Object(vec![
+                        field("id", Type::String),
+                        field("type", Type::Literal("toy".to_string())),
+                        field(
+                            "attributes",
+                            Type::Object(vec![field("squeaky", expr("@dog.toys[].squeaky"))])
+                        ),
+                        field(
+                            "relationships",
+                            Type::Object(vec![field(
+                                "owner",
+                                Type::Object(vec![field("data", identifier("owner"))])
+                            )])
+                        ),
+                    ]),
+                    Type::Object(vec![
+                        field("id", Type::String),
+                        field("type", Type::Literal("owner".to_string())),
+                        field(
+                            "attributes",
+                            Type::Object(vec![field("email", expr("@dog.toys[].owner.email"))])
+                        ),
+                    ]),
+                ])))
+            )
+        );
+    }
+}
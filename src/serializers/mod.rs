@@ -0,0 +1,418 @@
+use std::fs;
+
+use lib_ruby_parser::{nodes, Parser};
+use walkdir::WalkDir;
+
+use crate::{
+    diagnostics::Diagnostic,
+    inflection::{camelize, singularize},
+    ivars::{classify, source, Ivar, Value},
+    responses::Response,
+    ruby_parser::classes,
+    types::Type,
+};
+
+pub mod active_model;
+pub mod blueprinter;
+pub mod jsonapi;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssociationKind {
+    HasMany,
+    HasOne,
+    BelongsTo,
+}
+
+/// `has_many :toys, serializer: ToySerializer`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Association {
+    /// the key written into the JSON, `key:` renames it
+    pub key: String,
+    pub name: String,
+    pub kind: AssociationKind,
+    /// what the association reads off the record e.g. `object.toys`
+    pub expression: String,
+    /// the serializer given, otherwise it's looked up from the association's name
+    pub serializer: Option<String>,
+    /// the blueprint view it's rendered with e.g. `association :toys, blueprint: ToyBlueprint, view: :normal`
+    pub view: Option<String>,
+}
+
+/// `view :extended do ... end` in a blueprint, rendered on top of the blueprint's default fields
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct View {
+    pub name: String,
+    pub attributes: Vec<(String, Type)>,
+    pub associations: Vec<Association>,
+    /// `include_view :normal`
+    pub include_views: Vec<String>,
+    /// `exclude :age`
+    pub excludes: Vec<String>,
+}
+
+/// how a serializer lays out the records it renders
+#[derive(Debug, Clone, PartialEq)]
+pub enum Format {
+    /// active_model_serializers, associations are nested in the record
+    ActiveModel,
+    /// jsonapi-serializer, each record is a resource with its associations as relationships
+    JsonApi {
+        /// `set_type :dog`, otherwise named after the serializer
+        record_type: Option<String>,
+        /// `link :self, :url`
+        links: Vec<String>,
+    },
+    /// blueprinter, the fields rendered depend on the view asked for
+    Blueprinter { views: Vec<View> },
+}
+
+/// A serializer class, attributes are read off the record being serialized which is held in `object`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Serializer {
+    pub name: String,
+    pub parent: Option<String>,
+    /// none when it renders the same way as its parent
+    pub format: Option<Format>,
+    pub attributes: Vec<(String, Type)>,
+    pub associations: Vec<Association>,
+}
+
+/// `object.toys` read off `@dog` is `@dog.toys`
+fn on(expression: &str, object: &str) -> String {
+    match expression.strip_prefix("object") {
+        Some(rest) => format!("{}{}", object, rest),
+        None => expression.to_string(),
+    }
+}
+
+/// parses a class with whichever serializer library it uses
+fn parse_serializer(name: &str, class: &nodes::Class) -> Serializer {
+    let parent = class.superclass.as_deref().map(source);
+    let body = class.body.as_deref();
+    if jsonapi::is_jsonapi(body) {
+        jsonapi::parse_serializer(name, parent, body)
+    } else if blueprinter::is_blueprint(name, parent.as_deref()) {
+        blueprinter::parse_blueprint(name, parent, body)
+    } else {
+        active_model::parse_serializer(name, parent, body)
+    }
+}
+
+/// Every serializer class under `app/serializers` by its full name e.g. `Api::V1::DogSerializer`
+#[derive(Debug, Default)]
+pub struct Serializers {
+    serializers: Vec<Serializer>,
+    /// syntax errors in files that couldn't be parsed
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Serializers {
+    pub fn insert(&mut self, serializer: Serializer) {
+        self.serializers
+            .retain(|existing| existing.name != serializer.name);
+        self.serializers.push(serializer);
+    }
+
+    /// adds the serializers from another folder e.g. `app/blueprints` alongside `app/serializers`
+    pub fn merge(&mut self, other: Serializers) {
+        for serializer in other.serializers {
+            self.insert(serializer);
+        }
+        self.diagnostics.extend(other.diagnostics);
+    }
+
+    /// Parses every serializer class under `path`
+    pub fn parse_dir(path: &str) -> Result<Serializers, Box<dyn std::error::Error>> {
+        let mut serializers = Serializers::default();
+        for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+            let file = entry.path();
+            if !file.is_file() || file.extension().is_none_or(|extension| extension != "rb") {
+                continue;
+            }
+
+            let input = fs::read(file)?;
+            let parsed = Parser::new(&input, Default::default()).do_parse();
+            let ast = match parsed.ast {
+                Some(ast) => ast,
+                None => {
+                    serializers.diagnostics.extend(Diagnostic::syntax_errors(
+                        &file.display().to_string(),
+                        &input,
+                        &parsed.diagnostics,
+                    ));
+                    continue;
+                }
+            };
+
+            let mut found = Vec::new();
+            classes(&ast, "", &mut found);
+            for (name, class) in found {
+                serializers.insert(parse_serializer(&name, class));
+            }
+        }
+
+        Ok(serializers)
+    }
+
+    /// finds a serializer the way rails resolves constants, from the innermost namespace out
+    /// e.g. `DogSerializer` in `Api::V1` tries `Api::V1::DogSerializer`, `Api::DogSerializer` then `DogSerializer`
+    pub fn lookup(&self, name: &str, namespace: &str) -> Option<&Serializer> {
+        let name = name.trim_start_matches("::");
+        let mut namespace = namespace;
+        loop {
+            let candidate = match namespace {
+                "" => name.to_string(),
+                namespace => format!("{}::{}", namespace, name),
+            };
+            if let Some(serializer) = self.serializers.iter().find(|s| s.name == candidate) {
+                return Some(serializer);
+            }
+            if namespace.is_empty() {
+                return None;
+            }
+            namespace = namespace.rfind("::").map_or("", |i| &namespace[..i]);
+        }
+    }
+
+    /// the serializer followed by the serializers it inherits from
+    fn ancestors<'a>(&'a self, serializer: &'a Serializer) -> Vec<&'a Serializer> {
+        let mut ancestors = vec![serializer];
+        while let Some(parent) = ancestors
+            .last()
+            .and_then(|last| Some((last.parent.as_ref()?, &last.name)))
+            .and_then(|(parent, namespace)| self.lookup(parent, namespace))
+        {
+            if ancestors
+                .iter()
+                .any(|ancestor| ancestor.name == parent.name)
+            {
+                break;
+            }
+            ancestors.push(parent);
+        }
+        ancestors
+    }
+
+    /// how the serializer or the nearest class it inherits from lays out records
+    pub fn format(&self, serializer: &Serializer) -> Format {
+        self.ancestors(serializer)
+            .into_iter()
+            .find_map(|ancestor| ancestor.format.clone())
+            .unwrap_or(Format::ActiveModel)
+    }
+
+    /// the attributes and associations a serializer renders including the ones it inherits
+    pub fn fields(&self, serializer: &Serializer) -> (Vec<(String, Type)>, Vec<Association>) {
+        let mut attributes: Vec<(String, Type)> = Vec::new();
+        let mut associations: Vec<Association> = Vec::new();
+        for ancestor in self.ancestors(serializer).into_iter().rev() {
+            for (name, value) in &ancestor.attributes {
+                attributes.retain(|(existing, _)| existing != name);
+                attributes.push((name.clone(), value.clone()));
+            }
+            for association in &ancestor.associations {
+                associations.retain(|existing| existing.key != association.key);
+                associations.push(association.clone());
+            }
+        }
+        (attributes, associations)
+    }
+
+    /// the serializer an association renders with, looked up from inside the serializer declaring it
+    pub fn associated(
+        &self,
+        serializer: &Serializer,
+        association: &Association,
+    ) -> Option<&Serializer> {
+        let name = association
+            .serializer
+            .clone()
+            .unwrap_or_else(|| format!("{}Serializer", camelize(&singularize(&association.name))));
+        self.lookup(&name, &serializer.name)
+    }
+
+    /// The JSON a `render json:` sends through the serializer it names or the one for the model it renders,
+    /// serializers are looked up from the controller's namespace e.g. `Api::V1` for `Api::V1::Dogs`
+    pub fn for_response(
+        &self,
+        response: &Response,
+        ivars: &[Ivar],
+        controller: &str,
+    ) -> Option<Type> {
+        let (body, serializer, include, view) = match response {
+            Response::Json {
+                body: Some(body),
+                serializer,
+                include,
+                view,
+                ..
+            } => (body, serializer, include, view),
+            _ => return None,
+        };
+        let namespace = controller.rfind("::").map_or("", |i| &controller[..i]);
+
+        let (model, many) = match classify(body, ivars, &[]) {
+            Value::Record(model) => (Some(model), false),
+            Value::Collection(model) => (Some(model), true),
+            _ => (None, false),
+        };
+        let name = serializer
+            .clone()
+            .or_else(|| model.map(|model| format!("{}Serializer", model)))?;
+        let serializer = self.lookup(&name, namespace)?;
+
+        Some(match self.format(serializer) {
+            Format::JsonApi { .. } => jsonapi::document(self, serializer, body, many, include),
+            Format::Blueprinter { .. } if many => Type::Array(Box::new(blueprinter::render(
+                self,
+                serializer,
+                &format!("{}[]", body),
+                view.as_deref(),
+                &mut vec![serializer.name.clone()],
+            ))),
+            Format::Blueprinter { .. } => blueprinter::render(
+                self,
+                serializer,
+                body,
+                view.as_deref(),
+                &mut vec![serializer.name.clone()],
+            ),
+            Format::ActiveModel if many => Type::Array(Box::new(active_model::render(
+                self,
+                serializer,
+                &format!("{}[]", body),
+                &mut vec![serializer.name.clone()],
+            ))),
+            Format::ActiveModel => {
+                active_model::render(self, serializer, body, &mut vec![serializer.name.clone()])
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod serializers_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::{parse_serializer, Serializers};
+    use crate::ruby_parser::classes;
+    use crate::{ivars::search_for_ivars, responses::Response, types::Type};
+
+    fn helper(input: &str) -> Serializers {
+        let ast = Parser::new(input.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap();
+        let mut found = Vec::new();
+        classes(&ast, "", &mut found);
+
+        let mut serializers = Serializers::default();
+        for (name, class) in found {
+            serializers.insert(parse_serializer(&name, class));
+        }
+        serializers
+    }
+
+    fn field(name: &str, value: Type) -> (String, Type) {
+        (name.to_string(), value)
+    }
+
+    fn expr(expression: &str) -> Type {
+        Type::Expression(expression.to_string())
+    }
+
+    #[test]
+    fn lookup() {
+        let serializers = helper(
+            "
+            class DogSerializer < ActiveModel::Serializer; end
+            module Api
+                module V1
+                    class DogSerializer < ActiveModel::Serializer; end
+                end
+            end
+            ",
+        );
+
+        let name = |namespace: &str| {
+            serializers
+                .lookup("DogSerializer", namespace)
+                .map(|serializer| serializer.name.clone())
+        };
+        assert_eq!(name("Api::V1"), Some("Api::V1::DogSerializer".to_string()));
+        assert_eq!(name("Api::V2"), Some("DogSerializer".to_string()));
+        assert_eq!(name(""), Some("DogSerializer".to_string()));
+    }
+
+    #[test]
+    fn responses() {
+        let serializers = helper(
+            "
+            class BaseSerializer < ActiveModel::Serializer
+                attributes :id
+            end
+
+            class DogSerializer < BaseSerializer
+                attributes :name
+                has_many :toys
+                belongs_to :owner
+                has_many :friends, serializer: DogSerializer
+            end
+
+            class ToySerializer < BaseSerializer
+                attribute :squeaky do
+                    object.squeaks.positive?
+                end
+            end
+            ",
+        );
+        let ivars = search_for_ivars(
+            &Parser::new(
+                "@dog = Dog.find(params[:id])
+                @dogs = Dog.all"
+                    .as_bytes(),
+                Default::default(),
+            )
+            .do_parse()
+            .ast
+            .unwrap(),
+        );
+        let json = |body: &str, serializer: Option<&str>| Response::Json {
+            status: Some(200),
+            body: Some(body.to_string()),
+            serializer: serializer.map(str::to_string),
+            include: Vec::new(),
+            view: None,
+        };
+
+        let dog = |dog: &str| {
+            Type::Object(vec![
+                field("id", expr(&format!("{}.id", dog))),
+                field("name", expr(&format!("{}.name", dog))),
+                field(
+                    "toys",
+                    Type::Array(Box::new(Type::Object(vec![
+                        field("id", expr(&format!("{}.toys[].id", dog))),
+                        field("squeaky", Type::Boolean),
+                    ]))),
+                ),
+                field("owner", Type::Unknown),
+                field("friends", Type::Array(Box::new(Type::Unknown))),
+            ])
+        };
+
+        assert_eq!(
+            serializers.for_response(&json("@dog", None), &ivars, "Dogs"),
+            Some(dog("@dog"))
+        );
+        assert_eq!(
+            serializers.for_response(&json("@dogs", Some("DogSerializer")), &ivars, "Api::Dogs"),
+            Some(Type::Array(Box::new(dog("@dogs[]"))))
+        );
+        assert_eq!(
+            serializers.for_response(&json("@owner", None), &ivars, "Dogs"),
+            None
+        );
+    }
+}
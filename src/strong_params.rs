@@ -0,0 +1,264 @@
+// strong parameters e.g. `params.require(:dog).permit(:name, tags: [], owner: [:name])`
+
+use lib_ruby_parser::{nodes, traverse::Visitor, Node};
+
+use crate::types::Type;
+
+/// what a permitted key accepts
+#[derive(Debug, Clone, PartialEq)]
+pub enum Permitted {
+    /// `:name`
+    Scalar,
+    /// `tags: []`
+    Array,
+    /// `address: [:street, :zip]`
+    Hash(Vec<(String, Permitted)>),
+    /// `metadata: {}` permits any keys
+    AnyHash,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StrongParams {
+    /// `params.require(:dog)` raises when `dog` is missing
+    pub require: Option<String>,
+    pub permitted: Vec<(String, Permitted)>,
+}
+
+impl Permitted {
+    /// form and query values arrive as strings, nested keys keep their structure
+    pub fn value_type(&self) -> Type {
+        match self {
+            Permitted::Scalar => Type::String,
+            Permitted::Array => Type::Array(Box::new(Type::String)),
+            Permitted::Hash(permitted) => fields(permitted),
+            Permitted::AnyHash => Type::Object(Vec::new()),
+        }
+    }
+}
+
+fn fields(permitted: &[(String, Permitted)]) -> Type {
+    Type::Object(
+        permitted
+            .iter()
+            .map(|(name, permitted)| (name.clone(), permitted.value_type()))
+            .collect(),
+    )
+}
+
+impl StrongParams {
+    /// the type of a top level key e.g. `{ name: string }` for `dog` in `params.require(:dog).permit(:name)`
+    pub fn key_type(&self, name: &str) -> Option<Type> {
+        match &self.require {
+            Some(root) if root == name => Some(fields(&self.permitted)),
+            Some(_) => None,
+            None => self
+                .permitted
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, permitted)| permitted.value_type()),
+        }
+    }
+
+    /// the keys sent at the top level of the request
+    pub fn keys(&self) -> Vec<String> {
+        match &self.require {
+            Some(root) => vec![root.clone()],
+            None => self
+                .permitted
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect(),
+        }
+    }
+}
+
+fn name(node: &Node) -> Option<String> {
+    match node {
+        Node::Sym(sym) => Some(sym.name.to_string_lossy()),
+        Node::Str(str) => Some(str.value.to_string_lossy()),
+        _ => None,
+    }
+}
+
+fn is_params(node: &Option<Box<Node>>) -> bool {
+    matches!(node.as_deref(), Some(Node::Send(send)) if send.recv.is_none() && send.method_name == "params")
+}
+
+fn permitted_value(value: &Node) -> Permitted {
+    match value {
+        Node::Array(array) if array.elements.is_empty() => Permitted::Array,
+        Node::Array(array) => Permitted::Hash(permitted_list(&array.elements)),
+        Node::Hash(hash) if hash.pairs.is_empty() => Permitted::AnyHash,
+        Node::Hash(hash) => Permitted::Hash(permitted_list(&hash.pairs)),
+        _ => Permitted::Scalar,
+    }
+}
+
+/// `:name, tags: [], address: [:street]`
+fn permitted_list(args: &[Node]) -> Vec<(String, Permitted)> {
+    let mut permitted = Vec::new();
+    for arg in args {
+        match arg {
+            Node::Kwargs(nodes::Kwargs { pairs, .. }) | Node::Hash(nodes::Hash { pairs, .. }) => {
+                permitted.extend(permitted_list(pairs))
+            }
+            Node::Pair(pair) => {
+                if let Some(key) = name(&pair.key) {
+                    permitted.push((key, permitted_value(&pair.value)));
+                }
+            }
+            arg => {
+                if let Some(key) = name(arg) {
+                    permitted.push((key, Permitted::Scalar));
+                }
+            }
+        }
+    }
+    permitted
+}
+
+/// `params.require(:dog)` or `params.fetch(:dog, {})`
+fn required(send: &nodes::Send) -> Option<(bool, String)> {
+    match send.method_name.as_str() {
+        "require" | "fetch" if is_params(&send.recv) => {
+            Some((send.method_name == "require", name(send.args.first()?)?))
+        }
+        _ => None,
+    }
+}
+
+fn parse_strong_params(send: &nodes::Send) -> Option<StrongParams> {
+    match send.method_name.as_str() {
+        "permit" => {
+            let require = match send.recv.as_deref() {
+                _ if is_params(&send.recv) => None,
+                Some(Node::Send(recv)) => Some(required(recv)?),
+                _ => return None,
+            };
+            let permitted = permitted_list(&send.args);
+            Some(match require {
+                Some((true, root)) => StrongParams {
+                    require: Some(root),
+                    permitted,
+                },
+                // `fetch` doesn't raise so the root key is optional
+                Some((false, root)) => StrongParams {
+                    require: None,
+                    permitted: vec![(root, Permitted::Hash(permitted))],
+                },
+                None => StrongParams {
+                    require: None,
+                    permitted,
+                },
+            })
+        }
+        // rails 8 `params.expect(dog: [:name, :age])`
+        "expect" if is_params(&send.recv) => match permitted_list(&send.args).as_slice() {
+            [(root, Permitted::Hash(permitted))] => Some(StrongParams {
+                require: Some(root.clone()),
+                permitted: permitted.clone(),
+            }),
+            permitted => Some(StrongParams {
+                require: None,
+                permitted: permitted.to_vec(),
+            }),
+        },
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Search {
+    strong_params: Option<StrongParams>,
+}
+
+impl Visitor for Search {
+    fn on_send(&mut self, send: &nodes::Send) {
+        if self.strong_params.is_none() {
+            self.strong_params = parse_strong_params(send);
+        }
+
+        if let Some(recv) = &send.recv {
+            self.visit(recv);
+        }
+        self.visit_all(&send.args);
+    }
+}
+
+/// The first strong parameters a method body permits
+pub fn search_for_strong_params(body: &Node) -> Option<StrongParams> {
+    let mut search = Search::default();
+    search.visit(body);
+    search.strong_params
+}
+
+#[cfg(test)]
+mod strong_params_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::{search_for_strong_params, Permitted, StrongParams};
+
+    fn helper(input: &str) -> Option<StrongParams> {
+        search_for_strong_params(
+            &Parser::new(input.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+        )
+    }
+
+    fn scalar(name: &str) -> (String, Permitted) {
+        (name.to_string(), Permitted::Scalar)
+    }
+
+    #[test]
+    fn require_and_permit() {
+        assert_eq!(
+            helper(
+                "params.require(:user).permit(:name, 'email', tags: [], meta: {}, address: [:street, { geo: [:lat] }])"
+            ),
+            Some(StrongParams {
+                require: Some("user".to_string()),
+                    permitted: vec![
+                        scalar("name"),
+                        scalar("email"),
+                        ("tags".to_string(), Permitted::Array),
+                        ("meta".to_string(), Permitted::AnyHash),
+                        (
+                            "address".to_string(),
+                            Permitted::Hash(vec![
+                                scalar("street"),
+                                ("geo".to_string(), Permitted::Hash(vec![scalar("lat")])),
+                            ])
+                        ),
+                    ],
+                })
+        );
+    }
+
+    #[test]
+    fn other_forms() {
+        assert_eq!(
+            helper("params.permit(:page, :per_page)"),
+            Some(StrongParams {
+                require: None,
+                permitted: vec![scalar("page"), scalar("per_page")],
+            })
+        );
+        assert_eq!(
+            helper("params.fetch(:filter, {}).permit(:name)"),
+            Some(StrongParams {
+                require: None,
+                permitted: vec![("filter".to_string(), Permitted::Hash(vec![scalar("name")]))],
+            })
+        );
+        assert_eq!(
+            helper("params.expect(user: [:name])"),
+            Some(StrongParams {
+                require: Some("user".to_string()),
+                permitted: vec![scalar("name")],
+            })
+        );
+    }
+}
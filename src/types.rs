@@ -0,0 +1,156 @@
+/// The shape of a JSON value as far as it can be worked out without running any ruby
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Unknown,
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Date,
+    DateTime,
+    /// a string that's always the same e.g. the `type` of a JSON:API resource
+    Literal(String),
+    /// one of a fixed set of strings e.g. a rails `enum` or an `inclusion` validation
+    Enum(Vec<String>),
+    /// a record id passed to `find`, usually an integer but primary keys can be strings e.g. uuids
+    Id,
+    /// a value that can also be `null` e.g. a column without `null: false`
+    Nullable(Box<Type>),
+    Array(Box<Type>),
+    Object(Vec<(String, Type)>),
+    /// the value of a ruby expression such as `@dog.name`, `@dogs[]` is an element of `@dogs`
+    Expression(String),
+    /// a partial that hasn't been inlined yet, along with the expressions passed in as its locals
+    Partial {
+        name: String,
+        locals: Vec<(String, String)>,
+    },
+    /// every field of each type merged into one object e.g. an object that also renders a partial
+    AllOf(Vec<Type>),
+    /// any one of the types e.g. the different records in a JSON:API `included` list
+    OneOf(Vec<Type>),
+    /// a value a model validates before it's saved
+    Constrained(Box<Type>, Constraints),
+}
+
+/// what a model's validations say about an attribute
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Constraints {
+    /// `presence: true`, only when it always applies i.e. without `if:`, `unless:` or `on:`
+    pub required: bool,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// `format: { with: /\A[a-z]+\z/ }` as an ECMA regex
+    pub pattern: Option<String>,
+}
+
+fn substitute_expression(expression: String, locals: &[(String, String)]) -> String {
+    for (local, value) in locals {
+        if let Some(rest) = expression.strip_prefix(local.as_str()) {
+            if rest.is_empty() || rest.starts_with('.') || rest.starts_with('[') {
+                return format!("{}{}", value, rest);
+            }
+        }
+    }
+    expression
+}
+
+impl Type {
+    /// replaces the partial's local variables with the expressions that were passed in for them
+    pub fn substitute(self, locals: &[(String, String)]) -> Type {
+        match self {
+            Type::Expression(expression) => {
+                Type::Expression(substitute_expression(expression, locals))
+            }
+            Type::Array(item) => Type::Array(Box::new(item.substitute(locals))),
+            Type::Object(fields) => Type::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, field)| (name, field.substitute(locals)))
+                    .collect(),
+            ),
+            Type::Partial {
+                name,
+                locals: partial_locals,
+            } => Type::Partial {
+                name,
+                locals: partial_locals
+                    .into_iter()
+                    .map(|(local, value)| (local, substitute_expression(value, locals)))
+                    .collect(),
+            },
+            Type::AllOf(types) => {
+                Type::AllOf(types.into_iter().map(|t| t.substitute(locals)).collect())
+            }
+            Type::OneOf(types) => {
+                Type::OneOf(types.into_iter().map(|t| t.substitute(locals)).collect())
+            }
+            other => other,
+        }
+    }
+
+    /// gives the expressions `lookup` knows about a real type e.g. from the columns in `schema.rb`
+    pub fn resolve_expressions<F: Fn(&str) -> Option<Type>>(self, lookup: &F) -> Type {
+        match self {
+            Type::Expression(expression) => {
+                lookup(&expression).unwrap_or(Type::Expression(expression))
+            }
+            Type::Array(item) => Type::Array(Box::new(item.resolve_expressions(lookup))),
+            Type::Nullable(value) => Type::Nullable(Box::new(value.resolve_expressions(lookup))),
+            Type::Object(fields) => Type::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, field)| (name, field.resolve_expressions(lookup)))
+                    .collect(),
+            ),
+            Type::AllOf(types) => Type::AllOf(
+                types
+                    .into_iter()
+                    .map(|t| t.resolve_expressions(lookup))
+                    .collect(),
+            ),
+            Type::OneOf(types) => Type::OneOf(
+                types
+                    .into_iter()
+                    .map(|t| t.resolve_expressions(lookup))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    /// merges objects in an `AllOf` together once there is nothing left to inline
+    pub fn flatten(self) -> Type {
+        match self {
+            Type::AllOf(types) => {
+                let types = types.into_iter().map(Type::flatten).collect::<Vec<Type>>();
+                if types.iter().all(|t| matches!(t, Type::Object(_))) {
+                    let mut fields: Vec<(String, Type)> = Vec::new();
+                    for t in types {
+                        if let Type::Object(object) = t {
+                            for (name, field) in object {
+                                fields.retain(|(existing, _)| existing != &name);
+                                fields.push((name, field));
+                            }
+                        }
+                    }
+                    Type::Object(fields)
+                } else if types.len() == 1 {
+                    types.into_iter().next().unwrap()
+                } else {
+                    Type::AllOf(types)
+                }
+            }
+            Type::Array(item) => Type::Array(Box::new(item.flatten())),
+            Type::OneOf(types) => Type::OneOf(types.into_iter().map(Type::flatten).collect()),
+            Type::Object(fields) => Type::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, field)| (name, field.flatten()))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+}
@@ -0,0 +1,553 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::{
+    params::ParamLocation,
+    strong_params::{Permitted, StrongParams},
+    types::Type,
+    Endpoint,
+};
+
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' || first == '$' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        }
+        _ => false,
+    }
+}
+
+fn property(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_string()
+    } else {
+        serde_json::Value::String(name.to_string()).to_string()
+    }
+}
+
+fn type_name(endpoint: &Endpoint) -> String {
+    pascal_case(&format!(
+        "{}_{}",
+        endpoint.request.controller, endpoint.request.action
+    ))
+}
+
+/// one name per controller#action, `admin/dogs#index` and `admin_dogs#index` both
+/// pascal case to `AdminDogsIndex` so the later ones get a numeric suffix
+fn type_names(endpoints: &[Endpoint]) -> HashMap<(&str, &str), String> {
+    let mut names = HashMap::new();
+    let mut taken = HashSet::new();
+    for endpoint in endpoints {
+        let key = (
+            endpoint.request.controller.as_str(),
+            endpoint.request.action.as_str(),
+        );
+        if names.contains_key(&key) {
+            continue;
+        }
+        let base = type_name(endpoint);
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while !taken.insert(name.clone()) {
+            name = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        names.insert(key, name);
+    }
+    names
+}
+
+/// rails only names the first route for a uri so the others borrow its name,
+/// routes that are never named fall back to the controller and action
+fn route_name(endpoint: &Endpoint, endpoints: &[Endpoint]) -> String {
+    if !endpoint.request.prefix.is_empty() {
+        return endpoint.request.prefix.clone();
+    }
+
+    endpoints
+        .iter()
+        .find(|e| e.request.uri == endpoint.request.uri && !e.request.prefix.is_empty())
+        .map(|e| e.request.prefix.clone())
+        .unwrap_or_else(|| {
+            format!(
+                "{}_{}",
+                endpoint.request.controller.replace('/', "_"),
+                endpoint.request.action
+            )
+        })
+}
+
+/// permitted keys are optional unless the model validates their presence, rails drops the ones
+/// that aren't sent. `value` is the param's type which can be narrower than what's permitted e.g. an enum
+fn permitted_type(permitted: &Permitted, value: &Type, indent: usize) -> String {
+    match permitted {
+        Permitted::Hash(permitted) => {
+            let mut out = String::from("{\n");
+            for (name, permitted) in permitted {
+                let field = match value {
+                    Type::Object(fields) => fields
+                        .iter()
+                        .find(|(field, _)| field == name)
+                        .map(|(_, field)| field.clone()),
+                    _ => None,
+                }
+                .unwrap_or_else(|| permitted.value_type());
+                let optional = match &field {
+                    Type::Constrained(_, constraints) if constraints.required => "",
+                    _ => "?",
+                };
+                out += &format!(
+                    "{}  {}{}: {};\n",
+                    " ".repeat(indent),
+                    property(name),
+                    optional,
+                    permitted_type(permitted, &field, indent + 2)
+                );
+            }
+            out + &" ".repeat(indent) + "}"
+        }
+        Permitted::AnyHash => "Record<string, unknown>".to_string(),
+        _ => ts_type(value, indent),
+    }
+}
+
+fn request_interface(endpoint: &Endpoint, name: &str, out: &mut String) -> std::fmt::Result {
+    writeln!(out, "export interface {}Request {{", name)?;
+    for param in &endpoint.params {
+        let key = property(&param.name);
+        let inferred = endpoint
+            .method
+            .param_types
+            .iter()
+            .any(|(inferred, _)| inferred == &param.name);
+        let permitted = match &endpoint.method.strong_params {
+            Some(StrongParams {
+                require: Some(root),
+                permitted,
+            }) if root == &param.name => {
+                writeln!(
+                    out,
+                    "  {}: {};",
+                    key,
+                    permitted_type(&Permitted::Hash(permitted.clone()), &param.param_type, 2)
+                )?;
+                continue;
+            }
+            Some(StrongParams {
+                require: None,
+                permitted,
+            }) if !inferred => permitted.iter().find(|(key, _)| key == &param.name),
+            _ => None,
+        };
+
+        match permitted {
+            _ if param.location == ParamLocation::Path => {
+                writeln!(out, "  {}: {};", key, ts_type(&param.param_type, 2))?
+            }
+            Some((_, value)) => writeln!(
+                out,
+                "  {}?: {};",
+                key,
+                permitted_type(value, &param.param_type, 2)
+            )?,
+            None => writeln!(out, "  {}?: {};", key, ts_type(&param.param_type, 2))?,
+        }
+    }
+    writeln!(out, "}}")?;
+    writeln!(out)
+}
+
+fn fields(fields: &[(String, Type)], indent: usize) -> String {
+    let mut out = String::from("{\n");
+    for (name, field) in fields {
+        out += &format!(
+            "{}  {}: {};\n",
+            " ".repeat(indent),
+            property(name),
+            ts_type(field, indent + 2)
+        );
+    }
+    out + &" ".repeat(indent) + "}"
+}
+
+/// the TypeScript type for a value, expressions that can't be typed yet are `unknown`
+fn ts_type(value: &Type, indent: usize) -> String {
+    match value {
+        Type::Null => "null".to_string(),
+        Type::Boolean => "boolean".to_string(),
+        Type::Integer | Type::Number => "number".to_string(),
+        Type::String | Type::Date | Type::DateTime => "string".to_string(),
+        Type::Literal(value) => serde_json::Value::String(value.clone()).to_string(),
+        Type::Enum(values) => values
+            .iter()
+            .map(|value| serde_json::Value::String(value.clone()).to_string())
+            .collect::<Vec<String>>()
+            .join(" | "),
+        Type::Id => "number | string".to_string(),
+        Type::Nullable(value) => format!("{} | null", ts_type(value, indent)),
+        Type::Array(item) => {
+            let rendered = ts_type(item, indent);
+            // `[]` binds tighter than `|` and `&`, objects are wrapped too as they read better
+            if matches!(**item, Type::Object(_))
+                || rendered.contains(" | ")
+                || rendered.contains(" & ")
+            {
+                format!("Array<{}>", rendered)
+            } else {
+                format!("{}[]", rendered)
+            }
+        }
+        Type::Object(object) => fields(object, indent),
+        Type::AllOf(types) => types
+            .iter()
+            .map(|t| ts_type(t, indent))
+            .collect::<Vec<String>>()
+            .join(" & "),
+        Type::OneOf(types) => types
+            .iter()
+            .map(|t| ts_type(t, indent))
+            .collect::<Vec<String>>()
+            .join(" | "),
+        Type::Constrained(value, _) => ts_type(value, indent),
+        Type::Unknown | Type::Expression(_) | Type::Partial { .. } => "unknown".to_string(),
+    }
+}
+
+fn response_interface(endpoint: &Endpoint, name: &str, out: &mut String) -> std::fmt::Result {
+    match endpoint.success_body() {
+        Some(Type::Object(object)) => writeln!(
+            out,
+            "export interface {}Response {}",
+            name,
+            fields(object, 0)
+        )?,
+        Some(view) => writeln!(out, "export type {}Response = {};", name, ts_type(view, 0))?,
+        // nothing but a status and headers e.g. `head :no_content`
+        None if !endpoint.method.returns.is_empty()
+            && endpoint.method.returns.iter().all(|r| !r.has_body()) =>
+        {
+            writeln!(out, "export type {}Response = void;", name)?
+        }
+        None => {
+            writeln!(out, "export interface {}Response {{", name)?;
+            writeln!(out, "  [key: string]: unknown;")?;
+            writeln!(out, "}}")?;
+        }
+    }
+    writeln!(out)
+}
+
+/// path params are pulled out to build the uri, the rest go in the query string or body
+fn client_function(endpoint: &Endpoint, name: &str) -> String {
+    let path_params = endpoint.request.path_params();
+    let local = |name: &str| {
+        if is_identifier(name) {
+            name.to_string()
+        } else {
+            format!("param_{}", pascal_case(name))
+        }
+    };
+    let path = endpoint
+        .request
+        .map_path_params(|name| format!("${{{}}}", local(name)));
+
+    let location = if endpoint.request.method.has_body() {
+        ParamLocation::Body
+    } else {
+        ParamLocation::Query
+    };
+    let has_rest = endpoint
+        .params
+        .iter()
+        .any(|param| param.location == location);
+    let (args, request) = if path_params.is_empty() {
+        ("params".to_string(), format!("{{ {}: params }}", location))
+    } else {
+        let mut names = path_params
+            .iter()
+            .map(|name| match local(name) {
+                local if &local == name => local,
+                local => format!("{}: {}", property(name), local),
+            })
+            .collect::<Vec<String>>();
+        if has_rest {
+            names.push("...params".to_string());
+        }
+        let request = if has_rest {
+            format!("{{ {}: params }}", location)
+        } else {
+            "{}".to_string()
+        };
+        (format!("{{ {} }}", names.join(", ")), request)
+    };
+
+    format!(
+        "({args}: {name}Request) =>\n      fetcher<{name}Response>(\"{method:?}\", `{path}`, {request})",
+        args = args,
+        name = name,
+        method = endpoint.request.method,
+        path = path,
+        request = request
+    )
+}
+
+/// Generates a TypeScript module with request and response interfaces for every action
+/// and a client keyed by the rails route name
+pub fn generate(endpoints: &[Endpoint]) -> String {
+    let mut out = String::new();
+    let names = type_names(endpoints);
+    let mut seen = HashSet::new();
+    let mut client: BTreeMap<String, BTreeMap<&str, String>> = BTreeMap::new();
+
+    writeln!(
+        out,
+        "// generated by rts from the rails routes and controllers"
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+
+    for endpoint in endpoints {
+        // multiple verbs can route to the same action e.g. PUT and PATCH for update
+        let name = &names[&(
+            endpoint.request.controller.as_str(),
+            endpoint.request.action.as_str(),
+        )];
+        if seen.insert(name) {
+            request_interface(endpoint, name, &mut out).unwrap();
+            response_interface(endpoint, name, &mut out).unwrap();
+        }
+
+        client
+            .entry(route_name(endpoint, endpoints))
+            .or_default()
+            .entry(endpoint.request.method.lowercase())
+            .or_insert_with(|| client_function(endpoint, name));
+    }
+
+    writeln!(
+        out,
+        "export type Fetcher = <T>(\n  method: string,\n  path: string,\n  request: {{ query?: object; body?: object }}\n) => Promise<T>;"
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "export const createClient = (fetcher: Fetcher) => ({{").unwrap();
+    for (name, methods) in &client {
+        writeln!(out, "  {}: {{", property(name)).unwrap();
+        for (method, function) in methods {
+            writeln!(out, "    {}: {},", method, function).unwrap();
+        }
+        writeln!(out, "  }},").unwrap();
+    }
+    writeln!(out, "}});").unwrap();
+
+    out
+}
+
+#[cfg(test)]
+mod typescript_tests {
+    use lib_ruby_parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::{generate, pascal_case, property, ts_type};
+    use crate::{
+        inflection::Inflections,
+        match_routes,
+        routes::parse_routes,
+        ruby_parser::parse_file,
+        types::{Constraints, Type},
+    };
+
+    fn helper(controller: &str, routes: &str) -> String {
+        let file = parse_file(
+            Parser::new(controller.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+        )
+        .unwrap();
+        let routes = parse_routes(routes).unwrap();
+
+        generate(&match_routes(&[file], &routes, &Inflections::default()))
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(pascal_case("dogs/styles_index"), "DogsStylesIndex");
+        assert_eq!(pascal_case("dog_forms_show"), "DogFormsShow");
+        assert_eq!(property("dog_id"), "dog_id");
+        assert_eq!(property("content-type"), "\"content-type\"");
+        // JSON escapes, not rust's `\u{1}`
+        assert_eq!(property("tab\u{1}"), "\"tab\\u0001\"");
+    }
+
+    #[test]
+    fn client() {
+        let controller = "
+        class DogFormsController < ApplicationController
+            def show
+                @name = params[:name]
+                render json: @name
+            end
+
+            def update
+                @form = DogForm.first
+                @form.update(params.require(:dog_form).permit(:name, tags: [], owner: [:email]))
+                render json: @form
+            end
+        end
+        ";
+        let routes = "Prefix Verb    URI Pattern                 Controller#Action
+            dog_form GET     /dogs/:dog_id/form(.:format)       dog_forms#show
+                     PATCH   /dogs/:dog_id/form(.:format)       dog_forms#update
+                     PUT     /dogs/:dog_id/form(.:format)       dog_forms#update
+        ";
+
+        assert_eq!(
+            helper(controller, routes),
+            r#"// generated by rts from the rails routes and controllers
+
+export interface DogFormsShowRequest {
+  dog_id: string;
+  name?: string;
+}
+
+export interface DogFormsShowResponse {
+  [key: string]: unknown;
+}
+
+export interface DogFormsUpdateRequest {
+  dog_id: string;
+  dog_form: {
+    name?: string;
+    tags?: string[];
+    owner?: {
+      email?: string;
+    };
+  };
+}
+
+export interface DogFormsUpdateResponse {
+  [key: string]: unknown;
+}
+
+export type Fetcher = <T>(
+  method: string,
+  path: string,
+  request: { query?: object; body?: object }
+) => Promise<T>;
+
+export const createClient = (fetcher: Fetcher) => ({
+  dog_form: {
+    get: ({ dog_id, ...params }: DogFormsShowRequest) =>
+      fetcher<DogFormsShowResponse>("GET", `/dogs/${dog_id}/form`, { query: params }),
+    patch: ({ dog_id, ...params }: DogFormsUpdateRequest) =>
+      fetcher<DogFormsUpdateResponse>("PATCH", `/dogs/${dog_id}/form`, { body: params }),
+    put: ({ dog_id, ...params }: DogFormsUpdateRequest) =>
+      fetcher<DogFormsUpdateResponse>("PUT", `/dogs/${dog_id}/form`, { body: params }),
+  },
+});
+"#
+        );
+    }
+
+    #[test]
+    fn colliding_names() {
+        let controller = "
+        class Admin::DogsController < ApplicationController
+            def index
+                head :ok
+            end
+        end
+
+        class AdminDogsController < ApplicationController
+            def index
+                head :ok
+            end
+        end
+        ";
+        let routes = "Prefix Verb    URI Pattern                 Controller#Action
+          admin_dogs GET     /admin/dogs(.:format)       admin/dogs#index
+         legacy_dogs GET     /admin_dogs(.:format)       admin_dogs#index
+        ";
+
+        let out = helper(controller, routes);
+        assert!(out.contains("export type AdminDogsIndexResponse = void;"));
+        assert!(out.contains("export type AdminDogsIndex2Response = void;"));
+        assert!(out.contains(
+            "fetcher<AdminDogsIndexResponse>(\"GET\", `/admin/dogs`, { query: params })"
+        ));
+        assert!(out.contains(
+            "fetcher<AdminDogsIndex2Response>(\"GET\", `/admin_dogs`, { query: params })"
+        ));
+    }
+
+    #[test]
+    fn view_types() {
+        let view = Type::Array(Box::new(Type::Object(vec![
+            ("id".to_string(), Type::Integer),
+            (
+                "owner".to_string(),
+                Type::Object(vec![("first-name".to_string(), Type::String)]),
+            ),
+            ("tags".to_string(), Type::Array(Box::new(Type::String))),
+            (
+                "name".to_string(),
+                Type::Expression("@dogs[].name".to_string()),
+            ),
+        ])));
+
+        assert_eq!(
+            ts_type(&view, 0),
+            r#"Array<{
+  id: number;
+  owner: {
+    "first-name": string;
+  };
+  tags: string[];
+  name: unknown;
+}>"#
+        );
+    }
+
+    #[test]
+    fn array_items() {
+        let array = |item: Type| ts_type(&Type::Array(Box::new(item)), 0);
+
+        assert_eq!(array(Type::String), "string[]");
+        assert_eq!(array(Type::Id), "Array<number | string>");
+        assert_eq!(
+            array(Type::Constrained(
+                Box::new(Type::Enum(vec!["a".to_string(), "b".to_string()])),
+                Constraints::default()
+            )),
+            r#"Array<"a" | "b">"#
+        );
+        assert_eq!(
+            array(Type::Constrained(
+                Box::new(Type::Nullable(Box::new(Type::Integer))),
+                Constraints::default()
+            )),
+            "Array<number | null>"
+        );
+        assert_eq!(
+            array(Type::Array(Box::new(Type::Id))),
+            "Array<Array<number | string>>"
+        );
+    }
+}
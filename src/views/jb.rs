@@ -0,0 +1,281 @@
+use lib_ruby_parser::{nodes, Node, Parser};
+
+use super::{
+    block_args, expression, item, key, option, partial_local, partial_locals, value_type, Env,
+};
+use crate::{diagnostics::Diagnostic, types::Type};
+
+fn add_field(object: &mut Type, name: String, field: Type) {
+    match object {
+        Type::Object(fields) => {
+            fields.retain(|(existing, _)| existing != &name);
+            fields.push((name, field));
+        }
+        Type::AllOf(types) => types.push(Type::Object(vec![(name, field)])),
+        _ => {}
+    }
+}
+
+/// `render "dogs/dog", dog: dog` and `render partial: "dogs/dog", collection: @dogs, as: :dog`
+fn render(args: &[Node], env: &Env) -> Type {
+    let name = match args
+        .first()
+        .and_then(key)
+        .or_else(|| option(args, "partial").and_then(key))
+    {
+        Some(name) => name,
+        None => return Type::Unknown,
+    };
+    let mut locals = partial_locals(args, env);
+    let local = option(args, "as")
+        .and_then(key)
+        .unwrap_or_else(|| partial_local(&name));
+
+    if let Some(collection) = option(args, "collection") {
+        if let Some(item) = item(collection, env) {
+            locals.push((local, item));
+        }
+        Type::Array(Box::new(Type::Partial { name, locals }))
+    } else {
+        if let Some(object) = option(args, "object").and_then(|object| expression(object, env)) {
+            locals.push((local, object));
+        }
+        Type::Partial { name, locals }
+    }
+}
+
+// jb templates are plain ruby so the response is whatever the last expression evaluates to
+#[derive(Default)]
+struct Template {
+    // hashes built up in local variables before being returned e.g. `json = {}; json[:id] = 1; json`
+    locals: Vec<(String, Type)>,
+}
+
+impl Template {
+    fn local(&mut self, name: &str) -> Option<&mut Type> {
+        self.locals
+            .iter_mut()
+            .rev()
+            .find(|(local, _)| local == name)
+            .map(|(_, value)| value)
+    }
+
+    fn statements(&mut self, node: &Node, env: &Env) -> Type {
+        match node {
+            Node::Begin(nodes::Begin { statements, .. })
+            | Node::KwBegin(nodes::KwBegin { statements, .. }) => {
+                let mut last = Type::Null;
+                for statement in statements {
+                    last = self.statements(statement, env);
+                }
+                last
+            }
+            Node::Lvasgn(nodes::Lvasgn {
+                name,
+                value: Some(value),
+                ..
+            }) => {
+                let value = self.value(value, env);
+                self.locals.push((name.clone(), value.clone()));
+                value
+            }
+            Node::IndexAsgn(nodes::IndexAsgn {
+                recv,
+                indexes,
+                value: Some(value),
+                ..
+            }) => {
+                let value = self.value(value, env);
+                if let (Node::Lvar(lvar), Some(name)) = (&**recv, indexes.first().and_then(key)) {
+                    if let Some(object) = self.local(&lvar.name) {
+                        add_field(object, name, value.clone());
+                    }
+                }
+                value
+            }
+            Node::Send(send) if send.method_name == "merge!" => {
+                let merged = send
+                    .args
+                    .first()
+                    .map(|arg| self.value(arg, env))
+                    .unwrap_or(Type::Unknown);
+                if let Some(Node::Lvar(lvar)) = send.recv.as_deref() {
+                    if let Some(object) = self.local(&lvar.name) {
+                        let existing = std::mem::replace(object, Type::Unknown);
+                        *object = Type::AllOf(vec![existing, merged]);
+                        return object.clone();
+                    }
+                }
+                Type::Unknown
+            }
+            node => self.value(node, env),
+        }
+    }
+
+    fn value(&mut self, node: &Node, env: &Env) -> Type {
+        match node {
+            // `json.merge!(good_boy: true)` passes a hash as keyword arguments
+            Node::Hash(nodes::Hash { pairs, .. }) | Node::Kwargs(nodes::Kwargs { pairs, .. }) => {
+                let mut types = Vec::new();
+                let mut fields = Vec::new();
+                for pair in pairs {
+                    match pair {
+                        Node::Pair(pair) => {
+                            if let Some(name) = key(&pair.key) {
+                                fields.push((name, self.value(&pair.value, env)));
+                            }
+                        }
+                        Node::Kwsplat(splat) => types.push(self.value(&splat.value, env)),
+                        _ => {}
+                    }
+                }
+
+                if types.is_empty() {
+                    Type::Object(fields)
+                } else {
+                    types.push(Type::Object(fields));
+                    Type::AllOf(types)
+                }
+            }
+            Node::Array(array) => Type::Array(Box::new(
+                array
+                    .elements
+                    .first()
+                    .map(|element| self.value(element, env))
+                    .unwrap_or(Type::Unknown),
+            )),
+            // `@dogs.map { |dog| { id: dog.id } }`
+            Node::Block(block) => match &*block.call {
+                Node::Send(nodes::Send {
+                    recv: Some(recv),
+                    method_name,
+                    ..
+                }) if method_name == "map" || method_name == "collect" => {
+                    let mut env = env.clone();
+                    if let (Some(local), Some(item)) =
+                        (block_args(block).into_iter().next(), item(recv, &env))
+                    {
+                        env.push((local, item));
+                    }
+                    let element = match &block.body {
+                        Some(body) => self.statements(body, &env),
+                        None => Type::Null,
+                    };
+                    Type::Array(Box::new(element))
+                }
+                _ => Type::Unknown,
+            },
+            Node::Send(send) if send.recv.is_none() && send.method_name == "render" => {
+                render(&send.args, env)
+            }
+            Node::Send(nodes::Send {
+                recv: Some(recv),
+                method_name,
+                args,
+                ..
+            }) if method_name == "merge" => {
+                let mut types = vec![self.value(recv, env)];
+                types.extend(args.iter().map(|arg| self.value(arg, env)));
+                Type::AllOf(types)
+            }
+            Node::Lvar(lvar) => match self.local(&lvar.name) {
+                Some(local) => local.clone(),
+                None => value_type(node, env),
+            },
+            Node::If(nodes::If {
+                if_true, if_false, ..
+            }) => match if_true.as_deref().or(if_false.as_deref()) {
+                Some(branch) => self.statements(branch, env),
+                None => Type::Null,
+            },
+            Node::Begin(_) | Node::KwBegin(_) => self.statements(node, env),
+            node => value_type(node, env),
+        }
+    }
+}
+
+/// Works out the shape of the JSON a jb template returns
+pub fn parse_jb(input: &str) -> Result<Type, Vec<Diagnostic>> {
+    let parsed = Parser::new(input.as_bytes(), Default::default()).do_parse();
+    match parsed.ast {
+        Some(ast) => Ok(Template::default().statements(&ast, &Env::new()).flatten()),
+        None if input.trim().is_empty() => Ok(Type::Null),
+        None => Err(parsed.diagnostics.iter().map(Diagnostic::parser).collect()),
+    }
+}
+
+#[cfg(test)]
+mod jb_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::parse_jb;
+    use crate::types::Type;
+
+    fn field(name: &str, field: Type) -> (String, Type) {
+        (name.to_string(), field)
+    }
+
+    fn expr(expression: &str) -> Type {
+        Type::Expression(expression.to_string())
+    }
+
+    #[test]
+    fn hashes() {
+        let input = "
+        json = {
+            id: @dog.id,
+            name: @dog.name,
+            legs: 4,
+            toys: @dog.toys.map { |toy| { name: toy.name } },
+            owner: render('owners/owner', owner: @dog.owner)
+        }
+        json[:good_boy] = @dog.good?
+        json.merge!(kind: 'dog')
+        json
+        ";
+
+        assert_eq!(
+            parse_jb(input),
+            Ok(Type::Object(vec![
+                field("id", expr("@dog.id")),
+                field("name", expr("@dog.name")),
+                field("legs", Type::Integer),
+                field(
+                    "toys",
+                    Type::Array(Box::new(Type::Object(vec![field(
+                        "name",
+                        expr("@dog.toys[].name")
+                    )])))
+                ),
+                field(
+                    "owner",
+                    Type::Partial {
+                        name: "owners/owner".to_string(),
+                        locals: vec![("owner".to_string(), "@dog.owner".to_string())],
+                    }
+                ),
+                field("good_boy", Type::Boolean),
+                field("kind", Type::String),
+            ]))
+        );
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!(
+            parse_jb("@dogs.map { |dog| render 'dogs/dog', dog: dog }"),
+            Ok(Type::Array(Box::new(Type::Partial {
+                name: "dogs/dog".to_string(),
+                locals: vec![("dog".to_string(), "@dogs[]".to_string())],
+            })))
+        );
+
+        assert_eq!(
+            parse_jb("render partial: 'dogs/dog', collection: @dogs, as: :pet"),
+            Ok(Type::Array(Box::new(Type::Partial {
+                name: "dogs/dog".to_string(),
+                locals: vec![("pet".to_string(), "@dogs[]".to_string())],
+            })))
+        );
+    }
+}
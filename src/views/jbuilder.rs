@@ -0,0 +1,428 @@
+use lib_ruby_parser::{nodes, Node, Parser};
+
+use super::{
+    block_args, expression, item, key, option, partial_local, partial_locals, value_type, Env,
+};
+use crate::{diagnostics::Diagnostic, inflection::singularize, types::Type};
+
+fn is_json(recv: &Option<Box<Node>>) -> bool {
+    match recv.as_deref() {
+        Some(Node::Send(send)) => send.recv.is_none() && send.method_name == "json",
+        Some(Node::Lvar(lvar)) => lvar.name == "json",
+        _ => false,
+    }
+}
+
+fn positional(args: &[Node]) -> impl Iterator<Item = &Node> {
+    args.iter()
+        .filter(|arg| !matches!(arg, Node::Kwargs(_) | Node::Hash(_)))
+}
+
+#[derive(Default)]
+struct Builder {
+    fields: Vec<(String, Type)>,
+    // partials and hashes merged into the object being built
+    merged: Vec<Type>,
+    // set when the template renders an array instead of an object
+    root: Option<Type>,
+}
+
+impl Builder {
+    fn build(body: Option<&Node>, env: &Env) -> Type {
+        let mut builder = Builder::default();
+        if let Some(body) = body {
+            builder.statements(body, env);
+        }
+        builder.finish()
+    }
+
+    fn finish(self) -> Type {
+        if let Some(root) = self.root {
+            return root;
+        }
+        if self.merged.is_empty() {
+            return Type::Object(self.fields);
+        }
+
+        let mut types = self.merged;
+        if !self.fields.is_empty() {
+            types.push(Type::Object(self.fields));
+        }
+        if types.len() == 1 {
+            types.pop().unwrap()
+        } else {
+            Type::AllOf(types)
+        }
+    }
+
+    fn set(&mut self, name: String, field: Type) {
+        self.fields.retain(|(existing, _)| existing != &name);
+        self.fields.push((name, field));
+    }
+
+    fn statements(&mut self, node: &Node, env: &Env) {
+        match node {
+            Node::Begin(nodes::Begin { statements, .. })
+            | Node::KwBegin(nodes::KwBegin { statements, .. }) => {
+                for statement in statements {
+                    self.statements(statement, env);
+                }
+            }
+            Node::Send(send) if is_json(&send.recv) => self.json(send, None, env),
+            Node::Block(block) => {
+                if let Node::Send(send) = &*block.call {
+                    if is_json(&send.recv) {
+                        self.json(send, Some(block), env);
+                    }
+                }
+            }
+            // every branch could be rendered so all of their fields are kept
+            Node::If(nodes::If {
+                if_true, if_false, ..
+            })
+            | Node::IfMod(nodes::IfMod {
+                if_true, if_false, ..
+            }) => {
+                for branch in [if_true, if_false].iter().copied().flatten() {
+                    self.statements(branch, env);
+                }
+            }
+            Node::Case(case) => {
+                for when in &case.when_bodies {
+                    if let Node::When(nodes::When {
+                        body: Some(body), ..
+                    }) = when
+                    {
+                        self.statements(body, env);
+                    }
+                }
+                if let Some(body) = &case.else_body {
+                    self.statements(body, env);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn json(&mut self, send: &nodes::Send, block: Option<&nodes::Block>, env: &Env) {
+        let args = &send.args;
+        let body = block.and_then(|block| block.body.as_deref());
+
+        match send.method_name.as_str() {
+            "extract!" | "call" => {
+                let mut args = positional(args);
+                let object = args.next().and_then(|object| expression(object, env));
+                for name in args.filter_map(key) {
+                    let field = match &object {
+                        Some(object) => Type::Expression(format!("{}.{}", object, name)),
+                        None => Type::Unknown,
+                    };
+                    self.set(name, field);
+                }
+            }
+            "array!" => {
+                let collection = positional(args).next();
+                let item = collection.and_then(|collection| item(collection, env));
+                let element = self.element(args, block, item, env);
+                self.root = Some(Type::Array(Box::new(element)));
+            }
+            "partial!" => {
+                let name = positional(args)
+                    .next()
+                    .or_else(|| option(args, "partial"))
+                    .and_then(key);
+                if let Some(name) = name {
+                    let mut locals = partial_locals(args, env);
+                    let local = option(args, "as")
+                        .and_then(key)
+                        .unwrap_or_else(|| partial_local(&name));
+
+                    if let Some(collection) = option(args, "collection") {
+                        if let Some(item) = item(collection, env) {
+                            locals.push((local, item));
+                        }
+                        self.root = Some(Type::Array(Box::new(Type::Partial { name, locals })));
+                    } else {
+                        if let Some(object) =
+                            option(args, "object").and_then(|object| expression(object, env))
+                        {
+                            locals.push((local, object));
+                        }
+                        self.merged.push(Type::Partial { name, locals });
+                    }
+                }
+            }
+            "set!" => {
+                let mut positional = positional(args);
+                if let Some(name) = positional.next().and_then(key) {
+                    let field = self.field(&name, positional.collect(), args, block, env);
+                    self.set(name, field);
+                }
+            }
+            "merge!" => {
+                if let Some(hash) = args.first() {
+                    if let object @ Type::Object(_) = value_type(hash, env) {
+                        self.merged.push(object);
+                    }
+                }
+            }
+            "cache!" | "cache_if!" | "cache_root!" => {
+                if let Some(body) = body {
+                    self.statements(body, env);
+                }
+            }
+            "key_format!" | "deep_format_keys!" | "ignore_nil!" | "nil!" | "null!" | "child!"
+            | "attributes!" | "target!" => {}
+            name => {
+                let field = self.field(name, positional(args).collect(), args, block, env);
+                self.set(name.to_string(), field);
+            }
+        }
+    }
+
+    /// what a single element of an array looks like
+    fn element(
+        &self,
+        args: &[Node],
+        block: Option<&nodes::Block>,
+        item: Option<String>,
+        env: &Env,
+    ) -> Type {
+        if let Some(block) = block {
+            let mut env = env.clone();
+            if let (Some(local), Some(item)) = (block_args(block).into_iter().next(), &item) {
+                env.push((local, item.clone()));
+            }
+            return Builder::build(block.body.as_deref(), &env);
+        }
+
+        if let Some(name) = option(args, "partial").and_then(key) {
+            let local = option(args, "as")
+                .and_then(key)
+                .unwrap_or_else(|| partial_local(&name));
+            let mut locals = partial_locals(args, env);
+            if let Some(item) = item {
+                locals.push((local, item));
+            }
+            return Type::Partial { name, locals };
+        }
+
+        let attributes = positional(args).skip(1).filter_map(key).collect::<Vec<_>>();
+        match item {
+            Some(item) if !attributes.is_empty() => Type::Object(
+                attributes
+                    .into_iter()
+                    .map(|name| {
+                        let field = Type::Expression(format!("{}.{}", item, name));
+                        (name, field)
+                    })
+                    .collect(),
+            ),
+            Some(item) => Type::Expression(item),
+            None => Type::Unknown,
+        }
+    }
+
+    /// `json.name @dog.name`, `json.owner do ... end`, `json.toys @dog.toys do |toy| ... end`
+    /// and `json.owner @dog.owner, :id, :name`
+    fn field(
+        &self,
+        name: &str,
+        positional: Vec<&Node>,
+        args: &[Node],
+        block: Option<&nodes::Block>,
+        env: &Env,
+    ) -> Type {
+        let value = match positional.first() {
+            Some(value) => *value,
+            None => {
+                return match block {
+                    Some(block) => Builder::build(block.body.as_deref(), env),
+                    None => Type::Null,
+                };
+            }
+        };
+
+        // `json.owner @dog.owner, :id` is an object where `json.toys @dog.toys, :id` is an array
+        let plural = expression(value, env)
+            .map(|value| value.rsplit('.').next().unwrap_or_default().to_string())
+            .unwrap_or_else(|| name.to_string());
+        let is_collection = block.is_some()
+            || option(args, "partial").is_some()
+            || (positional.len() > 1 && singularize(&plural) != plural);
+
+        if is_collection {
+            let item = item(value, env);
+            Type::Array(Box::new(self.element(args, block, item, env)))
+        } else if positional.len() > 1 {
+            let object = expression(value, env);
+            Type::Object(
+                positional[1..]
+                    .iter()
+                    .filter_map(|attribute| key(attribute))
+                    .map(|attribute| {
+                        let field = match &object {
+                            Some(object) => Type::Expression(format!("{}.{}", object, attribute)),
+                            None => Type::Unknown,
+                        };
+                        (attribute, field)
+                    })
+                    .collect(),
+            )
+        } else {
+            value_type(value, env)
+        }
+    }
+}
+
+/// Works out the shape of the JSON a jbuilder template renders
+pub fn parse_jbuilder(input: &str) -> Result<Type, Vec<Diagnostic>> {
+    let parsed = Parser::new(input.as_bytes(), Default::default()).do_parse();
+    match parsed.ast {
+        Some(ast) => Ok(Builder::build(Some(&ast), &Env::new()).flatten()),
+        None if input.trim().is_empty() => Ok(Type::Object(Vec::new())),
+        None => Err(parsed.diagnostics.iter().map(Diagnostic::parser).collect()),
+    }
+}
+
+#[cfg(test)]
+mod jbuilder_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::parse_jbuilder;
+    use crate::types::Type;
+
+    fn field(name: &str, field: Type) -> (String, Type) {
+        (name.to_string(), field)
+    }
+
+    fn expr(expression: &str) -> Type {
+        Type::Expression(expression.to_string())
+    }
+
+    #[test]
+    fn fields() {
+        let input = "
+        json.extract! @dog, :id, :name
+        json.(@dog, :age)
+        json.kind 'dog'
+        json.good_boy true
+        json.legs 4
+        json.url dog_url(@dog, format: :json)
+        json.created_at @dog.created_at.iso8601
+        json.owner do
+            json.name @dog.owner.name
+        end
+        json.owner_details @dog.owner, :id, :email
+        json.set! :weight, @dog.weight.to_f
+        if @dog.vet
+            json.vet @dog.vet.name
+        end
+        ";
+
+        assert_eq!(
+            parse_jbuilder(input),
+            Ok(Type::Object(vec![
+                field("id", expr("@dog.id")),
+                field("name", expr("@dog.name")),
+                field("age", expr("@dog.age")),
+                field("kind", Type::String),
+                field("good_boy", Type::Boolean),
+                field("legs", Type::Integer),
+                field("url", Type::Unknown),
+                field("created_at", Type::String),
+                field(
+                    "owner",
+                    Type::Object(vec![field("name", expr("@dog.owner.name"))])
+                ),
+                field(
+                    "owner_details",
+                    Type::Object(vec![
+                        field("id", expr("@dog.owner.id")),
+                        field("email", expr("@dog.owner.email")),
+                    ])
+                ),
+                field("weight", Type::Number),
+                field("vet", expr("@dog.vet.name")),
+            ]))
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let input = "
+        json.array! @dogs do |dog|
+            json.id dog.id
+            json.toys dog.toys, :name
+            json.friends dog.friends do |friend|
+                json.name friend.name
+            end
+        end
+        ";
+
+        assert_eq!(
+            parse_jbuilder(input),
+            Ok(Type::Array(Box::new(Type::Object(vec![
+                field("id", expr("@dogs[].id")),
+                field(
+                    "toys",
+                    Type::Array(Box::new(Type::Object(vec![field(
+                        "name",
+                        expr("@dogs[].toys[].name")
+                    )])))
+                ),
+                field(
+                    "friends",
+                    Type::Array(Box::new(Type::Object(vec![field(
+                        "name",
+                        expr("@dogs[].friends[].name")
+                    )])))
+                ),
+            ]))))
+        );
+
+        assert_eq!(
+            parse_jbuilder("json.array! @dogs, :id, :name"),
+            Ok(Type::Array(Box::new(Type::Object(vec![
+                field("id", expr("@dogs[].id")),
+                field("name", expr("@dogs[].name")),
+            ]))))
+        );
+    }
+
+    #[test]
+    fn partials() {
+        assert_eq!(
+            parse_jbuilder("json.partial! 'dogs/dog', dog: @dog"),
+            Ok(Type::Partial {
+                name: "dogs/dog".to_string(),
+                locals: vec![("dog".to_string(), "@dog".to_string())],
+            })
+        );
+
+        assert_eq!(
+            parse_jbuilder(
+                "json.partial! partial: 'dogs/dog', collection: @dogs, as: :pet
+                json.ignored true"
+            ),
+            Ok(Type::Array(Box::new(Type::Partial {
+                name: "dogs/dog".to_string(),
+                locals: vec![("pet".to_string(), "@dogs[]".to_string())],
+            })))
+        );
+
+        assert_eq!(
+            parse_jbuilder(
+                "json.partial! 'dogs/dog', object: @dog
+                json.owner @dog.owner.name"
+            ),
+            Ok(Type::AllOf(vec![
+                Type::Partial {
+                    name: "dogs/dog".to_string(),
+                    locals: vec![("dog".to_string(), "@dog".to_string())],
+                },
+                Type::Object(vec![field("owner", expr("@dog.owner.name"))]),
+            ]))
+        );
+    }
+}
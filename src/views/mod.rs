@@ -0,0 +1,368 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use lib_ruby_parser::{nodes, Node};
+use walkdir::WalkDir;
+
+use crate::{diagnostics::Diagnostic, types::Type};
+
+pub mod jb;
+pub mod jbuilder;
+
+// local variables bound by blocks e.g. `|dog|` in `json.array! @dogs do |dog|`
+pub(crate) type Env = Vec<(String, String)>;
+
+pub(crate) fn block_args(block: &nodes::Block) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(args) = &block.args {
+        if let Node::Args(args) = &**args {
+            for arg in &args.args {
+                match arg {
+                    Node::Arg(arg) => names.push(arg.name.clone()),
+                    Node::Procarg0(procarg) => {
+                        for arg in &procarg.args {
+                            if let Node::Arg(arg) = arg {
+                                names.push(arg.name.clone());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    names
+}
+
+/// the ruby expression a node reads from e.g. `@dog.owner.name`
+pub(crate) fn expression(node: &Node, env: &Env) -> Option<String> {
+    let local = |name: &str| {
+        env.iter()
+            .rev()
+            .find(|(local, _)| local == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_else(|| name.to_string())
+    };
+
+    match node {
+        Node::Ivar(ivar) => Some(ivar.name.clone()),
+        Node::Lvar(lvar) => Some(local(&lvar.name)),
+        // locals passed to a partial look like method calls as they are never assigned
+        Node::Send(send) if send.args.is_empty() => match &send.recv {
+            None => Some(local(&send.method_name)),
+            Some(recv) => {
+                expression(recv, env).map(|recv| format!("{}.{}", recv, send.method_name))
+            }
+        },
+        _ => None,
+    }
+}
+
+/// the type of a value written into the JSON
+pub(crate) fn value_type(node: &Node, env: &Env) -> Type {
+    match node {
+        Node::Str(_) | Node::Dstr(_) | Node::Sym(_) | Node::Dsym(_) | Node::Heredoc(_) => {
+            Type::String
+        }
+        Node::Int(_) => Type::Integer,
+        Node::Float(_) | Node::Rational(_) => Type::Number,
+        Node::True(_) | Node::False(_) => Type::Boolean,
+        Node::Nil(_) => Type::Null,
+        Node::Array(array) => Type::Array(Box::new(
+            array
+                .elements
+                .first()
+                .map(|element| value_type(element, env))
+                .unwrap_or(Type::Unknown),
+        )),
+        Node::Hash(hash) => Type::Object(
+            hash.pairs
+                .iter()
+                .filter_map(|pair| match pair {
+                    Node::Pair(pair) => {
+                        key(&pair.key).map(|key| (key, value_type(&pair.value, env)))
+                    }
+                    _ => None,
+                })
+                .collect(),
+        ),
+        Node::Send(send) if send.recv.is_some() && send.args.is_empty() => {
+            match send.method_name.as_str() {
+                "to_s" | "iso8601" | "to_json" | "humanize" | "titleize" => Type::String,
+                "to_i" | "count" | "size" | "length" => Type::Integer,
+                "to_f" => Type::Number,
+                name if name.ends_with('?') => Type::Boolean,
+                _ => expression(node, env)
+                    .map(Type::Expression)
+                    .unwrap_or(Type::Unknown),
+            }
+        }
+        node => expression(node, env)
+            .map(Type::Expression)
+            .unwrap_or(Type::Unknown),
+    }
+}
+
+/// a hash key or field name given as a symbol or string
+pub(crate) fn key(node: &Node) -> Option<String> {
+    match node {
+        Node::Sym(sym) => Some(sym.name.to_string_lossy()),
+        Node::Str(str) => Some(str.value.to_string_lossy()),
+        _ => None,
+    }
+}
+
+/// `partial: "dogs/dog"` given as keyword arguments
+pub(crate) fn option<'a>(args: &'a [Node], name: &str) -> Option<&'a Node> {
+    args.iter().find_map(|arg| match arg {
+        Node::Kwargs(nodes::Kwargs { pairs, .. }) | Node::Hash(nodes::Hash { pairs, .. }) => {
+            pairs.iter().find_map(|pair| match pair {
+                Node::Pair(pair) if key(&pair.key).as_deref() == Some(name) => Some(&*pair.value),
+                _ => None,
+            })
+        }
+        _ => None,
+    })
+}
+
+/// the element of a collection e.g. `@dogs[]`
+pub(crate) fn item(collection: &Node, env: &Env) -> Option<String> {
+    expression(collection, env).map(|collection| format!("{}[]", collection))
+}
+
+/// `dogs/dog` renders with a local named `dog`
+pub(crate) fn partial_local(partial: &str) -> String {
+    partial.rsplit('/').next().unwrap_or(partial).to_string()
+}
+
+/// the locals passed to a partial either under `locals:` or as the remaining keyword arguments
+pub(crate) fn partial_locals(args: &[Node], env: &Env) -> Vec<(String, String)> {
+    let pairs = match option(args, "locals") {
+        Some(Node::Hash(hash)) => hash.pairs.iter().collect::<Vec<&Node>>(),
+        _ => args
+            .iter()
+            .filter_map(|arg| match arg {
+                Node::Kwargs(kwargs) => Some(kwargs.pairs.iter()),
+                _ => None,
+            })
+            .flatten()
+            .collect(),
+    };
+
+    pairs
+        .into_iter()
+        .filter_map(|pair| match pair {
+            Node::Pair(pair) => match key(&pair.key)?.as_str() {
+                "partial" | "locals" | "object" | "collection" | "as" | "cached" => None,
+                name => Some((name.to_string(), expression(&pair.value, env)?)),
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// `dogs/dog` -> `dogs/_dog`, partials without a folder are relative to the template rendering them
+pub(crate) fn partial_path(name: &str, dir: &str) -> String {
+    let name = name.trim_start_matches('/');
+    match name.rfind('/') {
+        Some(i) => format!("{}/_{}", &name[..i], &name[i + 1..]),
+        None if dir.is_empty() => format!("_{}", name),
+        None => format!("{}/_{}", dir, name),
+    }
+}
+
+/// Every jbuilder and jb template in the views folder by its path without the extension e.g. `dogs/show` or `dogs/_dog`
+#[derive(Debug, Default)]
+pub struct Views {
+    templates: HashMap<String, Type>,
+    /// syntax errors in templates that couldn't be parsed
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Views {
+    pub fn insert(&mut self, name: &str, template: Type) {
+        self.templates.insert(name.to_string(), template);
+    }
+
+    /// Parses every view template under `path`
+    pub fn parse_dir(path: &str) -> Result<Views, Box<dyn std::error::Error>> {
+        let mut views = Views::default();
+        let root = Path::new(path);
+        for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+            let file = entry.path();
+            let relative = file
+                .strip_prefix(root)?
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            let (name, template) = if let Some(name) = relative.strip_suffix(".json.jbuilder") {
+                (name, jbuilder::parse_jbuilder(&fs::read_to_string(file)?))
+            } else if let Some(name) = relative.strip_suffix(".json.jb") {
+                (name, jb::parse_jb(&fs::read_to_string(file)?))
+            } else {
+                continue;
+            };
+
+            match template {
+                Ok(template) => views.insert(name, template),
+                Err(errors) => {
+                    let source = fs::read(file)?;
+                    for mut diagnostic in errors {
+                        diagnostic.locate(&file.display().to_string(), &source);
+                        views.diagnostics.push(diagnostic);
+                    }
+                }
+            }
+        }
+
+        Ok(views)
+    }
+
+    /// The response rendered by the implicit template `<controller>/<action>`
+    pub fn for_action(&self, controller: &str, action: &str) -> Option<Type> {
+        self.template(&format!("{}/{}", controller, action))
+    }
+
+    /// The template with each partial it renders inlined
+    pub fn template(&self, name: &str) -> Option<Type> {
+        let template = self.templates.get(name)?.clone();
+        let dir = name.rfind('/').map(|i| &name[..i]).unwrap_or("");
+        Some(
+            self.resolve(template, dir, &mut vec![name.to_string()])
+                .flatten(),
+        )
+    }
+
+    fn resolve(&self, template: Type, dir: &str, seen: &mut Vec<String>) -> Type {
+        match template {
+            Type::Partial { name, locals } => {
+                let path = partial_path(&name, dir);
+                match self.templates.get(&path) {
+                    // partials rendering themselves are left as they are
+                    Some(partial) if !seen.contains(&path) => {
+                        seen.push(path.clone());
+                        let partial_dir = path.rfind('/').map(|i| &path[..i]).unwrap_or("");
+                        let resolved = self.resolve(partial.clone(), partial_dir, seen);
+                        seen.pop();
+                        resolved.substitute(&locals)
+                    }
+                    _ => Type::Partial { name: path, locals },
+                }
+            }
+            Type::Array(item) => Type::Array(Box::new(self.resolve(*item, dir, seen))),
+            Type::Object(fields) => Type::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, field)| (name, self.resolve(field, dir, seen)))
+                    .collect(),
+            ),
+            Type::AllOf(types) => Type::AllOf(
+                types
+                    .into_iter()
+                    .map(|t| self.resolve(t, dir, seen))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod views_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{jb::parse_jb, jbuilder::parse_jbuilder, partial_path, Views};
+    use crate::{diagnostics::SYNTAX_ERROR, types::Type};
+
+    fn helper(templates: Vec<(&str, &str)>) -> Views {
+        let mut views = Views::default();
+        for (name, input) in templates {
+            views.insert(name, parse_jbuilder(input).unwrap());
+        }
+        views
+    }
+
+    #[test]
+    fn partial_paths() {
+        assert_eq!(partial_path("dogs/dog", "cats"), "dogs/_dog");
+        assert_eq!(partial_path("dog", "dogs"), "dogs/_dog");
+        assert_eq!(partial_path("api/v1/dogs/dog", ""), "api/v1/dogs/_dog");
+    }
+
+    #[test]
+    fn inline_partials() {
+        let views = helper(vec![
+            (
+                "dogs/index",
+                "json.array! @dogs, partial: 'dogs/dog', as: :dog",
+            ),
+            (
+                "dogs/show",
+                "json.partial! 'dog', dog: @dog
+                json.owner_name @dog.owner.name",
+            ),
+            (
+                "dogs/_dog",
+                "json.extract! dog, :id, :name
+                json.toys dog.toys do |toy|
+                    json.partial! 'toys/toy', toy: toy
+                end",
+            ),
+            ("toys/_toy", "json.id toy.id"),
+        ]);
+
+        let dog = |dog: &str| {
+            vec![
+                ("id".to_string(), Type::Expression(format!("{}.id", dog))),
+                (
+                    "name".to_string(),
+                    Type::Expression(format!("{}.name", dog)),
+                ),
+                (
+                    "toys".to_string(),
+                    Type::Array(Box::new(Type::Object(vec![(
+                        "id".to_string(),
+                        Type::Expression(format!("{}.toys[].id", dog)),
+                    )]))),
+                ),
+            ]
+        };
+
+        assert_eq!(
+            views.for_action("dogs", "index"),
+            Some(Type::Array(Box::new(Type::Object(dog("@dogs[]")))))
+        );
+
+        let mut show = dog("@dog");
+        show.push((
+            "owner_name".to_string(),
+            Type::Expression("@dog.owner.name".to_string()),
+        ));
+        assert_eq!(views.for_action("dogs", "show"), Some(Type::Object(show)));
+        assert_eq!(views.for_action("dogs", "update"), None);
+    }
+
+    #[test]
+    fn jb_partials() {
+        let mut views = Views::default();
+        views.insert(
+            "dogs/index",
+            parse_jb("@dogs.map { |dog| render 'dog', dog: dog }").unwrap(),
+        );
+        views.insert("dogs/_dog", parse_jb("{ id: dog.id }").unwrap());
+
+        assert_eq!(
+            views.for_action("dogs", "index"),
+            Some(Type::Array(Box::new(Type::Object(vec![(
+                "id".to_string(),
+                Type::Expression("@dogs[].id".to_string())
+            )]))))
+        );
+    }
+
+    #[test]
+    fn broken_templates() {
+        let errors = parse_jbuilder("json.extract! @dog, :id,").unwrap_err();
+        assert_eq!(errors[0].code, SYNTAX_ERROR);
+        assert_eq!(parse_jb("").unwrap(), Type::Null);
+    }
+}